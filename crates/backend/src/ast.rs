@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use literal::{self, Literal};
 use proc_macro2::Span;
 use quote::{ToTokens, Tokens};
@@ -16,12 +18,23 @@ pub struct Export {
     pub class: Option<syn::Ident>,
     pub method: bool,
     pub mutable: bool,
+    /// Whether this method takes `self` by value, consuming the object the
+    /// JS wrapper points to.
+    pub consume: bool,
     pub function: Function,
+    /// The name of the Rust function, which may differ from the JS-facing
+    /// `function.name` when `js_name` is used. Symbols are named after it,
+    /// as JS names needn't be valid identifiers.
+    pub rust_name: syn::Ident,
+    /// The error type `E` if this function returns a `Result<T, E>`, in which
+    /// case `function.ret` is `T` and an `Err` is thrown in JS.
+    pub throws: Option<Type>,
 }
 
 pub struct Import {
     pub module: Option<String>,
-    pub js_namespace: Option<syn::Ident>,
+    /// Path of objects the import is nested in, outermost first
+    pub js_namespace: Option<Vec<String>>,
     pub kind: ImportKind,
 }
 
@@ -29,6 +42,8 @@ pub enum ImportKind {
     Function(ImportFunction),
     Static(ImportStatic),
     Type(ImportType),
+    Property(ImportProperty),
+    Enum(ImportEnum),
 }
 
 pub struct ImportFunction {
@@ -41,25 +56,105 @@ pub struct ImportFunction {
 pub enum ImportFunctionKind {
     Method { class: String, ty: syn::Type },
     JsConstructor { class: String, ty: syn::Type },
+    StaticMethod { class: String, ty: syn::Type },
     Normal,
 }
 
+/// A symbol a method can be keyed by in JS rather than by its name
+pub enum JsSymbol {
+    /// One of the well-known symbols like `Symbol.iterator`
+    WellKnown(syn::Ident),
+    /// A symbol from the global registry, `Symbol.for(key)`
+    Registered(String),
+}
+
+/// Index access a method can be bound to instead of a method call
+#[derive(Copy, Clone)]
+pub enum Indexing {
+    /// `this[key]`
+    Getter,
+    /// `this[key] = value`
+    Setter,
+    /// `delete this[key]`
+    Deleter,
+}
+
+/// How an exported method takes `self`
+#[derive(Copy, Clone, PartialEq)]
+pub enum Receiver {
+    /// `&self`
+    Ref,
+    /// `&mut self`
+    RefMut,
+    /// `self`, after which the JS object no longer points to anything
+    Value,
+}
+
 pub struct ImportStatic {
     pub vis: syn::Visibility,
     pub ty: syn::Type,
     pub shim: syn::Ident,
     pub rust_name: syn::Ident,
-    pub js_name: syn::Ident,
+    pub js_name: String,
+    /// Whether the JS lookup of a value imported from a module is put off
+    /// until it's first used rather than done when the JS glue is loaded.
+    pub lazy: bool,
+}
+
+/// A data property of an imported class, read with `this.name` and written
+/// with `this.name = value` rather than through a getter and setter.
+pub struct ImportProperty {
+    pub vis: syn::Visibility,
+    pub class: String,
+    pub class_ty: syn::Type,
+    pub js_name: String,
+    pub rust_name: syn::Ident,
+    /// Type of the property's value
+    pub ty: syn::Type,
+    /// Whether only a method reading the property is generated
+    pub readonly: bool,
+    pub get_shim: syn::Ident,
+    pub set_shim: syn::Ident,
+    pub rust_attrs: Vec<syn::Attribute>,
 }
 
 pub struct ImportType {
     pub vis: syn::Visibility,
     pub name: syn::Ident,
+    /// Module the type is imported from, which is part of its descriptor.
+    pub module: Option<String>,
+    /// Whether values of this type are duck-typed in JS and passed around as
+    /// plain `JsValue`s.
+    pub structural: bool,
+    /// Whether values passed to an export are checked with `instanceof`.
+    pub instanceof: bool,
+    /// Whether this is a JS iterator, consumed in Rust through `IntoIterator`.
+    pub iterator: bool,
+    /// Imported types this one inherits from in JS, nearest first.
+    pub extends: Vec<syn::Ident>,
+    /// TypeScript type used for values of this type in the generated
+    /// definitions, rather than `any`.
+    pub typescript_type: Option<String>,
 }
 
-pub struct Function {
+/// A Rust enum whose variants stand for JS constants, declared with
+/// `#[wasm_bindgen(imported)]` on the enum itself.
+pub struct ImportEnum {
     pub name: syn::Ident,
+    /// Each variant other than `unknown` with the constant it stands for
+    pub variants: Vec<(syn::Ident, String)>,
+    /// The last variant, which values that aren't any of the constants are
+    /// converted to if the enum is `#[non_exhaustive]`
+    pub unknown: Option<syn::Ident>,
+}
+
+pub struct Function {
+    /// The name of the function in JS
+    pub name: String,
     pub arguments: Vec<Type>,
+    pub argument_names: Vec<String>,
+    /// Whether each argument may be `null`, only ever set for imports
+    pub nullable: Vec<bool>,
     pub ret: Option<Type>,
     pub opts: BindgenAttrs,
     pub rust_attrs: Vec<syn::Attribute>,
@@ -69,6 +164,23 @@ pub struct Function {
 
 pub struct Struct {
     pub name: syn::Ident,
+    pub fields: Vec<StructField>,
+    /// Extra names the JS class is also exported under, from `js_class`
+    pub js_class_aliases: Vec<syn::Ident>,
+}
+
+/// A `pub` field of an exported struct, accessed through a getter and setter
+pub struct StructField {
+    pub name: syn::Ident,
+    pub struct_name: syn::Ident,
+    /// Whether the field only gets a getter in JS
+    pub readonly: bool,
+    /// Whether the getter clones the field, rather than requiring it to be
+    /// `Copy`
+    pub getter_with_clone: bool,
+    pub ty: syn::Type,
+    pub getter: syn::Ident,
+    pub setter: syn::Ident,
 }
 
 pub struct Enum {
@@ -78,6 +190,8 @@ pub struct Enum {
 
 pub struct Variant {
     pub name: syn::Ident,
+    /// The key of the variant in the JS enum object if it isn't `name`
+    pub js_name: Option<String>,
     pub value: u32,
 }
 
@@ -104,18 +218,70 @@ impl Program {
                     }
                     _ => {}
                 }
-                f.to_tokens(tokens);
+                if opts.async_() {
+                    if opts.start() {
+                        panic!("the `start` function can't be `async`");
+                    }
+                    let syn::ItemFn { ref attrs, ref vis, ident, ref decl, ref block, .. } = f;
+                    let syn::FnDecl { ref generics, ref inputs, ref output, .. } = **decl;
+                    let where_clause = &generics.where_clause;
+                    (quote! {
+                        #(#attrs)*
+                        #vis async fn #ident #generics(#inputs) #output #where_clause #block
+                    }).to_tokens(tokens);
+                } else {
+                    f.to_tokens(tokens);
+                }
+                // A skipped function is still compiled as is, it just isn't
+                // exported to JS
+                if opts.skip() {
+                    return
+                }
+                let rust_name = f.ident;
+                let mut function = Function::from(f, opts);
+                let throws = function.extract_result();
+                function.check_borrowed_ret();
+                if function.opts.catch() && throws.is_none() {
+                    panic!("can't `catch` without returning a Result");
+                }
+                if function.opts.async_() {
+                    if function.opts.catch() {
+                        panic!("`async` functions can't `catch` panics");
+                    }
+                    let borrows = function.arguments.iter().any(|a| match *a {
+                        Type::ByValue(ref t) => is_cow_str(t) || is_option_str(t),
+                        _ => true,
+                    });
+                    if borrows {
+                        panic!("`async` functions can't take references");
+                    }
+                }
+                if function.opts.start() {
+                    if function.arguments.len() > 0 || function.ret.is_some() || throws.is_some() {
+                        panic!("the `start` function can't take arguments or return anything");
+                    }
+                    if self.exports.iter().any(|e| e.function.opts.start()) {
+                        panic!("only one function can be marked as `start`");
+                    }
+                }
+                if function.opts.constructor() {
+                    panic!("only methods in an `impl` can be constructors");
+                }
                 self.exports.push(Export {
                     class: None,
                     method: false,
                     mutable: false,
-                    function: Function::from(f, opts),
+                    consume: false,
+                    function,
+                    rust_name,
+                    throws,
                 });
             }
             syn::Item::Struct(mut s) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut s.attrs));
+                let item = Struct::from(&mut s, opts);
                 s.to_tokens(tokens);
-                self.structs.push(Struct::from(s, opts));
+                self.structs.push(item);
             }
             syn::Item::Impl(mut i) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut i.attrs));
@@ -128,8 +294,13 @@ impl Program {
             }
             syn::Item::Enum(mut e) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut e.attrs));
-                e.to_tokens(tokens);
-                self.push_enum(e, opts);
+                if opts.imported() {
+                    self.push_imported_enum(&mut e, opts);
+                    e.to_tokens(tokens);
+                } else {
+                    self.push_enum(&mut e, opts);
+                    e.to_tokens(tokens);
+                }
             }
             _ => panic!(
                 "#[wasm_bindgen] can only be applied to a function, \
@@ -189,33 +360,72 @@ impl Program {
         }
 
         let opts = BindgenAttrs::find(&mut method.attrs);
+        if opts.skip() {
+            return
+        }
+        let js_name = opts.js_name().unwrap_or(method.sig.ident.to_string());
 
-        let (function, mutable) = Function::from_decl(
-            method.sig.ident,
+        let (mut function, receiver) = Function::from_decl(
+            js_name,
             Box::new(method.sig.decl),
             method.attrs,
             opts,
             method.vis,
             true,
         );
+        let throws = function.extract_result();
+        function.check_borrowed_ret();
+        function.check_no_nullable();
+        if function.opts.catch() && throws.is_none() {
+            panic!("can't `catch` without returning a Result");
+        }
+        if function.opts.start() {
+            panic!("only free functions can be marked as `start`");
+        }
+        if function.opts.constructor() {
+            if receiver.is_some() {
+                panic!("constructors can't take `self`");
+            }
+            let returns_class = match function.ret {
+                Some(Type::ByValue(syn::Type::Path(syn::TypePath {
+                    qself: None,
+                    ref path,
+                }))) => extract_path_ident(path) == Some(class),
+                _ => false,
+            };
+            if !returns_class {
+                panic!("constructors must return `{}`", class);
+            }
+            let exists = self.exports.iter().any(|e| {
+                e.class == Some(class) && e.function.opts.constructor()
+            });
+            if exists {
+                panic!("only one method can be the constructor of `{}`", class);
+            }
+        }
         self.exports.push(Export {
             class: Some(class),
-            method: mutable.is_some(),
-            mutable: mutable.unwrap_or(false),
+            method: receiver.is_some(),
+            mutable: receiver == Some(Receiver::RefMut),
+            consume: receiver == Some(Receiver::Value),
             function,
+            rust_name: method.sig.ident,
+            throws,
         });
     }
 
-    pub fn push_enum(&mut self, item: syn::ItemEnum, _opts: BindgenAttrs) {
+    pub fn push_enum(&mut self, item: &mut syn::ItemEnum, _opts: BindgenAttrs) {
         match item.vis {
             syn::Visibility::Public(_) => {}
             _ => panic!("only public enums are allowed"),
         }
 
+        // Like in Rust a variant without an explicit value is one more than
+        // the previous variant's.
+        let mut next = Some(0u32);
         let variants = item.variants
-            .iter()
-            .enumerate()
-            .map(|(i, v)| {
+            .iter_mut()
+            .map(|v| {
                 match v.fields {
                     syn::Fields::Unit => (),
                     _ => panic!("Only C-Style enums allowed"),
@@ -233,22 +443,89 @@ impl Program {
                         }
                         int_lit.value() as u32
                     }
-                    None => i as u32,
+                    None => {
+                        next.expect("Enums can only support numbers that can be represented as u32")
+                    }
                     _ => panic!("Enums may only have number literal values"),
                 };
+                next = value.checked_add(1);
 
+                let variant_opts = BindgenAttrs::find(&mut v.attrs);
                 Variant {
                     name: v.ident,
+                    js_name: variant_opts.js_name(),
                     value,
                 }
             })
-            .collect();
+            .collect::<Vec<_>>();
+        let js_name = |v: &Variant| {
+            v.js_name.clone().unwrap_or(v.name.to_string())
+        };
+        for (i, v) in variants.iter().enumerate() {
+            let clash = variants[..i].iter().any(|prev| js_name(prev) == js_name(v));
+            if clash {
+                panic!("more than one variant of `{}` is named `{}` in JS",
+                       item.ident, js_name(v));
+            }
+        }
         self.enums.push(Enum {
             name: item.ident,
             variants,
         });
     }
 
+    /// Variants of an imported enum can't have values of their own, as the
+    /// values are only known in JS. A `#[non_exhaustive]` enum needs a last
+    /// variant marked `unknown` for any other values JS passes.
+    pub fn push_imported_enum(&mut self, item: &mut syn::ItemEnum, opts: BindgenAttrs) {
+        match item.vis {
+            syn::Visibility::Public(_) => {}
+            _ => panic!("only public enums are allowed"),
+        }
+        let non_exhaustive = item.attrs.iter().any(|a| {
+            a.interpret_meta().map(|m| m.name() == "non_exhaustive").unwrap_or(false)
+        });
+        let mut variants = Vec::new();
+        let mut unknown = None;
+        let count = item.variants.len();
+        for (i, v) in item.variants.iter_mut().enumerate() {
+            match v.fields {
+                syn::Fields::Unit => (),
+                _ => panic!("Only C-Style enums allowed"),
+            }
+            if v.discriminant.is_some() {
+                panic!("variants of imported enums can't have values, they're \
+                        the JS constants named by `js_name`");
+            }
+            let variant_opts = BindgenAttrs::find(&mut v.attrs);
+            if variant_opts.unknown() {
+                if !non_exhaustive {
+                    panic!("only `#[non_exhaustive]` enums can have an `unknown` variant");
+                }
+                if i != count - 1 {
+                    panic!("the `unknown` variant must be the last one");
+                }
+                unknown = Some(v.ident);
+            } else {
+                let js_name = variant_opts.js_name().unwrap_or(v.ident.to_string());
+                variants.push((v.ident, js_name));
+            }
+        }
+        if non_exhaustive && unknown.is_none() {
+            panic!("`#[non_exhaustive]` imported enums need a last variant \
+                    marked `unknown`");
+        }
+        self.imports.push(Import {
+            module: opts.module().map(|s| s.to_string()),
+            js_namespace: opts.js_namespace(),
+            kind: ImportKind::Enum(ImportEnum {
+                name: item.ident,
+                variants,
+                unknown,
+            }),
+        });
+    }
+
     pub fn push_foreign_mod(&mut self, f: syn::ItemForeignMod, opts: BindgenAttrs) {
         match f.abi.name {
             Some(ref l) if l.value() == "C" => {}
@@ -267,9 +544,17 @@ impl Program {
             };
             let module = item_opts.module().or(opts.module()).map(|s| s.to_string());
             let js_namespace = item_opts.js_namespace().or(opts.js_namespace());
+            if item_opts.late_bound() && module.is_some() {
+                panic!("`late_bound` functions are looked up on the global object \
+                        so they can't come from a `module`");
+            }
             let mut kind = match item {
-                syn::ForeignItem::Fn(f) => self.push_foreign_fn(f, item_opts),
-                syn::ForeignItem::Type(t) => self.push_foreign_ty(t),
+                syn::ForeignItem::Fn(f) => {
+                    self.push_foreign_fn(f, item_opts, js_namespace.as_ref())
+                }
+                syn::ForeignItem::Type(t) => {
+                    self.push_foreign_ty(t, item_opts, module.as_ref())
+                }
                 syn::ForeignItem::Static(s) => self.push_foreign_static(s, item_opts),
                 _ => panic!("only foreign functions/types allowed for now"),
             };
@@ -280,16 +565,68 @@ impl Program {
                 kind,
             });
         }
+        self.check_writeonly_properties();
     }
 
-    pub fn push_foreign_fn(&mut self, f: syn::ForeignItemFn, opts: BindgenAttrs) -> ImportKind {
-        let js_name = opts.js_name().unwrap_or(f.ident);
-        let mut wasm = Function::from_decl(js_name, f.decl, f.attrs, opts, f.vis, false).0;
+    /// Panics if a property bound by a `writeonly` setter also has a getter
+    /// bound anywhere else in this program.
+    fn check_writeonly_properties(&self) {
+        let mut getters = HashSet::new();
+        let mut setters = Vec::new();
+        for import in self.imports.iter() {
+            match import.kind {
+                ImportKind::Function(ref f) => {
+                    let class = match f.kind {
+                        ImportFunctionKind::Method { ref class, .. } |
+                        ImportFunctionKind::StaticMethod { ref class, .. } => Some(class.clone()),
+                        _ => None,
+                    };
+                    if let Some(g) = f.function.opts.getter() {
+                        let name = g.map(|g| g.to_string())
+                            .unwrap_or_else(|| f.infer_getter_property());
+                        getters.insert((class, name));
+                    } else if let Some(s) = f.function.opts.setter() {
+                        if f.function.opts.writeonly() {
+                            let name = s.map(|s| s.to_string())
+                                .unwrap_or_else(|| f.infer_setter_property());
+                            setters.push((class, name));
+                        }
+                    }
+                }
+                ImportKind::Property(ref p) => {
+                    getters.insert((Some(p.class.clone()), p.js_name.clone()));
+                }
+                _ => {}
+            }
+        }
+        for key in setters {
+            if getters.contains(&key) {
+                let (class, name) = key;
+                match class {
+                    Some(class) => panic!("`{}.{}` is `writeonly` but also has a getter", class, name),
+                    None => panic!("`{}` is `writeonly` but also has a getter", name),
+                }
+            }
+        }
+    }
+
+    pub fn push_foreign_fn(
+        &mut self,
+        f: syn::ForeignItemFn,
+        opts: BindgenAttrs,
+        js_namespace: Option<&Vec<String>>,
+    ) -> ImportKind {
+        if opts.property() {
+            return self.push_foreign_property(f, opts)
+        }
+        let js_name = opts.js_name().unwrap_or(f.ident.to_string());
+        let mut wasm = Function::from_decl(js_name.clone(), f.decl, f.attrs, opts, f.vis,
+                                           false).0;
         if wasm.opts.catch() {
             // TODO: this assumes a whole bunch:
             //
             // * The outer type is actually a `Result`
-            // * The error type is a `JsValue`
+            // * The error type implements `FromCaughtException`
             // * The actual type is the first type parameter
             //
             // should probably fix this one day...
@@ -297,6 +634,27 @@ impl Program {
                 .expect("can't `catch` without returning a Result");
         }
 
+        if wasm.opts.variadic() {
+            // Only `JsValue`s can be spread out as they are, typed arrays
+            // would be passed as one argument
+            let ok = match wasm.arguments.last() {
+                Some(&Type::ByRef(syn::Type::Slice(ref s))) => {
+                    match *s.elem {
+                        syn::Type::Path(ref p) => {
+                            p.path.segments.last()
+                                .map(|s| s.value().ident.as_ref() == "JsValue")
+                                .unwrap_or(false)
+                        }
+                        _ => false,
+                    }
+                }
+                _ => false,
+            };
+            if !ok {
+                panic!("the last argument of a variadic import must be `&[JsValue]`")
+            }
+        }
+
         let kind = if wasm.opts.method() {
             let class = wasm.arguments
                 .get(0)
@@ -338,17 +696,130 @@ impl Program {
                 class: class_name.as_ref().to_string(),
                 ty: class.clone(),
             }
+        } else if let Some(class) = wasm.opts.static_method_of() {
+            ImportFunctionKind::StaticMethod {
+                class: class.as_ref().to_string(),
+                ty: syn::Type::Path(syn::TypePath {
+                    qself: None,
+                    path: class.into(),
+                }),
+            }
         } else {
             ImportFunctionKind::Normal
         };
 
+        if wasm.opts.symbol().is_some() {
+            match kind {
+                ImportFunctionKind::Method { .. } |
+                ImportFunctionKind::StaticMethod { .. } => {}
+                _ => panic!("only methods can be keyed by a symbol"),
+            }
+        }
+
+        if wasm.opts.final_() {
+            match kind {
+                ImportFunctionKind::Method { .. } => {}
+                _ => panic!("only methods can be `final`"),
+            }
+            if wasm.opts.structural() {
+                panic!("`final` methods can't also be `structural`");
+            }
+        }
+
+        // A setter is only ever called for its side effect
+        if wasm.opts.pure() && wasm.opts.setter().is_some() {
+            panic!("setters can't be `pure`");
+        }
+
+        if let Some(indexing) = wasm.opts.indexing() {
+            match kind {
+                ImportFunctionKind::Method { .. } => {}
+                _ => panic!("indexing getters, setters and deleters must be methods"),
+            }
+            let (what, args) = match indexing {
+                Indexing::Getter => ("getters", 2),
+                Indexing::Setter => ("setters", 3),
+                Indexing::Deleter => ("deleters", 2),
+            };
+            if wasm.arguments.len() != args {
+                panic!("indexing {} must take {} arguments including `this`", what, args);
+            }
+        }
+
+        if wasm.opts.late_bound() {
+            match kind {
+                ImportFunctionKind::Normal => {}
+                _ => panic!("only free functions can be `late_bound`"),
+            }
+            if wasm.opts.getter().is_some() || wasm.opts.setter().is_some() {
+                panic!("getters and setters can't be `late_bound`");
+            }
+        }
+
+        if wasm.opts.this_arg() {
+            match kind {
+                ImportFunctionKind::Normal => {}
+                _ => panic!("only free functions can take a `this_arg`"),
+            }
+            if wasm.opts.getter().is_some() || wasm.opts.setter().is_some() {
+                panic!("getters and setters can't take a `this_arg`");
+            }
+            match wasm.arguments.get(0) {
+                Some(&Type::ByRef(_)) | Some(&Type::ByValue(_)) => {}
+                Some(&Type::ByMutRef(_)) => panic!("a `this_arg` cannot be a mutable ref"),
+                None => panic!("functions taking a `this_arg` must have at least one argument"),
+            }
+        }
+
+        if let ImportFunctionKind::Normal = kind {
+            if wasm.opts.getter().is_some() && wasm.arguments.len() != 0 {
+                panic!("getters which aren't methods can't take arguments");
+            }
+            if wasm.opts.setter().is_some() && wasm.arguments.len() != 1 {
+                panic!("setters which aren't methods must take one argument");
+            }
+        }
+        if wasm.opts.setter().is_some() {
+            if let ImportFunctionKind::Method { .. } = kind {
+                if wasm.arguments.len() != 2 {
+                    panic!("setters which are methods must take `this` and one argument");
+                }
+            }
+            if wasm.ret.is_some() {
+                panic!("setters can't return anything");
+            }
+        }
+        if wasm.opts.writeonly() {
+            if wasm.opts.setter().is_none() {
+                panic!("only setters can be `writeonly`");
+            }
+            if wasm.opts.getter().is_some() {
+                panic!("`writeonly` setters can't also be getters");
+            }
+        }
+        // Everything else is looked up as a property of something
+        if let ImportFunctionKind::Normal = kind {
+            let global = js_namespace.is_none() && !wasm.opts.late_bound();
+            if global && !shared::is_js_identifier(&wasm.name) {
+                panic!("the `js_name` of an imported function must be a valid \
+                        JS identifier unless it's in a `js_namespace`, not {:?}",
+                       wasm.name);
+            }
+        }
+
+        // Several Rust functions can bind the same JS function, for example
+        // one per overload, so the shim is named after the Rust function too
         let shim = {
             let ns = match kind {
-                ImportFunctionKind::Normal => "n",
-                ImportFunctionKind::Method { ref class, .. } => class,
-                ImportFunctionKind::JsConstructor { ref class, .. } => class,
+                ImportFunctionKind::Normal => match js_namespace {
+                    Some(ns) => ns.iter().map(|s| ident_chars(s)).collect::<Vec<_>>().join("_"),
+                    None => "n".to_string(),
+                },
+                ImportFunctionKind::Method { ref class, .. } => class.clone(),
+                ImportFunctionKind::JsConstructor { ref class, .. } => class.clone(),
+                ImportFunctionKind::StaticMethod { ref class, .. } => class.clone(),
             };
-            format!("__wbg_f_{}_{}_{}", js_name, f.ident, ns)
+            format!("__wbg_f_{}_{}_{}", ident_chars(&js_name), f.ident, ns)
         };
         ImportKind::Function(ImportFunction {
             function: wasm,
@@ -358,10 +829,87 @@ impl Program {
         })
     }
 
-    pub fn push_foreign_ty(&mut self, f: syn::ForeignItemType) -> ImportKind {
+    /// A `property` is declared like a method taking only `this` and
+    /// returning the property's value, which becomes a method reading it and,
+    /// unless it's `readonly`, a `set_` method writing it.
+    fn push_foreign_property(&mut self, f: syn::ForeignItemFn, opts: BindgenAttrs) -> ImportKind {
+        let js_name = opts.js_name().unwrap_or(f.ident.to_string());
+        let wasm = Function::from_decl(js_name.clone(), f.decl, f.attrs, opts, f.vis,
+                                       false).0;
+        if !wasm.opts.method() {
+            panic!("properties must be `method`s of an imported type");
+        }
+        if wasm.opts.catch() || wasm.opts.getter().is_some() || wasm.opts.setter().is_some() {
+            panic!("properties can't also be `catch`, `getter` or `setter`");
+        }
+        if wasm.arguments.len() != 1 {
+            panic!("properties must only take `this`");
+        }
+        let class_ty = match wasm.arguments[0] {
+            Type::ByRef(ref t) => t.clone(),
+            _ => panic!("properties must take `this` by reference"),
+        };
+        let class = match class_ty {
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
+                extract_path_ident(path)
+            }
+            _ => None,
+        };
+        let class = class.expect("first argument of a property must be a bare type");
+        let ty = match wasm.ret {
+            Some(Type::ByValue(ref t)) => t.clone(),
+            _ => panic!("properties must return their value by value"),
+        };
+        let get_shim = format!("__wbg_p_get_{}_{}_{}", ident_chars(&js_name), f.ident, class);
+        let set_shim = format!("__wbg_p_set_{}_{}_{}", ident_chars(&js_name), f.ident, class);
+        ImportKind::Property(ImportProperty {
+            vis: wasm.rust_vis,
+            class: class.as_ref().to_string(),
+            class_ty,
+            js_name,
+            rust_name: f.ident,
+            ty,
+            readonly: wasm.opts.readonly(),
+            get_shim: get_shim.into(),
+            set_shim: set_shim.into(),
+            rust_attrs: wasm.rust_attrs,
+        })
+    }
+
+    pub fn push_foreign_ty(
+        &mut self,
+        f: syn::ForeignItemType,
+        opts: BindgenAttrs,
+        module: Option<&String>,
+    ) -> ImportKind {
+        if opts.instanceof() && (opts.structural() || opts.iterator()) {
+            panic!("`instanceof` can't be used with `structural` or `iterator` \
+                    types, which aren't instances of a class");
+        }
+        if opts.structural() && opts.typescript_type().is_some() {
+            panic!("`typescript_type` can't be used with `structural` types, \
+                    which are passed around as plain `JsValue`s");
+        }
+        let mut typescript_type = opts.typescript_type().map(|s| s.to_string());
+        if let Some(generics) = opts.typescript_generics() {
+            if opts.structural() || typescript_type.is_some() {
+                panic!("`typescript_generics` can't be used with `structural` \
+                        types or together with `typescript_type`");
+            }
+            // The Rust type stands for the class with any type arguments, so
+            // each parameter is filled in with its default or `any`
+            let args = typescript_generic_defaults(generics);
+            typescript_type = Some(format!("{}<{}>", f.ident, args.join(", ")));
+        }
         ImportKind::Type(ImportType {
             vis: f.vis,
             name: f.ident,
+            module: module.cloned(),
+            structural: opts.structural(),
+            instanceof: opts.instanceof(),
+            iterator: opts.iterator(),
+            extends: opts.extends(),
+            typescript_type,
         })
     }
 
@@ -373,7 +921,11 @@ impl Program {
         if f.mutability.is_some() {
             panic!("cannot import mutable globals yet")
         }
-        let js_name = opts.js_name().unwrap_or(f.ident);
+        let js_name = opts.js_name().unwrap_or(f.ident.to_string());
+        if !shared::is_js_identifier(&js_name) {
+            panic!("the `js_name` of a static must be a valid JS identifier, not {:?}",
+                   js_name);
+        }
         let shim = format!("__wbg_static_accessor_{}_{}", js_name, f.ident);
         ImportKind::Static(ImportStatic {
             ty: *f.ty,
@@ -381,6 +933,7 @@ impl Program {
             rust_name: f.ident,
             js_name,
             shim: shim.into(),
+            lazy: opts.lazy(),
         })
     }
 
@@ -416,17 +969,27 @@ impl Function {
             panic!("can only bindgen safe functions");
         }
 
-        Function::from_decl(input.ident, input.decl, input.attrs, opts, input.vis, false).0
+        // Unlike methods, free functions are exported from the JS module
+        // under their name, which has to be an identifier
+        let js_name = opts.js_name().unwrap_or(input.ident.to_string());
+        if !shared::is_js_identifier(&js_name) {
+            panic!("the `js_name` of an exported function must be a valid JS \
+                    identifier, not {:?}", js_name);
+        }
+        let function = Function::from_decl(js_name, input.decl, input.attrs, opts,
+                                           input.vis, false).0;
+        function.check_no_nullable();
+        function
     }
 
     pub fn from_decl(
-        name: syn::Ident,
+        name: String,
         decl: Box<syn::FnDecl>,
         attrs: Vec<syn::Attribute>,
         opts: BindgenAttrs,
         vis: syn::Visibility,
         allow_self: bool,
-    ) -> (Function, Option<bool>) {
+    ) -> (Function, Option<Receiver>) {
         if decl.variadic.is_some() {
             panic!("can't bindgen variadic functions")
         }
@@ -434,42 +997,331 @@ impl Function {
             panic!("can't bindgen functions with lifetime or type parameters")
         }
 
-        let mut mutable = None;
-        let arguments = decl.inputs
+        let mut receiver = None;
+        let captured = decl.inputs
             .iter()
             .filter_map(|arg| match *arg {
                 syn::FnArg::Captured(ref c) => Some(c),
-                syn::FnArg::SelfValue(_) => {
-                    panic!("by-value `self` not yet supported");
+                syn::FnArg::SelfValue(_) if allow_self => {
+                    assert!(receiver.is_none());
+                    receiver = Some(Receiver::Value);
+                    None
                 }
                 syn::FnArg::SelfRef(ref a) if allow_self => {
-                    assert!(mutable.is_none());
-                    mutable = Some(a.mutability.is_some());
+                    assert!(receiver.is_none());
+                    receiver = Some(if a.mutability.is_some() {
+                        Receiver::RefMut
+                    } else {
+                        Receiver::Ref
+                    });
                     None
                 }
                 _ => panic!("arguments cannot be `self` or ignored"),
             })
+            .collect::<Vec<_>>();
+        let arguments = captured.iter()
             .map(|arg| Type::from(&arg.ty))
             .collect::<Vec<_>>();
+        let argument_names = argument_names(&captured);
+        let nullable = opts.nullable();
+        for name in nullable.iter() {
+            if !argument_names.iter().any(|a| a == name.as_ref()) {
+                panic!("no argument named `{}` to mark as `nullable`", name);
+            }
+        }
+        let nullable = argument_names.iter()
+            .map(|a| nullable.iter().any(|n| n.as_ref() == a))
+            .collect();
 
+        // An explicit `-> ()` is the same as not returning anything
         let ret = match decl.output {
             syn::ReturnType::Default => None,
-            syn::ReturnType::Type(_, ref t) => Some(Type::from(t)),
+            syn::ReturnType::Type(_, ref t) => match **t {
+                syn::Type::Tuple(ref t) if t.elems.len() == 0 => None,
+                ref t => Some(Type::from(t)),
+            },
         };
 
         (
             Function {
                 name,
                 arguments,
+                argument_names,
+                nullable,
                 ret,
                 opts,
                 rust_vis: vis,
                 rust_decl: decl,
                 rust_attrs: attrs,
             },
-            mutable,
+            receiver,
         )
     }
+
+    /// Arguments of exports are always checked by their conversion into Rust
+    /// so `nullable` is only meaningful for imports.
+    fn check_no_nullable(&self) {
+        if self.nullable.iter().any(|n| *n) {
+            panic!("only arguments of imported functions can be `nullable`");
+        }
+    }
+
+    /// Exports may return `&str` and `&[T]`, which are copied out into a
+    /// `String` or `Vec<T>` while the borrow is still alive, but no other
+    /// references. The copy is made right after the call for methods too, so
+    /// the string may borrow from `self`.
+    fn check_borrowed_ret(&self) {
+        let ty = match self.ret {
+            Some(Type::ByRef(ref t)) => t,
+            Some(Type::ByMutRef(_)) => panic!("can't return a mutable reference"),
+            _ => return,
+        };
+        let copied = match *ty {
+            syn::Type::Slice(_) => true,
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
+                extract_path_ident(path).map(|i| i.as_ref() == "str").unwrap_or(false)
+            }
+            _ => false,
+        };
+        if !copied {
+            panic!("the only references which can be returned are `&str` and `&[T]`");
+        }
+        if self.opts.async_() {
+            panic!("`async` functions can't return references");
+        }
+    }
+
+    /// Runs of two or more adjacent by-value `bool` arguments, as
+    /// `(first index, length)`, which exports pass to wasm packed together
+    /// as bits of a single `u32`.
+    ///
+    /// A lone `bool` is passed on its own as usual, and runs longer than 32
+    /// are split up so each fits in one word.
+    pub fn packed_bools(&self) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut i = 0;
+        while i < self.arguments.len() {
+            let start = i;
+            while i < self.arguments.len() && i - start < 32 && self.arguments[i].is_bool() {
+                i += 1;
+            }
+            match i - start {
+                0 => i += 1,
+                1 => {}
+                n => runs.push((start, n)),
+            }
+        }
+        runs
+    }
+
+    /// If this function returns a `Result<T, E>` then `ret` is replaced with
+    /// `T` (or `None` if it's `()`) and `E` is returned.
+    ///
+    /// Like `catch` on imports this is purely syntactic: any type named
+    /// `Result` with two type parameters is assumed to be `std`'s.
+    pub fn extract_result(&mut self) -> Option<Type> {
+        let (ok, err) = match self.ret {
+            Some(Type::ByValue(ref t)) => match extract_result_ty_params(t) {
+                Some(pair) => pair,
+                None => return None,
+            },
+            _ => return None,
+        };
+        let err = match err {
+            syn::Type::Tuple(ref t) if t.elems.len() == 0 => {
+                panic!("the error type of an exported `Result` can't be `()`")
+            }
+            ref t => Type::from(t),
+        };
+        self.ret = match ok {
+            syn::Type::Tuple(ref t) if t.elems.len() == 0 => None,
+            ref t => Some(Type::from(t)),
+        };
+        Some(err)
+    }
+}
+
+fn extract_result_ty_params(ty: &syn::Type) -> Option<(syn::Type, syn::Type)> {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        }) => path,
+        _ => return None,
+    };
+    let seg = path.segments.last()?.into_value();
+    if seg.ident.as_ref() != "Result" {
+        return None;
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    if generics.args.len() != 2 {
+        return None;
+    }
+    let mut tys = generics.args.iter().filter_map(|arg| match *arg {
+        syn::GenericArgument::Type(ref t) => Some(t.clone()),
+        _ => None,
+    });
+    let ok = tys.next()?;
+    let err = tys.next()?;
+    Some((ok, err))
+}
+
+/// Names for each argument as they'll appear in the generated JS.
+///
+/// Plain identifier patterns keep their name, anything else (tuple
+/// destructuring, `_`, ...) is given a positional `argN` name, renamed if
+/// needed so it doesn't clash with any other argument.
+fn argument_names(args: &[&syn::ArgCaptured]) -> Vec<String> {
+    let idents = args.iter()
+        .map(|arg| match arg.pat {
+            syn::Pat::Ident(syn::PatIdent { ident, subpat: None, .. }) => {
+                Some(ident.as_ref().to_string())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut names = Vec::<String>::new();
+    for (i, ident) in idents.iter().enumerate() {
+        let name = match *ident {
+            Some(ref name) => name.clone(),
+            None => {
+                let mut name = format!("arg{}", i);
+                while idents.contains(&Some(name.clone())) || names.contains(&name) {
+                    name.push('_');
+                }
+                name
+            }
+        };
+        names.push(name);
+    }
+    names
+}
+
+/// Takes the type parameters of a TypeScript class, like `K, V = string`, and
+/// returns the type argument to use for each one when nothing more specific
+/// is known: its default if it has one, and `any` otherwise.
+fn typescript_generic_defaults(params: &str) -> Vec<String> {
+    // Defaults may themselves have type arguments, so only commas outside of
+    // any brackets separate parameters
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut prev = ' ';
+    for (i, c) in params.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            // The arrow of a function type isn't a bracket
+            '>' if prev == '=' => {}
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    split.push(&params[start..]);
+
+    split.into_iter()
+        .map(|param| {
+            let mut parts = param.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            // A constraint doesn't change the argument used, `any` satisfies
+            // it either way
+            let name = name.split_whitespace().next().unwrap_or("");
+            let valid = name.chars().next().map(|c| c.is_alphabetic() || c == '_') == Some(true) &&
+                name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            if !valid {
+                panic!("invalid TypeScript type parameter in `typescript_generics`: `{}`",
+                       param.trim());
+            }
+            match parts.next() {
+                Some(default) if default.trim().len() > 0 => default.trim().to_string(),
+                Some(_) => panic!("missing default for `{}` in `typescript_generics`", name),
+                None => "any".to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Whether `ty` is `impl Iterator<Item = T>`, possibly with more bounds.
+fn is_impl_iterator(ty: &syn::Type) -> bool {
+    let bounds = match *ty {
+        syn::Type::ImplTrait(ref t) => &t.bounds,
+        _ => return false,
+    };
+    bounds.iter().any(|b| match *b {
+        syn::TypeParamBound::Trait(ref t) => {
+            t.path.segments.last()
+                .map(|s| s.value().ident.as_ref() == "Iterator")
+                .unwrap_or(false)
+        }
+        _ => false,
+    })
+}
+
+/// Whether `ty` is `Cow<str>`, which an export takes like a `&str` and
+/// which borrows the string JS passed in for the length of the call.
+pub fn is_cow_str(ty: &syn::Type) -> bool {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return false,
+    };
+    let segment = match path.segments.last() {
+        Some(s) => s.into_value(),
+        None => return false,
+    };
+    if segment.ident.as_ref() != "Cow" {
+        return false
+    }
+    let args = match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref a) => &a.args,
+        _ => return false,
+    };
+    args.iter().any(|a| match *a {
+        syn::GenericArgument::Type(syn::Type::Path(syn::TypePath { qself: None, ref path })) => {
+            extract_path_ident(path).map(|i| i.as_ref() == "str").unwrap_or(false)
+        }
+        _ => false,
+    })
+}
+
+/// Whether `ty` is `Option<&str>`, which an export takes through an anchor
+/// borrowing the string JS passed in for the length of the call.
+pub fn is_option_str(ty: &syn::Type) -> bool {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return false,
+    };
+    let segment = match path.segments.last() {
+        Some(s) => s.into_value(),
+        None => return false,
+    };
+    if segment.ident.as_ref() != "Option" {
+        return false
+    }
+    let args = match segment.arguments {
+        syn::PathArguments::AngleBracketed(ref a) => &a.args,
+        _ => return false,
+    };
+    args.iter().any(|a| match *a {
+        syn::GenericArgument::Type(syn::Type::Reference(ref r)) => {
+            if r.mutability.is_some() {
+                return false
+            }
+            match *r.elem {
+                syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
+                    extract_path_ident(path).map(|i| i.as_ref() == "str").unwrap_or(false)
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    })
 }
 
 pub fn extract_path_ident(path: &syn::Path) -> Option<syn::Ident> {
@@ -486,6 +1338,14 @@ pub fn extract_path_ident(path: &syn::Path) -> Option<syn::Ident> {
     path.segments.first().map(|v| v.value().ident)
 }
 
+/// `name` with anything that can't be part of a Rust identifier replaced, for
+/// using JS names in symbol names.
+fn ident_chars(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
 impl Type {
     pub fn from(ty: &syn::Type) -> Type {
         if let syn::Type::Reference(ref r) = *ty {
@@ -495,8 +1355,23 @@ impl Type {
                 Type::ByRef((*r.elem).clone())
             }
         }
+        // An `impl Iterator` can't be named, so it's passed as the
+        // `RustIterator` it's converted into
+        if is_impl_iterator(ty) {
+            let ty = syn::parse_str("::wasm_bindgen::convert::RustIterator").unwrap();
+            return Type::ByValue(ty)
+        }
         Type::ByValue(ty.clone())
     }
+
+    fn is_bool(&self) -> bool {
+        match *self {
+            Type::ByValue(syn::Type::Path(syn::TypePath { qself: None, ref path })) => {
+                extract_path_ident(path).map(|i| i.as_ref() == "bool").unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Export {
@@ -507,16 +1382,19 @@ impl Export {
             generated_name.push_str(class.as_ref());
         }
         generated_name.push_str("_");
-        generated_name.push_str(self.function.name.as_ref());
+        generated_name.push_str(self.rust_name.as_ref());
         syn::Ident::from(generated_name)
     }
 
     pub fn export_name(&self) -> syn::LitStr {
+        if self.function.opts.start() {
+            return syn::LitStr::new(shared::START_FUNCTION, Span::call_site())
+        }
         let name = match self.class {
             Some(class) => {
-                shared::struct_function_export_name(class.as_ref(), self.function.name.as_ref())
+                shared::struct_function_export_name(class.as_ref(), self.rust_name.as_ref())
             }
-            None => shared::free_function_export_name(self.function.name.as_ref()),
+            None => shared::free_function_export_name(self.rust_name.as_ref()),
         };
         syn::LitStr::new(&name, Span::call_site())
     }
@@ -524,19 +1402,86 @@ impl Export {
 
 impl ImportFunction {
     pub fn infer_getter_property(&self) -> String {
-        self.function.name.as_ref().to_string()
+        self.function.name.clone()
     }
 
     pub fn infer_setter_property(&self) -> String {
-        let name = self.function.name.as_ref();
+        let name = &self.function.name;
         assert!(name.starts_with("set_"), "setters must start with `set_`");
         name[4..].to_string()
     }
+
+    /// Whether this is a method taking `this` by reference and returning the
+    /// same imported type, as fluent JS APIs returning `this` do.
+    pub fn returns_self(&self) -> bool {
+        let class = match self.kind {
+            ImportFunctionKind::Method { ref class, .. } => class,
+            _ => return false,
+        };
+        match self.function.arguments.get(0) {
+            Some(&Type::ByRef(_)) => {}
+            _ => return false,
+        }
+        match self.function.ret {
+            Some(Type::ByValue(syn::Type::Path(syn::TypePath {
+                qself: None,
+                ref path,
+            }))) => match extract_path_ident(path) {
+                Some(ident) => {
+                    let name: &str = ident.as_ref();
+                    name == &class[..]
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl ImportType {
+    pub fn descriptor(&self) -> u32 {
+        let module = self.module.as_ref().map(|s| &s[..]);
+        shared::imported_type_descriptor(module, self.name.as_ref())
+    }
 }
 
 impl Struct {
-    fn from(s: syn::ItemStruct, _opts: BindgenAttrs) -> Struct {
-        Struct { name: s.ident }
+    fn from(s: &mut syn::ItemStruct, opts: BindgenAttrs) -> Struct {
+        // On the struct itself `getter_with_clone` applies to every field
+        let getter_with_clone = opts.getter_with_clone();
+        let mut fields = Vec::new();
+        if let syn::Fields::Named(ref mut named) = s.fields {
+            for field in named.named.iter_mut() {
+                match field.vis {
+                    syn::Visibility::Public(..) => {}
+                    _ => continue,
+                }
+                let name = match field.ident {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let opts = BindgenAttrs::find(&mut field.attrs);
+                if opts.skip() {
+                    continue
+                }
+                let getter = shared::struct_field_get(s.ident.as_ref(), name.as_ref());
+                let setter = shared::struct_field_set(s.ident.as_ref(), name.as_ref());
+                fields.push(StructField {
+                    name,
+                    struct_name: s.ident,
+                    readonly: opts.readonly(),
+                    getter_with_clone: getter_with_clone || opts.getter_with_clone(),
+                    ty: field.ty.clone(),
+                    getter: getter.into(),
+                    setter: setter.into(),
+                });
+            }
+        }
+        let js_class_aliases = opts.js_class_aliases();
+        if js_class_aliases.contains(&s.ident) {
+            panic!("`js_class` alias `{}` is the struct's own name", s.ident);
+        }
+        Struct { name: s.ident, fields, js_class_aliases }
     }
 }
 
@@ -569,6 +1514,37 @@ impl BindgenAttrs {
             .next()
     }
 
+    fn typescript_generics(&self) -> Option<&str> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::TypescriptGenerics(ref s) => Some(&s[..]),
+                _ => None,
+            })
+            .next()
+    }
+
+    fn typescript_type(&self) -> Option<&str> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::TypescriptType(ref s) => Some(&s[..]),
+                _ => None,
+            })
+            .next()
+    }
+
+    fn nullable(&self) -> Vec<syn::Ident> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::Nullable(ref names) => Some(names.iter().cloned()),
+                _ => None,
+            })
+            .flat_map(|names| names)
+            .collect()
+    }
+
     pub fn catch(&self) -> bool {
         self.attrs.iter().any(|a| match *a {
             BindgenAttr::Catch => true,
@@ -576,7 +1552,7 @@ impl BindgenAttrs {
         })
     }
 
-    fn constructor(&self) -> bool {
+    pub fn constructor(&self) -> bool {
         self.attrs.iter().any(|a| match *a {
             BindgenAttr::Constructor => true,
             _ => false,
@@ -590,11 +1566,21 @@ impl BindgenAttrs {
         })
     }
 
-    fn js_namespace(&self) -> Option<syn::Ident> {
+    fn static_method_of(&self) -> Option<syn::Ident> {
         self.attrs
             .iter()
             .filter_map(|a| match *a {
-                BindgenAttr::JsNamespace(s) => Some(s),
+                BindgenAttr::StaticMethodOf(c) => Some(c),
+                _ => None,
+            })
+            .next()
+    }
+
+    fn js_namespace(&self) -> Option<Vec<String>> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::JsNamespace(ref s) => Some(s.clone()),
                 _ => None,
             })
             .next()
@@ -627,11 +1613,176 @@ impl BindgenAttrs {
         })
     }
 
-    pub fn js_name(&self) -> Option<syn::Ident> {
+    pub fn final_(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Final => true,
+            _ => false,
+        })
+    }
+
+    pub fn pure(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Pure => true,
+            _ => false,
+        })
+    }
+
+    fn readonly(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Readonly => true,
+            _ => false,
+        })
+    }
+
+    fn getter_with_clone(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::GetterWithClone => true,
+            _ => false,
+        })
+    }
+
+    fn writeonly(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Writeonly => true,
+            _ => false,
+        })
+    }
+
+    fn property(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Property => true,
+            _ => false,
+        })
+    }
+
+    fn skip(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Skip => true,
+            _ => false,
+        })
+    }
+
+    pub fn indexing(&self) -> Option<Indexing> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::Indexing(i) => Some(i),
+                _ => None,
+            })
+            .next()
+    }
+
+    pub fn symbol(&self) -> Option<&JsSymbol> {
         self.attrs
             .iter()
             .filter_map(|a| match *a {
-                BindgenAttr::JsName(s) => Some(s),
+                BindgenAttr::Symbol(ref s) => Some(s),
+                _ => None,
+            })
+            .next()
+    }
+
+    pub fn late_bound(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::LateBound => true,
+            _ => false,
+        })
+    }
+
+    fn imported(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Imported => true,
+            _ => false,
+        })
+    }
+
+    fn unknown(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Unknown => true,
+            _ => false,
+        })
+    }
+
+    pub fn this_arg(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::ThisArg => true,
+            _ => false,
+        })
+    }
+
+    fn lazy(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Lazy => true,
+            _ => false,
+        })
+    }
+
+    pub fn start(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Start => true,
+            _ => false,
+        })
+    }
+
+    /// Whether these are the options of an `async fn`, which the macro
+    /// notes itself as `syn` can't parse the `async` keyword.
+    pub fn async_(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Async => true,
+            _ => false,
+        })
+    }
+
+    pub fn set_async(&mut self) {
+        self.attrs.push(BindgenAttr::Async);
+    }
+
+    fn iterator(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Iterator => true,
+            _ => false,
+        })
+    }
+
+    fn instanceof(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Instanceof => true,
+            _ => false,
+        })
+    }
+
+    pub fn variadic(&self) -> bool {
+        self.attrs.iter().any(|a| match *a {
+            BindgenAttr::Variadic => true,
+            _ => false,
+        })
+    }
+
+    fn extends(&self) -> Vec<syn::Ident> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::Extends(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn js_class_aliases(&self) -> Vec<syn::Ident> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::JsClass(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn js_name(&self) -> Option<String> {
+        self.attrs
+            .iter()
+            .filter_map(|a| match *a {
+                BindgenAttr::JsName(ref s) => Some(s.clone()),
                 _ => None,
             })
             .next()
@@ -657,12 +1808,37 @@ enum BindgenAttr {
     Catch,
     Constructor,
     Method,
-    JsNamespace(syn::Ident),
+    StaticMethodOf(syn::Ident),
+    JsNamespace(Vec<syn::Ident>),
     Module(String),
     Getter(Option<syn::Ident>),
     Setter(Option<syn::Ident>),
     Structural,
-    JsName(syn::Ident),
+    Final,
+    Pure,
+    Readonly,
+    GetterWithClone,
+    Writeonly,
+    Property,
+    Skip,
+    Symbol(JsSymbol),
+    Variadic,
+    Iterator,
+    Instanceof,
+    Start,
+    Async,
+    Lazy,
+    LateBound,
+    ThisArg,
+    Imported,
+    Unknown,
+    JsName(String),
+    JsClass(syn::Ident),
+    Extends(syn::Ident),
+    Indexing(Indexing),
+    TypescriptType(String),
+    TypescriptGenerics(String),
+    Nullable(Vec<syn::Ident>),
 }
 
 impl syn::synom::Synom for BindgenAttr {
@@ -673,6 +1849,13 @@ impl syn::synom::Synom for BindgenAttr {
         |
         call!(term, "method") => { |_| BindgenAttr::Method }
         |
+        do_parse!(
+            call!(term, "static_method_of") >>
+            punct!(=) >>
+            cls: syn!(syn::Ident) >>
+            (cls)
+        )=> { BindgenAttr::StaticMethodOf }
+        |
         do_parse!(
             call!(term, "getter") >>
             val: option!(do_parse!(
@@ -695,13 +1878,100 @@ impl syn::synom::Synom for BindgenAttr {
         |
         call!(term, "structural") => { |_| BindgenAttr::Structural }
         |
+        call!(term, "final") => { |_| BindgenAttr::Final }
+        |
+        call!(term, "pure") => { |_| BindgenAttr::Pure }
+        |
+        call!(term, "readonly") => { |_| BindgenAttr::Readonly }
+        |
+        call!(term, "getter_with_clone") => { |_| BindgenAttr::GetterWithClone }
+        |
+        call!(term, "writeonly") => { |_| BindgenAttr::Writeonly }
+        |
+        call!(term, "property") => { |_| BindgenAttr::Property }
+        |
+        call!(term, "skip") => { |_| BindgenAttr::Skip }
+        |
+        call!(term, "variadic") => { |_| BindgenAttr::Variadic }
+        |
+        call!(term, "iterator") => { |_| BindgenAttr::Iterator }
+        |
+        call!(term, "instanceof") => { |_| BindgenAttr::Instanceof }
+        |
+        call!(term, "start") => { |_| BindgenAttr::Start }
+        |
+        call!(term, "lazy") => { |_| BindgenAttr::Lazy }
+        |
+        call!(term, "late_bound") => { |_| BindgenAttr::LateBound }
+        |
+        call!(term, "this_arg") => { |_| BindgenAttr::ThisArg }
+        |
+        call!(term, "imported") => { |_| BindgenAttr::Imported }
+        |
+        call!(term, "unknown") => { |_| BindgenAttr::Unknown }
+        |
+        call!(term, "indexing_getter") => { |_| BindgenAttr::Indexing(Indexing::Getter) }
+        |
+        call!(term, "indexing_setter") => { |_| BindgenAttr::Indexing(Indexing::Setter) }
+        |
+        call!(term, "indexing_deleter") => { |_| BindgenAttr::Indexing(Indexing::Deleter) }
+        |
+        do_parse!(
+            call!(term, "symbol") >>
+            punct!(=) >>
+            name: syn!(syn::Ident) >>
+            (name)
+        )=> { |name| BindgenAttr::Symbol(JsSymbol::WellKnown(name)) }
+        |
+        do_parse!(
+            call!(term, "symbol") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { |key: String| {
+            if key.contains('"') || key.contains('\\') {
+                panic!("symbol keys can't contain quotes or backslashes");
+            }
+            BindgenAttr::Symbol(JsSymbol::Registered(key))
+        } }
+        |
         do_parse!(
             call!(term, "js_namespace") >>
             punct!(=) >>
             ns: syn!(syn::Ident) >>
-            (ns)
+            (vec![ns.to_string()])
         )=> { BindgenAttr::JsNamespace }
         |
+        do_parse!(
+            call!(term, "js_namespace") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { |s: String| {
+            if s.split('.').any(|segment| segment.is_empty()) {
+                panic!("invalid `js_namespace`: {:?}", s);
+            }
+            let path = s.split('.').map(|s| s.to_string()).collect();
+            BindgenAttr::JsNamespace(js_namespace(path))
+        } }
+        |
+        do_parse!(
+            call!(term, "js_namespace") >>
+            punct!(=) >>
+            segments: brackets!(call!(
+                syn::punctuated::Punctuated::<syn::LitStr, syn::token::Comma>::parse_terminated
+            )) >>
+            (segments.1.into_iter().map(|s| s.value()).collect())
+        )=> { |segments: Vec<String>| {
+            if segments.is_empty() {
+                panic!("`js_namespace` needs at least one segment");
+            }
+            if let Some(segment) = segments.iter().find(|s| s.is_empty() || s.contains('.')) {
+                panic!("invalid `js_namespace` segment: {:?}", segment);
+            }
+            BindgenAttr::JsNamespace(js_namespace(segments))
+        } }
+        |
         do_parse!(
             call!(term, "module") >>
             punct!(=) >>
@@ -713,11 +1983,73 @@ impl syn::synom::Synom for BindgenAttr {
             call!(term, "js_name") >>
             punct!(=) >>
             ns: syn!(syn::Ident) >>
-            (ns)
+            (ns.to_string())
         )=> { BindgenAttr::JsName }
+        |
+        do_parse!(
+            call!(term, "js_name") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { |name: String| {
+            if name.is_empty() || name.contains('\'') || name.contains('\\') {
+                panic!("invalid `js_name`: {:?}", name);
+            }
+            BindgenAttr::JsName(name)
+        } }
+        |
+        do_parse!(
+            call!(term, "js_class") >>
+            punct!(=) >>
+            cls: syn!(syn::Ident) >>
+            (cls)
+        )=> { BindgenAttr::JsClass }
+        |
+        do_parse!(
+            call!(term, "extends") >>
+            punct!(=) >>
+            cls: syn!(syn::Ident) >>
+            (cls)
+        )=> { BindgenAttr::Extends }
+        |
+        do_parse!(
+            call!(term, "typescript_type") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::TypescriptType }
+        |
+        do_parse!(
+            call!(term, "typescript_generics") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::TypescriptGenerics }
+        |
+        do_parse!(
+            call!(term, "nullable") >>
+            names: parens!(call!(
+                syn::punctuated::Punctuated::<syn::Ident, syn::token::Comma>::parse_terminated
+            )) >>
+            (names.1.into_iter().collect())
+        )=> { BindgenAttr::Nullable }
     ));
 }
 
+/// Checks the segments of a `js_namespace`, which may be any property names
+/// except the first, since that's looked up in scope.
+fn js_namespace(path: Vec<String>) -> Vec<String> {
+    if !shared::is_js_identifier(&path[0]) {
+        panic!("the first segment of a `js_namespace` must be a JS identifier, \
+                not {:?}", path[0]);
+    }
+    if let Some(segment) = path.iter().find(|s| s.contains('\'') || s.contains('\\')) {
+        panic!("`js_namespace` segments can't contain quotes or backslashes: {:?}",
+               segment);
+    }
+    path
+}
+
 fn extract_first_ty_param(ty: Option<&Type>) -> Option<Option<Type>> {
     let ty = match ty {
         Some(t) => t,
@@ -39,12 +39,18 @@ impl ToTokens for ast::Program {
         let mut types = HashSet::new();
         for i in self.imports.iter() {
             if let ast::ImportKind::Type(ref t) = i.kind {
-                types.insert(t.name);
+                types.insert(t.name.to_string());
             }
         }
         for i in self.imports.iter() {
+            // Enums are items of their own even when they're namespaced
+            if let ast::ImportKind::Enum(ref e) = i.kind {
+                e.to_tokens(tokens);
+                continue
+            }
             match i.js_namespace {
-                Some(ns) if types.contains(&ns) => {
+                Some(ref ns) if ns.len() == 1 && types.contains(&ns[0]) => {
+                    let ns = syn::Ident::from(&ns[0][..]);
                     let kind = &i.kind;
                     (quote! { impl #ns { #kind } }).to_tokens(tokens);
                 }
@@ -91,8 +97,8 @@ impl ToTokens for ast::Struct {
         let new_fn = syn::Ident::from(shared::new_function(self.name.as_ref()));
         let free_fn = syn::Ident::from(shared::free_function(self.name.as_ref()));
         let c = shared::name_to_descriptor(name.as_ref());
-        let descriptor = Literal::byte_string(format!("{:4}", c).as_bytes());
-        let borrowed_descriptor = Literal::byte_string(format!("{:4}", c + 1).as_bytes());
+        let descriptor = Literal::byte_string(format!("{:8}", c).as_bytes());
+        let borrowed_descriptor = Literal::byte_string(format!("{:8}", c + 1).as_bytes());
         (quote! {
             impl ::wasm_bindgen::convert::WasmBoundary for #name {
                 type Abi = u32;
@@ -110,29 +116,25 @@ impl ToTokens for ast::Struct {
                 unsafe fn from_abi(js: u32, _extra: &mut ::wasm_bindgen::convert::Stack)
                     -> Self
                 {
-                    let js = js as *mut ::wasm_bindgen::__rt::WasmRefCell<#name>;
-                    ::wasm_bindgen::__rt::assert_not_null(js);
-                    let js = Box::from_raw(js);
-                    js.borrow_mut(); // make sure no one's borrowing
-                    js.into_inner()
+                    ::wasm_bindgen::__rt::take_struct(js)
                 }
             }
 
+            impl ::wasm_bindgen::convert::WasmStruct for #name {}
+
             impl ::wasm_bindgen::convert::FromRefWasmBoundary for #name {
                 type Abi = u32;
                 const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor =
                     ::wasm_bindgen::convert::Descriptor {
                         __x: *#borrowed_descriptor
                     };
-                type RefAnchor = ::wasm_bindgen::__rt::Ref<'static, #name>;
+                type RefAnchor = ::wasm_bindgen::__rt::StructRef<#name>;
 
                 unsafe fn from_abi_ref(
                     js: Self::Abi,
                     _extra: &mut ::wasm_bindgen::convert::Stack,
                 ) -> Self::RefAnchor {
-                    let js = js as *mut ::wasm_bindgen::__rt::WasmRefCell<#name>;
-                    ::wasm_bindgen::__rt::assert_not_null(js);
-                    (*js).borrow()
+                    ::wasm_bindgen::__rt::borrow_struct(js)
                 }
             }
 
@@ -148,9 +150,7 @@ impl ToTokens for ast::Struct {
                     js: Self::Abi,
                     _extra: &mut ::wasm_bindgen::convert::Stack,
                 ) -> Self::RefAnchor {
-                    let js = js as *mut ::wasm_bindgen::__rt::WasmRefCell<#name>;
-                    ::wasm_bindgen::__rt::assert_not_null(js);
-                    (*js).borrow_mut()
+                    ::wasm_bindgen::__rt::borrow_struct_mut(js)
                 }
             }
 
@@ -158,7 +158,7 @@ impl ToTokens for ast::Struct {
                 fn from(value: #name) -> Self {
                     let ptr = ::wasm_bindgen::convert::WasmBoundary::into_abi(
                         value,
-                        unsafe { &mut ::wasm_bindgen::convert::GlobalStack::new() },
+                        unsafe { &mut ::wasm_bindgen::convert::FrameStack::empty() },
                     );
 
                     #[wasm_import_module = "__wbindgen_placeholder__"]
@@ -170,7 +170,7 @@ impl ToTokens for ast::Struct {
                         <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary>
                             ::from_abi(
                                 #new_fn(ptr),
-                                &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                                &mut ::wasm_bindgen::convert::FrameStack::empty(),
                             )
                     }
                 }
@@ -178,10 +178,70 @@ impl ToTokens for ast::Struct {
 
             #[no_mangle]
             pub unsafe extern fn #free_fn(ptr: u32) {
-                <#name as ::wasm_bindgen::convert::WasmBoundary>::from_abi(
-                    ptr,
-                    &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                ::wasm_bindgen::__rt::free_struct::<#name>(ptr);
+            }
+        }).to_tokens(tokens);
+
+        for field in self.fields.iter() {
+            field.to_tokens(tokens);
+        }
+    }
+}
+
+impl ToTokens for ast::StructField {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let name = &self.name;
+        let struct_name = &self.struct_name;
+        let ty = &self.ty;
+        let getter = &self.getter;
+        let setter = &self.setter;
+        // JS gets a copy of its own, which is freed like any other value
+        // returned to it while the field itself stays where it is
+        let val = if self.getter_with_clone {
+            quote! {
+                ::std::clone::Clone::clone(
+                    &::wasm_bindgen::__rt::borrow_struct::<#struct_name>(js).#name
+                )
+            }
+        } else {
+            quote! {{
+                ::wasm_bindgen::__rt::assert_copy::<#ty>();
+                ::wasm_bindgen::__rt::borrow_struct::<#struct_name>(js).#name
+            }}
+        };
+        (quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub unsafe extern fn #getter(js: u32, __wbindgen_frame: *mut u32)
+                -> <#ty as ::wasm_bindgen::convert::WasmBoundary>::Abi
+            {
+                ::wasm_bindgen::__rt::link_this_library();
+                let val = #val;
+                <#ty as ::wasm_bindgen::convert::WasmBoundary>::into_abi(
+                    val,
+                    &mut ::wasm_bindgen::convert::FrameStack::new(__wbindgen_frame),
+                )
+            }
+        }).to_tokens(tokens);
+
+        if self.readonly {
+            return
+        }
+
+        (quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub unsafe extern fn #setter(
+                js: u32,
+                val: <#ty as ::wasm_bindgen::convert::WasmBoundary>::Abi,
+                __wbindgen_frame: *mut u32,
+            ) {
+                ::wasm_bindgen::__rt::link_this_library();
+                let val = <#ty as ::wasm_bindgen::convert::WasmBoundary>::from_abi(
+                    val,
+                    &mut ::wasm_bindgen::convert::FrameStack::new(__wbindgen_frame),
                 );
+                ::wasm_bindgen::__rt::borrow_struct_mut::<#struct_name>(js).#name = val;
             }
         }).to_tokens(tokens);
     }
@@ -194,27 +254,88 @@ impl ToTokens for ast::Export {
         let mut args = vec![];
         let mut arg_conversions = vec![];
         let mut converted_arguments = vec![];
+        let mut arg_slots = vec![];
         let ret = syn::Ident::from("_ret");
 
         let mut offset = 0;
         if self.method {
             let class = self.class.unwrap();
-            args.push(quote! { me: *mut ::wasm_bindgen::__rt::WasmRefCell<#class> });
-            arg_conversions.push(quote! {
-                ::wasm_bindgen::__rt::assert_not_null(me);
-                let me = unsafe { &*me };
+            args.push(quote! { me: u32 });
+            arg_conversions.push(if self.consume {
+                quote! {
+                    let me = unsafe { ::wasm_bindgen::__rt::take_struct::<#class>(me) };
+                }
+            } else if self.mutable {
+                quote! {
+                    let mut me = unsafe {
+                        ::wasm_bindgen::__rt::borrow_struct_mut::<#class>(me)
+                    };
+                }
+            } else {
+                quote! {
+                    let me = unsafe { ::wasm_bindgen::__rt::borrow_struct::<#class>(me) };
+                }
             });
             offset = 1;
         }
 
+        let packed_bools = self.function.packed_bools();
         for (i, ty) in self.function.arguments.iter().enumerate() {
+            let packed = packed_bools.iter()
+                .find(|&&(start, len)| start <= i && i < start + len)
+                .map(|&(start, _)| (start + offset, (i - start) as u32));
             let i = i + offset;
             let ident = syn::Ident::from(format!("arg{}", i));
+            if let Some((start, bit)) = packed {
+                let packed = syn::Ident::from(format!("packed{}", start));
+                if start == i {
+                    args.push(quote! { #packed: u32 });
+                }
+                arg_conversions.push(quote! {
+                    let #ident = #packed & (1 << #bit) != 0;
+                });
+                converted_arguments.push(quote! { #ident });
+                continue
+            }
             match *ty {
+                // A `Cow<str>` can only be taken, borrowing the string for the
+                // call just like a `&str` does
+                ast::Type::ByValue(ref t) if ast::is_cow_str(t) => {
+                    args.push(quote! {
+                        #ident: <str as ::wasm_bindgen::convert::FromRefWasmBoundary>::Abi
+                    });
+                    arg_slots.push(quote! {
+                        <str as ::wasm_bindgen::convert::FromRefWasmBoundary>::STACK_SLOTS
+                    });
+                    arg_conversions.push(quote! {
+                        let #ident = unsafe {
+                            <str as ::wasm_bindgen::convert::FromRefWasmBoundary>
+                                ::from_abi_ref(#ident, &mut __stack)
+                        };
+                        let #ident = ::std::borrow::Cow::Borrowed(&*#ident);
+                    });
+                }
+                // Neither can an `Option<&str>`, which borrows the string for
+                // as long as its anchor lives
+                ast::Type::ByValue(ref t) if ast::is_option_str(t) => {
+                    args.push(quote! { #ident: u32 });
+                    arg_slots.push(quote! {
+                        ::wasm_bindgen::convert::OptionStrAnchor::STACK_SLOTS
+                    });
+                    arg_conversions.push(quote! {
+                        let #ident = unsafe {
+                            ::wasm_bindgen::convert::OptionStrAnchor::from_abi(#ident, &mut __stack)
+                        };
+                        let #ident = #ident.get();
+                    });
+                }
                 ast::Type::ByValue(ref t) => {
                     args.push(quote! {
                         #ident: <#t as ::wasm_bindgen::convert::WasmBoundary>::Abi
                     });
+                    arg_slots.push(quote! {
+                        <#t as ::wasm_bindgen::convert::WasmBoundary>::STACK_SLOTS
+                    });
                     arg_conversions.push(quote! {
                         let #ident = unsafe {
                             <#t as ::wasm_bindgen::convert::WasmBoundary>
@@ -226,6 +347,9 @@ impl ToTokens for ast::Export {
                     args.push(quote! {
                         #ident: <#ty as ::wasm_bindgen::convert::FromRefWasmBoundary>::Abi
                     });
+                    arg_slots.push(quote! {
+                        <#ty as ::wasm_bindgen::convert::FromRefWasmBoundary>::STACK_SLOTS
+                    });
                     arg_conversions.push(quote! {
                         let #ident = unsafe {
                             <#ty as ::wasm_bindgen::convert::FromRefWasmBoundary>
@@ -238,6 +362,9 @@ impl ToTokens for ast::Export {
                     args.push(quote! {
                         #ident: <#ty as ::wasm_bindgen::convert::FromRefMutWasmBoundary>::Abi
                     });
+                    arg_slots.push(quote! {
+                        <#ty as ::wasm_bindgen::convert::FromRefMutWasmBoundary>::STACK_SLOTS
+                    });
                     arg_conversions.push(quote! {
                         let mut #ident = unsafe {
                             <#ty as ::wasm_bindgen::convert::FromRefMutWasmBoundary>
@@ -249,54 +376,159 @@ impl ToTokens for ast::Export {
             }
             converted_arguments.push(quote! { #ident });
         }
-        let ret_ty;
-        let convert_ret;
+        args.push(quote! { __wbindgen_frame: *mut u32 });
+        let mut ret_ty;
+        let mut convert_ret;
+        let mut ret_slots = vec![];
         match self.function.ret {
             Some(ast::Type::ByValue(ref t)) => {
                 ret_ty = quote! {
-                    -> <#t as ::wasm_bindgen::convert::WasmBoundary>::Abi
+                    -> <#t as ::wasm_bindgen::convert::ToWasmBoundary>::Abi
                 };
+                // Going through `From` lets a function returning `impl
+                // Iterator` be converted into the `RustIterator` it's passed
+                // as, while every other type is converted into itself
                 convert_ret = quote! {
-                    <#t as ::wasm_bindgen::convert::WasmBoundary>
-                        ::into_abi(#ret, &mut unsafe {
-                            ::wasm_bindgen::convert::GlobalStack::new()
-                        })
+                    <#t as ::wasm_bindgen::convert::ToWasmBoundary>
+                        ::to_abi(<#t as ::std::convert::From<_>>::from(#ret), &mut __stack)
                 };
+                ret_slots.push(quote! {
+                    <#t as ::wasm_bindgen::convert::ToWasmBoundary>::STACK_SLOTS
+                });
             }
-            Some(ast::Type::ByMutRef(_))
-            | Some(ast::Type::ByRef(_)) => {
-                panic!("can't return a borrowed ref");
+            Some(ast::Type::ByRef(ref t)) => {
+                // The call is followed immediately by a copy, see below
+                ret_ty = quote! {
+                    -> <<#t as ::std::borrow::ToOwned>::Owned
+                        as ::wasm_bindgen::convert::ToWasmBoundary>::Abi
+                };
+                convert_ret = quote! {
+                    <<#t as ::std::borrow::ToOwned>::Owned
+                        as ::wasm_bindgen::convert::ToWasmBoundary>
+                        ::to_abi(#ret, &mut __stack)
+                };
+                ret_slots.push(quote! {
+                    <<#t as ::std::borrow::ToOwned>::Owned
+                        as ::wasm_bindgen::convert::ToWasmBoundary>::STACK_SLOTS
+                });
+            }
+            Some(ast::Type::ByMutRef(_)) => {
+                panic!("can't return a mutable reference");
             }
             None => {
                 ret_ty = quote!{};
                 convert_ret = quote!{};
             }
         }
-
-        let name = self.function.name;
-        let receiver = match self.class {
-            Some(_) if self.method => {
-                if self.mutable {
-                    quote! { me.borrow_mut().#name }
-                } else {
-                    quote! { me.borrow().#name }
+        match self.throws {
+            Some(ast::Type::ByValue(ref e)) => {
+                let err_ret = match self.function.ret {
+                    Some(_) => quote! { unsafe { ::std::mem::zeroed() } },
+                    None => quote!{},
+                };
+                // An `Ok` is preceded by a 0, and an `Err` by a 1 and itself
+                ret_slots = match ret_slots.pop() {
+                    Some(ok) => vec![quote! { 1 + #ok }],
+                    None => vec![quote! { 1 }],
+                };
+                ret_slots.push(quote! {
+                    2 + <#e as ::wasm_bindgen::convert::WasmBoundary>::STACK_SLOTS
+                });
+                convert_ret = quote! {
+                    match #ret {
+                        Ok(#ret) => {
+                            let mut __stack = unsafe {
+                                ::wasm_bindgen::convert::FrameStack::new(__wbindgen_frame)
+                            };
+                            ::wasm_bindgen::convert::Stack::push(&mut __stack, 0);
+                            #convert_ret
+                        }
+                        Err(e) => {
+                            unsafe {
+                                ::wasm_bindgen::convert::FrameStack::store_err::<#e>(
+                                    __wbindgen_frame,
+                                    e,
+                                );
+                            }
+                            #err_ret
+                        }
+                    }
+                };
+            }
+            Some(ast::Type::ByMutRef(_))
+            | Some(ast::Type::ByRef(_)) => {
+                panic!("can't throw a borrowed ref");
+            }
+            None => {
+                if self.function.ret.is_some() {
+                    convert_ret = quote! {
+                        let mut __stack = unsafe {
+                            ::wasm_bindgen::convert::FrameStack::new(__wbindgen_frame)
+                        };
+                        #convert_ret
+                    };
                 }
             }
+        }
+
+        let name = self.rust_name;
+        let receiver = match self.class {
+            Some(_) if self.method => quote! { me.#name },
             Some(class) => quote! { #class::#name },
             None => quote!{ #name },
         };
+        let mut call = quote! { #receiver(#(#converted_arguments),*) };
+        // A borrowed return value is copied out in the same expression as
+        // the call, while whatever it borrows from, like the `RefCell`
+        // borrow of `self` for methods, is still alive
+        if let Some(ast::Type::ByRef(_)) = self.function.ret {
+            call = match self.throws {
+                Some(_) => quote! {
+                    ::std::result::Result::map(#call, ::std::borrow::ToOwned::to_owned)
+                },
+                None => quote! { ::std::borrow::ToOwned::to_owned(#call) },
+            };
+        }
+
+        // An `async fn` returns its promise right away, converting the
+        // future's output into the value the promise settles with once it
+        // completes
+        if self.function.opts.async_() {
+            let ok = match self.function.ret {
+                Some(_) => quote! { ::wasm_bindgen::JsValue::from(v) },
+                None => quote! { { let () = v; ::wasm_bindgen::JsValue::undefined() } },
+            };
+            let settle = match self.throws {
+                Some(_) => quote! {
+                    match #ret {
+                        Ok(v) => Ok(#ok),
+                        Err(e) => Err(::wasm_bindgen::JsValue::from(e)),
+                    }
+                },
+                None => quote! { { let v = #ret; Ok(#ok) } },
+            };
+            ret_ty = quote! { -> u32 };
+            convert_ret = quote! {
+                ::wasm_bindgen::future::__export(#ret, |#ret| #settle)
+            };
+            ret_slots.clear();
+        }
 
         let tokens = quote! {
             #[export_name = #export_name]
             #[allow(non_snake_case)]
             pub extern fn #generated_name(#(#args),*) #ret_ty {
                 ::wasm_bindgen::__rt::link_this_library();
+                // Fails to compile if the arguments, or the return value,
+                // need more extra words than there are in a frame
+                let _: [(); ::wasm_bindgen::convert::FRAME_LEN - (0 #(+ #arg_slots)*)];
+                #(let _: [(); ::wasm_bindgen::convert::FRAME_LEN - (#ret_slots)];)*
                 let #ret = {
                     let mut __stack = unsafe {
-                        ::wasm_bindgen::convert::GlobalStack::new()
+                        ::wasm_bindgen::convert::FrameStack::new(__wbindgen_frame)
                     };
                     #(#arg_conversions)*
-                    #receiver(#(#converted_arguments),*)
+                    #call
                 };
                 #convert_ret
             }
@@ -309,8 +541,76 @@ impl ToTokens for ast::ImportType {
     fn to_tokens(&self, tokens: &mut Tokens) {
         let vis = &self.vis;
         let name = &self.name;
+        // Unless duck-typed, imported types get their own descriptor so the
+        // CLI can tell them apart, to check them or give them a TS type
+        let (descriptor, borrowed_descriptor) = if self.structural {
+            (
+                quote! {
+                    <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary>
+                        ::DESCRIPTOR
+                },
+                quote! {
+                    <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::ToRefWasmBoundary>
+                        ::DESCRIPTOR
+                },
+            )
+        } else {
+            let c = self.descriptor();
+            let descriptor = Literal::byte_string(format!("{:8}", c).as_bytes());
+            let borrowed_descriptor = Literal::byte_string(format!("{:8}", c + 1).as_bytes());
+            (
+                quote! {
+                    ::wasm_bindgen::convert::Descriptor { __x: *#descriptor }
+                },
+                quote! {
+                    ::wasm_bindgen::convert::Descriptor { __x: *#borrowed_descriptor }
+                },
+            )
+        };
+        if self.iterator {
+            (quote! {
+                impl IntoIterator for #name {
+                    type Item = ::wasm_bindgen::JsValue;
+                    type IntoIter = ::wasm_bindgen::JsIterator;
+
+                    fn into_iter(self) -> ::wasm_bindgen::JsIterator {
+                        ::wasm_bindgen::JsIterator::new(self.obj)
+                    }
+                }
+            }).to_tokens(tokens);
+        }
+        // Every imported type is a `#[repr(C)]` wrapper around a `JsValue`,
+        // so a reference to one can be reinterpreted as a reference to any
+        // of the types it extends.
+        for (i, base) in self.extends.iter().enumerate() {
+            if i == 0 {
+                (quote! {
+                    impl ::std::ops::Deref for #name {
+                        type Target = #base;
+
+                        fn deref(&self) -> &#base {
+                            unsafe { &*(self as *const #name as *const #base) }
+                        }
+                    }
+                }).to_tokens(tokens);
+            }
+            (quote! {
+                impl AsRef<#base> for #name {
+                    fn as_ref(&self) -> &#base {
+                        unsafe { &*(self as *const #name as *const #base) }
+                    }
+                }
+
+                impl From<#name> for #base {
+                    fn from(obj: #name) -> #base {
+                        <#base as From<::wasm_bindgen::JsValue>>::from(obj.obj)
+                    }
+                }
+            }).to_tokens(tokens);
+        }
         (quote! {
             #[allow(bad_style)]
+            #[repr(C)]
             #vis struct #name {
                 obj: ::wasm_bindgen::JsValue,
             }
@@ -318,9 +618,7 @@ impl ToTokens for ast::ImportType {
             impl ::wasm_bindgen::convert::WasmBoundary for #name {
                 type Abi = <::wasm_bindgen::JsValue as
                     ::wasm_bindgen::convert::WasmBoundary>::Abi;
-                const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor =
-                    <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary>
-                        ::DESCRIPTOR;
+                const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor = #descriptor;
 
                 fn into_abi(self, extra: &mut ::wasm_bindgen::convert::Stack) -> Self::Abi {
                     self.obj.into_abi(extra)
@@ -338,8 +636,7 @@ impl ToTokens for ast::ImportType {
                 type Abi = <::wasm_bindgen::JsValue as
                     ::wasm_bindgen::convert::ToRefWasmBoundary>::Abi;
                 const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor =
-                    <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::ToRefWasmBoundary>
-                        ::DESCRIPTOR;
+                    #borrowed_descriptor;
 
                 fn to_abi_ref(&self, extra: &mut ::wasm_bindgen::convert::Stack) -> u32 {
                     self.obj.to_abi_ref(extra)
@@ -350,8 +647,7 @@ impl ToTokens for ast::ImportType {
                 type Abi = <::wasm_bindgen::JsValue as
                     ::wasm_bindgen::convert::ToRefWasmBoundary>::Abi;
                 const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor =
-                    <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::ToRefWasmBoundary>
-                        ::DESCRIPTOR;
+                    #borrowed_descriptor;
                 type RefAnchor = ::std::mem::ManuallyDrop<#name>;
 
                 unsafe fn from_abi_ref(
@@ -386,10 +682,93 @@ impl ToTokens for ast::ImportKind {
             ast::ImportKind::Function(ref f) => f.to_tokens(tokens),
             ast::ImportKind::Static(ref s) => s.to_tokens(tokens),
             ast::ImportKind::Type(ref t) => t.to_tokens(tokens),
+            ast::ImportKind::Property(ref p) => p.to_tokens(tokens),
+            ast::ImportKind::Enum(ref e) => e.to_tokens(tokens),
         }
     }
 }
 
+impl ToTokens for ast::ImportProperty {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let vis = &self.vis;
+        let attrs = &self.rust_attrs;
+        let class = &self.class_ty;
+        let ty = &self.ty;
+        let rust_name = self.rust_name;
+        let get_shim = self.get_shim;
+        let frame_ptr = syn::Ident::from("__wbindgen_frame");
+
+        let getter = quote! {
+            #(#attrs)*
+            #[allow(bad_style)]
+            #vis fn #rust_name(&self) -> #ty {
+                ::wasm_bindgen::__rt::link_this_library();
+                #[wasm_import_module = "__wbindgen_placeholder__"]
+                extern {
+                    fn #get_shim(this: u32, #frame_ptr: *mut u32)
+                        -> <#ty as ::wasm_bindgen::convert::WasmBoundary>::Abi;
+                }
+                unsafe {
+                    let mut __frame: ::wasm_bindgen::convert::Frame =
+                        [0; ::wasm_bindgen::convert::FRAME_LEN];
+                    let #frame_ptr = __frame.as_mut_ptr();
+                    let _ret = {
+                        let mut __stack = ::wasm_bindgen::convert::FrameStack::new(#frame_ptr);
+                        let this = <#class as ::wasm_bindgen::convert::ToRefWasmBoundary>
+                            ::to_abi_ref(self, &mut __stack);
+                        #get_shim(this, #frame_ptr)
+                    };
+                    <#ty as ::wasm_bindgen::convert::WasmBoundary>
+                        ::from_abi(
+                            _ret,
+                            &mut ::wasm_bindgen::convert::FrameStack::new(#frame_ptr),
+                        )
+                }
+            }
+        };
+
+        let setter = if self.readonly {
+            quote! {}
+        } else {
+            let set_name = syn::Ident::from(format!("set_{}", rust_name));
+            let set_shim = self.set_shim;
+            quote! {
+                #(#attrs)*
+                #[allow(bad_style)]
+                #vis fn #set_name(&self, value: #ty) {
+                    ::wasm_bindgen::__rt::link_this_library();
+                    #[wasm_import_module = "__wbindgen_placeholder__"]
+                    extern {
+                        fn #set_shim(
+                            this: u32,
+                            value: <#ty as ::wasm_bindgen::convert::WasmBoundary>::Abi,
+                            #frame_ptr: *mut u32,
+                        );
+                    }
+                    unsafe {
+                        let mut __frame: ::wasm_bindgen::convert::Frame =
+                            [0; ::wasm_bindgen::convert::FRAME_LEN];
+                        let #frame_ptr = __frame.as_mut_ptr();
+                        let mut __stack = ::wasm_bindgen::convert::FrameStack::new(#frame_ptr);
+                        let this = <#class as ::wasm_bindgen::convert::ToRefWasmBoundary>
+                            ::to_abi_ref(self, &mut __stack);
+                        let value = <#ty as ::wasm_bindgen::convert::WasmBoundary>
+                            ::into_abi(value, &mut __stack);
+                        #set_shim(this, value, #frame_ptr)
+                    }
+                }
+            }
+        };
+
+        (quote! {
+            impl #class {
+                #getter
+                #setter
+            }
+        }).to_tokens(tokens);
+    }
+}
+
 impl ToTokens for ast::ImportFunction {
     fn to_tokens(&self, tokens: &mut Tokens) {
         let mut class_ty = None;
@@ -399,7 +778,8 @@ impl ToTokens for ast::ImportFunction {
                 is_method = true;
                 class_ty = Some(ty);
             }
-            ast::ImportFunctionKind::JsConstructor { ref ty, .. } => {
+            ast::ImportFunctionKind::JsConstructor { ref ty, .. } |
+            ast::ImportFunctionKind::StaticMethod { ref ty, .. } => {
                 class_ty = Some(ty);
             }
             ast::ImportFunctionKind::Normal => {}
@@ -411,7 +791,9 @@ impl ToTokens for ast::ImportFunction {
         let mut abi_argument_names = Vec::new();
         let mut abi_arguments = Vec::new();
         let mut arg_conversions = Vec::new();
+        let mut arg_slots = Vec::new();
         let ret_ident = syn::Ident::from("_ret");
+        let frame_ptr = syn::Ident::from("__wbindgen_frame");
 
         let names = self.function
             .rust_decl
@@ -436,7 +818,7 @@ impl ToTokens for ast::ImportFunction {
                 ast::Type::ByValue(ref t) => {
                     abi_argument_names.push(name);
                     abi_arguments.push(quote! {
-                        #name: <#t as ::wasm_bindgen::convert::WasmBoundary>::Abi
+                        #name: <#t as ::wasm_bindgen::convert::ToWasmBoundary>::Abi
                     });
                     let var = if i == 0 && is_method {
                         quote! { self }
@@ -444,8 +826,11 @@ impl ToTokens for ast::ImportFunction {
                         quote! { #name }
                     };
                     arg_conversions.push(quote! {
-                        let #name = <#t as ::wasm_bindgen::convert::WasmBoundary>
-                            ::into_abi(#var, &mut __stack);
+                        let #name = <#t as ::wasm_bindgen::convert::ToWasmBoundary>
+                            ::to_abi(#var, &mut __stack);
+                    });
+                    arg_slots.push(quote! {
+                        <#t as ::wasm_bindgen::convert::ToWasmBoundary>::STACK_SLOTS
                     });
                 }
                 ast::Type::ByMutRef(_) => panic!("urgh mut"),
@@ -457,25 +842,34 @@ impl ToTokens for ast::ImportFunction {
                     } else {
                         quote! { #name }
                     };
+                    // `Self` is left to be inferred as naming a trait object
+                    // like `&Fn()` here would require it to be `'static`
                     arg_conversions.push(quote! {
-                        let #name = <#t as ::wasm_bindgen::convert::ToRefWasmBoundary>
+                        let #name = ::wasm_bindgen::convert::ToRefWasmBoundary
                             ::to_abi_ref(#var, &mut __stack);
                     });
+                    arg_slots.push(quote! {
+                        <#t as ::wasm_bindgen::convert::ToRefWasmBoundary>::STACK_SLOTS
+                    });
                 }
             }
         }
         let abi_ret;
         let mut convert_ret;
+        let mut ret_slots = quote! { 0 };
         match self.function.ret {
             Some(ast::Type::ByValue(ref t)) => {
                 abi_ret = quote! {
                     <#t as ::wasm_bindgen::convert::WasmBoundary>::Abi
                 };
+                ret_slots = quote! {
+                    <#t as ::wasm_bindgen::convert::WasmBoundary>::STACK_SLOTS
+                };
                 convert_ret = quote! {
                     <#t as ::wasm_bindgen::convert::WasmBoundary>
                         ::from_abi(
                             #ret_ident,
-                            &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                            &mut ::wasm_bindgen::convert::FrameStack::new(#frame_ptr),
                         )
                 };
             }
@@ -494,13 +888,11 @@ impl ToTokens for ast::ImportFunction {
             abi_argument_names.push(exn_data_ptr);
             abi_arguments.push(quote! { #exn_data_ptr: *mut u32 });
             convert_ret = quote! { Ok(#convert_ret) };
+            // The first word is 0 if nothing was thrown and otherwise says
+            // where the exception came from.
             exceptional_ret = quote! {
-                if #exn_data[0] == 1 {
-                    return Err(
-                        <
-                            ::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary
-                        >::from_abi(#exn_data[1], &mut ::wasm_bindgen::convert::GlobalStack::new()),
-                    )
+                if #exn_data[0] != 0 {
+                    return Err(::wasm_bindgen::__rt::caught_exception(#exn_data[0], #exn_data[1]))
                 }
             };
             quote! {
@@ -510,6 +902,9 @@ impl ToTokens for ast::ImportFunction {
         } else {
             quote! {}
         };
+        // The frame for extra words always comes last
+        abi_argument_names.push(frame_ptr);
+        abi_arguments.push(quote! { #frame_ptr: *mut u32 });
 
         let rust_name = self.rust_name;
         let import_name = self.shim;
@@ -533,14 +928,24 @@ impl ToTokens for ast::ImportFunction {
             #[allow(bad_style)]
             #vis extern #fn_token #rust_name(#me #(#arguments),*) #ret {
                 ::wasm_bindgen::__rt::link_this_library();
+                // Zero-sized arguments are declared as `()`, which isn't
+                // otherwise allowed in an `extern` block
                 #[wasm_import_module = "__wbindgen_placeholder__"]
+                #[allow(improper_ctypes)]
                 extern {
                     fn #import_name(#(#abi_arguments),*) -> #abi_ret;
                 }
+                // Fails to compile if the arguments, or the return value,
+                // need more extra words than there are in a frame
+                let _: [(); ::wasm_bindgen::convert::FRAME_LEN - (0 #(+ #arg_slots)*)];
+                let _: [(); ::wasm_bindgen::convert::FRAME_LEN - (#ret_slots)];
                 unsafe {
                     #exn_data
+                    let mut __frame: ::wasm_bindgen::convert::Frame =
+                        [0; ::wasm_bindgen::convert::FRAME_LEN];
+                    let #frame_ptr = __frame.as_mut_ptr();
                     let #ret_ident = {
-                        let mut __stack = ::wasm_bindgen::convert::GlobalStack::new();
+                        let mut __stack = ::wasm_bindgen::convert::FrameStack::new(#frame_ptr);
                         #(#arg_conversions)*
                         #import_name(#(#abi_argument_names),*)
                     };
@@ -565,7 +970,7 @@ impl ToTokens for ast::ImportFunction {
 impl ToTokens for ast::Enum {
     fn to_tokens(&self, into: &mut Tokens) {
         let enum_name = &self.name;
-        let descriptor = format!("{:4}", shared::TYPE_ENUM);
+        let descriptor = format!("{:8}", shared::TYPE_ENUM);
         let descriptor = Literal::byte_string(descriptor.as_bytes());
         let incoming_u32 = quote! { n };
         let enum_name_as_string = enum_name.to_string();
@@ -608,6 +1013,45 @@ impl ToTokens for ast::Enum {
     }
 }
 
+impl ToTokens for ast::ImportEnum {
+    fn to_tokens(&self, into: &mut Tokens) {
+        let name = &self.name;
+        let c = shared::name_to_descriptor(name.as_ref());
+        let descriptor = Literal::byte_string(format!("{:8}", c).as_bytes());
+        // Variants are passed by their index, which JS checks is in range
+        let variants = self.variants.iter()
+            .map(|&(v, _)| v)
+            .chain(self.unknown)
+            .collect::<Vec<_>>();
+        let indices = (0..variants.len() as u32).collect::<Vec<_>>();
+        let names = variants.iter().map(|_| name);
+        let message = format!("invalid value passed for `{}`", name);
+        (quote! {
+            impl ::wasm_bindgen::convert::WasmBoundary for #name {
+                type Abi = u32;
+                const DESCRIPTOR: ::wasm_bindgen::convert::Descriptor =
+                    ::wasm_bindgen::convert::Descriptor {
+                        __x: *#descriptor,
+                    };
+
+                fn into_abi(self, _extra: &mut ::wasm_bindgen::convert::Stack) -> u32 {
+                    self as u32
+                }
+
+                unsafe fn from_abi(
+                    js: u32,
+                    _extra: &mut ::wasm_bindgen::convert::Stack,
+                ) -> Self {
+                    match js {
+                        #(#indices => #names::#variants,)*
+                        _ => ::wasm_bindgen::throw(#message),
+                    }
+                }
+            }
+        }).to_tokens(into);
+    }
+}
+
 impl ToTokens for ast::ImportStatic {
     fn to_tokens(&self, into: &mut Tokens) {
         let name = self.rust_name;
@@ -625,7 +1069,7 @@ impl ToTokens for ast::ImportStatic {
                     unsafe {
                         ::wasm_bindgen::convert::WasmBoundary::from_abi(
                             #shim_name(),
-                            &mut ::wasm_bindgen::convert::GlobalStack::new(),
+                            &mut ::wasm_bindgen::convert::FrameStack::empty(),
                         )
                     }
                 }
@@ -1,7 +1,8 @@
 use ast;
 use quote::{ToTokens, Tokens};
 use shared;
-use std::collections::BTreeSet;
+use syn;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub struct LiteralBuilder<'a> {
     dst: &'a mut Tokens,
@@ -31,7 +32,14 @@ impl<'a> LiteralBuilder<'a> {
 
     fn str(&mut self, s: &str) {
         self.append("\"");
-        self.append(s);
+        for c in s.chars() {
+            match c {
+                '"' => self.append("\\\""),
+                '\\' => self.append("\\\\"),
+                c if (c as u32) < 0x20 => self.append(&format!("\\u{:04x}", c as u32)),
+                c => self.append(c.encode_utf8(&mut [0; 4])),
+            }
+        }
         self.append("\"");
     }
 
@@ -53,8 +61,12 @@ impl<'a> LiteralBuilder<'a> {
             ,(#tokens).__x[1]
             ,(#tokens).__x[2]
             ,(#tokens).__x[3]
+            ,(#tokens).__x[4]
+            ,(#tokens).__x[5]
+            ,(#tokens).__x[6]
+            ,(#tokens).__x[7]
         }).to_tokens(self.dst);
-        self.cnt += 4;
+        self.cnt += 8;
     }
 
     pub fn fields(&mut self, fields: &[(&str, &Fn(&mut Self))]) {
@@ -98,18 +110,34 @@ pub trait Literal {
     fn literal(&self, a: &mut LiteralBuilder);
 }
 
+// Everything embedded has to come out the same on every build of the same
+// source, so lists are either in source order or sorted by name and nothing
+// here iterates a hash map.
 impl Literal for ast::Program {
     fn literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
             ("exports", &|a| a.list_of(&self.exports)),
             ("imports", &|a| a.list_of(&self.imports)),
             ("enums", &|a| a.list_of(&self.enums)),
+            ("structs", &|a| a.list_of(&self.structs)),
             ("custom_type_names", &|a| {
                 let names = self.exports
                     .iter()
                     .filter_map(|e| e.class)
                     .chain(self.structs.iter().map(|s| s.name))
                     .collect::<BTreeSet<_>>();
+                let imported = self.imports.iter().filter_map(|i| match i.kind {
+                    ast::ImportKind::Type(ref t) if !t.structural => {
+                        Some((t.descriptor(), t.name))
+                    }
+                    ast::ImportKind::Enum(ref e) => {
+                        Some((shared::name_to_descriptor(e.name.as_ref()), e.name))
+                    }
+                    _ => None,
+                });
+                let exported = names.iter()
+                    .map(|n| (shared::name_to_descriptor(n.as_ref()), *n));
+                check_descriptor_collisions(exported.chain(imported));
                 a.list(&names, |s, a| {
                     let val = shared::name_to_descriptor(s.as_ref());
                     a.fields(&[
@@ -126,32 +154,78 @@ impl Literal for ast::Program {
 
 impl Literal for ast::Function {
     fn literal(&self, a: &mut LiteralBuilder) {
-        a.fields(&[
-            ("name", &|a| a.str(self.name.as_ref())),
-            ("arguments", &|a| a.list_of(&self.arguments)),
-            ("ret", &|a| match self.ret {
-                Some(ref s) => s.literal(a),
-                None => a.append("null"),
-            }),
-        ]);
+        function_literal(self, a, false)
     }
 }
 
+/// Arguments passed to imports are converted with `ToWasmBoundary` and
+/// `ToRefWasmBoundary`, rather than `WasmBoundary` and `FromRefWasmBoundary`
+/// as for exports, so those are the descriptors used for them. Likewise
+/// exports return values with `ToWasmBoundary`.
+fn function_literal(f: &ast::Function, a: &mut LiteralBuilder, import: bool) {
+    a.fields(&[
+        ("name", &|a| a.str(&f.name)),
+        ("arguments", &|a| a.list(&f.arguments, |ty, a| type_literal(ty, a, import))),
+        ("argument_names", &|a| a.list(&f.argument_names, |s, a| a.str(s))),
+        ("nullable", &|a| a.list(&f.nullable, |n, a| a.bool(*n))),
+        ("ret", &|a| match f.ret {
+            // Exports copy borrowed return values out into their owned
+            // version, like a `String` for a `&str`
+            Some(ast::Type::ByRef(ref t)) if !import => a.as_char(quote! {
+                <<#t as ::std::borrow::ToOwned>::Owned
+                    as ::wasm_bindgen::convert::ToWasmBoundary>::DESCRIPTOR
+            }),
+            Some(ast::Type::ByValue(ref t)) if !import => a.as_char(quote! {
+                <#t as ::wasm_bindgen::convert::ToWasmBoundary>::DESCRIPTOR
+            }),
+            Some(ref s) => s.literal(a),
+            None => a.append("null"),
+        }),
+    ]);
+}
+
 impl Literal for ast::Type {
     fn literal(&self, a: &mut LiteralBuilder) {
-        match *self {
-            ast::Type::ByValue(ref t) => {
-                a.as_char(quote! {
-                    <#t as ::wasm_bindgen::convert::WasmBoundary>::DESCRIPTOR
-                });
-            }
-            ast::Type::ByRef(ref ty) | ast::Type::ByMutRef(ref ty) => {
-                // TODO: this assumes `ToRef*` and `FromRef*` use the same
-                // descriptor.
-                a.as_char(quote! {
-                    <#ty as ::wasm_bindgen::convert::FromRefWasmBoundary>::DESCRIPTOR
-                });
-            }
+        type_literal(self, a, false)
+    }
+}
+
+fn type_literal(ty: &ast::Type, a: &mut LiteralBuilder, import: bool) {
+    match *ty {
+        ast::Type::ByValue(ref t) if import => {
+            a.as_char(quote! {
+                <#t as ::wasm_bindgen::convert::ToWasmBoundary>::DESCRIPTOR
+            });
+        }
+        ast::Type::ByValue(ref t) if ast::is_cow_str(t) => {
+            a.as_char(quote! {
+                <str as ::wasm_bindgen::convert::FromRefWasmBoundary>::DESCRIPTOR
+            });
+        }
+        ast::Type::ByValue(ref t) if ast::is_option_str(t) => {
+            a.as_char(quote! {
+                ::wasm_bindgen::convert::OptionStrAnchor::DESCRIPTOR
+            });
+        }
+        ast::Type::ByValue(ref t) => {
+            a.as_char(quote! {
+                <#t as ::wasm_bindgen::convert::WasmBoundary>::DESCRIPTOR
+            });
+        }
+        ast::Type::ByRef(ref ty) if import => {
+            a.as_char(quote! {
+                <#ty as ::wasm_bindgen::convert::ToRefWasmBoundary>::DESCRIPTOR
+            });
+        }
+        ast::Type::ByRef(ref ty) => {
+            a.as_char(quote! {
+                <#ty as ::wasm_bindgen::convert::FromRefWasmBoundary>::DESCRIPTOR
+            });
+        }
+        ast::Type::ByMutRef(ref ty) => {
+            a.as_char(quote! {
+                <#ty as ::wasm_bindgen::convert::FromRefMutWasmBoundary>::DESCRIPTOR
+            });
         }
     }
 }
@@ -164,7 +238,22 @@ impl Literal for ast::Export {
                 None => a.append("null"),
             }),
             ("method", &|a| a.bool(self.method)),
+            ("consume", &|a| a.bool(self.consume)),
+            ("catch", &|a| a.bool(self.function.opts.catch())),
+            ("start", &|a| a.bool(self.function.opts.start())),
+            ("constructor", &|a| a.bool(self.function.opts.constructor())),
+            ("async_", &|a| a.bool(self.function.opts.async_())),
             ("function", &|a| self.function.literal(a)),
+            ("rust_name", &|a| a.str(self.rust_name.as_ref())),
+            ("packed_bools", &|a| {
+                a.list(&self.function.packed_bools(), |&(start, len), a| {
+                    a.append(&format!("[{},{}]", start, len))
+                })
+            }),
+            ("throws", &|a| match self.throws {
+                Some(ref t) => t.literal(a),
+                None => a.append("null"),
+            }),
         ]);
     }
 }
@@ -177,7 +266,7 @@ impl Literal for ast::Import {
                 None => a.append("null"),
             }),
             ("js_namespace", &|a| match self.js_namespace {
-                Some(ref s) => a.str(s.as_ref()),
+                Some(ref path) => a.list(path, |s, a| a.str(s)),
                 None => a.append("null"),
             }),
             ("kind", &|a| self.kind.literal(a)),
@@ -191,6 +280,8 @@ impl Literal for ast::ImportKind {
             ast::ImportKind::Function(ref f) => f.literal(a),
             ast::ImportKind::Static(ref s) => s.literal(a),
             ast::ImportKind::Type(ref t) => t.literal(a),
+            ast::ImportKind::Property(ref p) => p.literal(a),
+            ast::ImportKind::Enum(ref e) => e.literal(a),
         }
     }
 }
@@ -209,12 +300,18 @@ impl Literal for ast::ImportFunction {
                 js_new = true;
                 class_name = Some(class);
             }
+            // Neither `method` nor `js_new` means `class.function(...)`
+            ast::ImportFunctionKind::StaticMethod { ref class, .. } => {
+                class_name = Some(class);
+            }
             ast::ImportFunctionKind::Normal => {}
         }
 
         let mut getter = None;
         let mut setter = None;
         let structural = self.function.opts.structural();
+        let final_ = self.function.opts.final_();
+        let variadic = self.function.opts.variadic();
 
         if let Some(s) = self.function.opts.getter() {
             let s = s.map(|s| s.to_string());
@@ -230,6 +327,27 @@ impl Literal for ast::ImportFunction {
             ("method", &|a| a.bool(method)),
             ("js_new", &|a| a.bool(js_new)),
             ("structural", &|a| a.bool(structural)),
+            ("final_", &|a| a.bool(final_)),
+            ("symbol", &|a| match self.function.opts.symbol() {
+                Some(&ast::JsSymbol::WellKnown(ref name)) => {
+                    a.fields(&[("well_known", &|a| a.str(name.as_ref()))])
+                }
+                Some(&ast::JsSymbol::Registered(ref key)) => {
+                    a.fields(&[("registered", &|a| a.str(key))])
+                }
+                None => a.append("null"),
+            }),
+            ("variadic", &|a| a.bool(variadic)),
+            ("indexing", &|a| match self.function.opts.indexing() {
+                Some(ast::Indexing::Getter) => a.str("getter"),
+                Some(ast::Indexing::Setter) => a.str("setter"),
+                Some(ast::Indexing::Deleter) => a.str("deleter"),
+                None => a.append("null"),
+            }),
+            ("returns_self", &|a| a.bool(self.returns_self())),
+            ("late_bound", &|a| a.bool(self.function.opts.late_bound())),
+            ("pure", &|a| a.bool(self.function.opts.pure())),
+            ("this_arg", &|a| a.bool(self.function.opts.this_arg())),
             ("shim", &|a| a.str(self.shim.as_ref())),
             ("getter", &|a| match getter {
                 Some(ref s) => a.str(s),
@@ -239,7 +357,7 @@ impl Literal for ast::ImportFunction {
                 Some(ref s) => a.str(s),
                 None => a.append("null"),
             }),
-            ("function", &|a| self.function.literal(a)),
+            ("function", &|a| function_literal(&self.function, a, true)),
             ("class", &|a| match class_name {
                 Some(s) => a.str(s),
                 None => a.append("null"),
@@ -248,6 +366,45 @@ impl Literal for ast::ImportFunction {
     }
 }
 
+/// Custom types are told apart in JS by their descriptor, which is a hash of
+/// their name, so two types hashing to the same descriptor can't coexist.
+fn check_descriptor_collisions<I>(types: I)
+where
+    I: IntoIterator<Item = (u32, syn::Ident)>,
+{
+    let mut seen = BTreeMap::new();
+    for (descriptor, name) in types {
+        match seen.insert(descriptor, name) {
+            Some(prev) if prev != name => panic!(
+                "the types `{}` and `{}` are both assigned the descriptor {}, \
+                 try renaming one of them",
+                prev, name, descriptor
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl Literal for ast::Struct {
+    fn literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("fields", &|a| a.list_of(&self.fields)),
+            ("js_class_aliases", &|a| a.list(&self.js_class_aliases, |s, a| a.str(s.as_ref()))),
+        ]);
+    }
+}
+
+impl Literal for ast::StructField {
+    fn literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("readonly", &|a| a.bool(self.readonly)),
+            ("ty", &|a| ast::Type::ByValue(self.ty.clone()).literal(a)),
+        ]);
+    }
+}
+
 impl Literal for ast::Enum {
     fn literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
@@ -261,6 +418,10 @@ impl Literal for ast::Variant {
     fn literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
             ("name", &|a| a.str(self.name.as_ref())),
+            ("js_name", &|a| match self.js_name {
+                Some(ref s) => a.str(s),
+                None => a.append("null"),
+            }),
             ("value", &|a| a.append(&format!("{}", self.value))),
         ])
     }
@@ -270,14 +431,60 @@ impl Literal for ast::ImportStatic {
     fn literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
             ("kind", &|a| a.str("static")),
-            ("name", &|a| a.str(self.js_name.as_ref())),
+            ("name", &|a| a.str(&self.js_name)),
             ("shim", &|a| a.str(self.shim.as_ref())),
+            ("lazy", &|a| a.bool(self.lazy)),
+        ])
+    }
+}
+
+impl Literal for ast::ImportProperty {
+    fn literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("kind", &|a| a.str("property")),
+            ("class", &|a| a.str(&self.class)),
+            ("name", &|a| a.str(&self.js_name)),
+            ("this", &|a| type_literal(&ast::Type::ByRef(self.class_ty.clone()), a, true)),
+            ("ty", &|a| ast::Type::ByValue(self.ty.clone()).literal(a)),
+            ("get_shim", &|a| a.str(self.get_shim.as_ref())),
+            ("set_shim", &|a| if self.readonly {
+                a.append("null")
+            } else {
+                a.str(self.set_shim.as_ref())
+            }),
+        ])
+    }
+}
+
+impl Literal for ast::ImportEnum {
+    fn literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("kind", &|a| a.str("enum")),
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("descriptor", &|a| a.u32(shared::name_to_descriptor(self.name.as_ref()))),
+            ("variants", &|a| a.list(&self.variants, |&(_, ref js), a| a.str(js))),
+            ("non_exhaustive", &|a| a.bool(self.unknown.is_some())),
         ])
     }
 }
 
 impl Literal for ast::ImportType {
     fn literal(&self, a: &mut LiteralBuilder) {
-        a.fields(&[("kind", &|a| a.str("type"))])
+        a.fields(&[
+            ("kind", &|a| a.str("type")),
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("descriptor", &|a| if self.structural {
+                a.append("null")
+            } else {
+                a.u32(self.descriptor())
+            }),
+            ("iterator", &|a| a.bool(self.iterator)),
+            ("instanceof", &|a| a.bool(self.instanceof)),
+            ("extends", &|a| a.list(&self.extends, |s, a| a.str(s.as_ref()))),
+            ("typescript_type", &|a| match self.typescript_type {
+                Some(ref s) => a.str(s),
+                None => a.append("null"),
+            }),
+        ])
     }
 }
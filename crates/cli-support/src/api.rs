@@ -0,0 +1,288 @@
+//! A JSON description of a module's API for documentation tooling.
+//!
+//! Unlike the descriptions `#[wasm_bindgen]` embeds in the wasm this is meant
+//! to be read by other tools, so types are written out as Rust types like
+//! `Option<u32>` rather than as descriptors, and nothing about shims or how
+//! values are passed is included. The format is versioned on its own by
+//! `API_SCHEMA_VERSION`, which only changes when the format itself does.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use shared;
+
+/// Version of the format written by `api_schema`, bumped whenever an existing
+/// field changes meaning or goes away.
+pub const API_SCHEMA_VERSION: u32 = 1;
+
+/// Describes everything exported and imported by `programs`.
+pub fn api_schema(programs: &[shared::Program]) -> Value {
+    let mut names = HashMap::new();
+    for program in programs {
+        for c in program.custom_type_names.iter() {
+            names.insert(c.descriptor, c.name.clone());
+        }
+        for import in program.imports.iter() {
+            match import.kind {
+                shared::ImportKind::Type(ref t) => {
+                    if let Some(descriptor) = t.descriptor {
+                        names.insert(descriptor, t.name.clone());
+                    }
+                }
+                shared::ImportKind::Enum(ref e) => {
+                    names.insert(e.descriptor, e.name.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+    let types = TypeNames { names };
+
+    let mut functions = Vec::new();
+    let mut classes = Vec::new();
+    let mut class_idx = HashMap::new();
+    let mut enums = Vec::new();
+    let mut imports = Vec::new();
+    {
+        // Structs and their methods may come from different programs, so
+        // each class is created by whichever comes first
+        let mut class = |classes: &mut Vec<Value>, name: &str| -> usize {
+            *class_idx.entry(name.to_string()).or_insert_with(|| {
+                classes.push(json!({
+                    "name": name,
+                    "aliases": [],
+                    "fields": [],
+                    "constructor": null,
+                    "methods": [],
+                }));
+                classes.len() - 1
+            })
+        };
+        for program in programs {
+            for s in program.structs.iter() {
+                let fields = s.fields.iter()
+                    .map(|f| json!({
+                        "name": f.name,
+                        "type": types.name(f.ty),
+                        "readonly": f.readonly,
+                    }))
+                    .collect::<Vec<_>>();
+                let i = class(&mut classes, &s.name);
+                classes[i]["aliases"] = json!(s.js_class_aliases);
+                classes[i]["fields"] = Value::Array(fields);
+            }
+            for export in program.exports.iter() {
+                if export.start {
+                    continue
+                }
+                let mut f = types.function(&export.function, export.throws);
+                f.insert("async".to_string(), Value::Bool(export.async_));
+                let class_name = match export.class {
+                    Some(ref c) => c,
+                    None => {
+                        functions.push(Value::Object(f));
+                        continue
+                    }
+                };
+                let i = class(&mut classes, class_name);
+                if export.constructor {
+                    classes[i]["constructor"] = Value::Object(f);
+                } else {
+                    f.insert("static".to_string(), Value::Bool(!export.method));
+                    classes[i]["methods"].as_array_mut().unwrap().push(Value::Object(f));
+                }
+            }
+            for e in program.enums.iter() {
+                let variants = e.variants.iter()
+                    .map(|v| json!({ "name": v.name, "value": v.value }))
+                    .collect::<Vec<_>>();
+                enums.push(json!({ "name": e.name, "variants": variants }));
+            }
+            for import in program.imports.iter() {
+                imports.push(types.import(import));
+            }
+        }
+    }
+
+    json!({
+        "api_schema_version": API_SCHEMA_VERSION,
+        "functions": functions,
+        "classes": classes,
+        "enums": enums,
+        "imports": imports,
+    })
+}
+
+struct TypeNames {
+    names: HashMap<u32, String>,
+}
+
+impl TypeNames {
+    fn function(&self, f: &shared::Function, throws: Option<shared::Type>) -> Map<String, Value> {
+        let arguments = f.arguments.iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                let name = match f.argument_names.get(i) {
+                    Some(name) => name.clone(),
+                    None => format!("arg{}", i),
+                };
+                json!({ "name": name, "type": self.name(*ty) })
+            })
+            .collect::<Vec<_>>();
+        let mut ret = Map::new();
+        ret.insert("name".to_string(), Value::String(f.name.clone()));
+        ret.insert("arguments".to_string(), Value::Array(arguments));
+        ret.insert("returns".to_string(), self.opt_name(f.ret));
+        ret.insert("throws".to_string(), self.opt_name(throws));
+        ret
+    }
+
+    fn import(&self, import: &shared::Import) -> Value {
+        let mut ret = match import.kind {
+            shared::ImportKind::Function(ref f) => {
+                let mut ret = self.function(&f.function, None);
+                ret.insert("kind".to_string(), Value::String("function".to_string()));
+                ret.insert("class".to_string(), json!(f.class));
+                ret.insert("method".to_string(), Value::Bool(f.method));
+                ret.insert("constructor".to_string(), Value::Bool(f.js_new));
+                ret.insert("catch".to_string(), Value::Bool(f.catch));
+                ret.insert("this_arg".to_string(), Value::Bool(f.this_arg));
+                ret.insert("getter".to_string(), json!(f.getter));
+                ret.insert("setter".to_string(), json!(f.setter));
+                ret
+            }
+            shared::ImportKind::Static(ref s) => {
+                let mut ret = Map::new();
+                ret.insert("kind".to_string(), Value::String("static".to_string()));
+                ret.insert("name".to_string(), Value::String(s.name.clone()));
+                ret
+            }
+            shared::ImportKind::Type(ref t) => {
+                let mut ret = Map::new();
+                ret.insert("kind".to_string(), Value::String("type".to_string()));
+                ret.insert("name".to_string(), Value::String(t.name.clone()));
+                ret.insert("extends".to_string(), json!(t.extends));
+                ret
+            }
+            shared::ImportKind::Property(ref p) => {
+                let mut ret = Map::new();
+                ret.insert("kind".to_string(), Value::String("property".to_string()));
+                ret.insert("class".to_string(), Value::String(p.class.clone()));
+                ret.insert("name".to_string(), Value::String(p.name.clone()));
+                ret.insert("type".to_string(), self.name(p.ty));
+                ret.insert("readonly".to_string(), Value::Bool(p.set_shim.is_none()));
+                ret
+            }
+            shared::ImportKind::Enum(ref e) => {
+                let mut ret = Map::new();
+                ret.insert("kind".to_string(), Value::String("enum".to_string()));
+                ret.insert("name".to_string(), Value::String(e.name.clone()));
+                ret.insert("variants".to_string(), json!(e.variants));
+                ret.insert("non_exhaustive".to_string(), Value::Bool(e.non_exhaustive));
+                ret
+            }
+        };
+        ret.insert("module".to_string(), json!(import.module));
+        ret.insert("namespace".to_string(), json!(import.js_namespace));
+        Value::Object(ret)
+    }
+
+    fn opt_name(&self, ty: Option<shared::Type>) -> Value {
+        match ty {
+            Some(ty) => self.name(ty),
+            None => Value::Null,
+        }
+    }
+
+    fn name(&self, ty: shared::Type) -> Value {
+        Value::String(self.rust_type(ty))
+    }
+
+    /// The Rust type described by `ty`. Types sharing a descriptor, like
+    /// `i32` and `isize`, are all given the name of the most common one.
+    fn rust_type(&self, ty: shared::Type) -> String {
+        if ty >= shared::TYPE_OPTION_OFFSET {
+            return format!("Option<{}>", self.rust_type(ty - shared::TYPE_OPTION_OFFSET))
+        }
+        if let Some(elem) = shared::unwrap_vector_descriptor(ty) {
+            return format!("Vec<{}>", self.rust_type(elem))
+        }
+        if let Some((len, elem)) = shared::unwrap_array_descriptor(ty) {
+            let elem = self.rust_type(elem);
+            return format!("[{}; {}]",
+                           elem.trim_start_matches("Vec<").trim_end_matches('>'),
+                           len)
+        }
+        let name = match ty {
+            shared::TYPE_VECTOR_JSVALUE => "Vec<JsValue>",
+            shared::TYPE_ENUM => "enum",
+            shared::TYPE_NUMBER => "i32",
+            shared::TYPE_BORROWED_STR => "&str",
+            shared::TYPE_STRING => "String",
+            shared::TYPE_BOOLEAN => "bool",
+            shared::TYPE_SLICE_U8 => "&[u8]",
+            shared::TYPE_VECTOR_U8 => "Vec<u8>",
+            shared::TYPE_SLICE_I8 => "&[i8]",
+            shared::TYPE_VECTOR_I8 => "Vec<i8>",
+            shared::TYPE_SLICE_U16 => "&[u16]",
+            shared::TYPE_VECTOR_U16 => "Vec<u16>",
+            shared::TYPE_SLICE_I16 => "&[i16]",
+            shared::TYPE_VECTOR_I16 => "Vec<i16>",
+            shared::TYPE_SLICE_U32 => "&[u32]",
+            shared::TYPE_VECTOR_U32 => "Vec<u32>",
+            shared::TYPE_SLICE_I32 => "&[i32]",
+            shared::TYPE_VECTOR_I32 => "Vec<i32>",
+            shared::TYPE_SLICE_F32 => "&[f32]",
+            shared::TYPE_VECTOR_F32 => "Vec<f32>",
+            shared::TYPE_SLICE_F64 => "&[f64]",
+            shared::TYPE_VECTOR_F64 => "Vec<f64>",
+            shared::TYPE_JS_OWNED => "JsValue",
+            shared::TYPE_JS_REF => "&JsValue",
+            shared::TYPE_F32 => "f32",
+            shared::TYPE_F64 => "f64",
+            shared::TYPE_I64 => "i64",
+            shared::TYPE_U64 => "u64",
+            shared::TYPE_SLICE_JSVALUE => "&[JsValue]",
+            shared::TYPE_CHAR => "char",
+            shared::TYPE_SLICE_MUT_U8 => "&mut [u8]",
+            shared::TYPE_SLICE_MUT_I8 => "&mut [i8]",
+            shared::TYPE_SLICE_MUT_U16 => "&mut [u16]",
+            shared::TYPE_SLICE_MUT_I16 => "&mut [i16]",
+            shared::TYPE_SLICE_MUT_U32 => "&mut [u32]",
+            shared::TYPE_SLICE_MUT_I32 => "&mut [i32]",
+            shared::TYPE_SLICE_MUT_F32 => "&mut [f32]",
+            shared::TYPE_SLICE_MUT_F64 => "&mut [f64]",
+            shared::TYPE_U32 => "u32",
+            shared::TYPE_PROMISE => "JsFuture",
+            shared::TYPE_ITERATOR => "impl Iterator",
+            shared::TYPE_DATE => "JsDate",
+            shared::TYPE_I8 => "i8",
+            shared::TYPE_U8 => "u8",
+            shared::TYPE_I16 => "i16",
+            shared::TYPE_U16 => "u16",
+            shared::TYPE_NONZERO_U32 => "NonZeroU32",
+            shared::TYPE_NONZERO_U64 => "NonZeroU64",
+            shared::TYPE_UNIT => "()",
+            shared::TYPE_STACK_CLOSURE => "&Fn",
+            shared::TYPE_BOXED_SLICE_U8 => "Box<[u8]>",
+            shared::TYPE_BOXED_SLICE_I8 => "Box<[i8]>",
+            shared::TYPE_BOXED_SLICE_U16 => "Box<[u16]>",
+            shared::TYPE_BOXED_SLICE_I16 => "Box<[i16]>",
+            shared::TYPE_BOXED_SLICE_U32 => "Box<[u32]>",
+            shared::TYPE_BOXED_SLICE_I32 => "Box<[i32]>",
+            shared::TYPE_BOXED_SLICE_F32 => "Box<[f32]>",
+            shared::TYPE_BOXED_SLICE_F64 => "Box<[f64]>",
+            shared::TYPE_BOXED_SLICE_JSVALUE => "Box<[JsValue]>",
+            shared::TYPE_CLOSURE => "&Closure",
+            _ => {
+                let flag = ty & shared::TYPE_CUSTOM_REF_FLAG;
+                return match self.names.get(&(ty & !flag)) {
+                    Some(name) if flag != 0 => format!("&{}", name),
+                    Some(name) => name.clone(),
+                    None => format!("<unknown descriptor {}>", ty),
+                }
+            }
+        };
+        name.to_string()
+    }
+}
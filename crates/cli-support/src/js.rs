@@ -5,7 +5,7 @@ use std::mem;
 use shared;
 use parity_wasm::elements::*;
 
-use super::Bindgen;
+use super::{Bindgen, Error};
 
 pub struct Context<'a> {
     pub globals: String,
@@ -18,13 +18,58 @@ pub struct Context<'a> {
     pub module: &'a mut Module,
     pub custom_type_names: HashMap<u32, String>,
     pub imported_names: HashSet<String>,
+    pub imported_types: HashMap<u32, ImportedType>,
+    pub imported_enums: HashMap<u32, ImportedEnum>,
+    /// Names of the namespace objects modules are imported as for lazy
+    /// imports, keyed by module
+    pub lazy_modules: HashMap<String, String>,
+    /// Names the default exports of modules are imported as, keyed by module
+    pub default_imports: HashMap<String, String>,
     pub exported_classes: HashMap<String, ExportedClass>,
+    /// Names of the methods `new` calls on exported classes, keyed by class
+    pub constructors: HashMap<String, String>,
+    /// The exported class which is also the module's default export
+    pub default_export: Option<String>,
+    /// Whether some function is marked `#[wasm_bindgen(start)]`
+    pub start: bool,
+    /// What each imported function's shim calls in JS, keyed by shim
+    pub import_shims: HashMap<String, String>,
+    /// Names of the helpers already generated for `share_conversions`
+    pub shared_conversions: HashSet<String>,
+}
+
+pub struct ImportedType {
+    pub module: Option<String>,
+    pub js_namespace: Option<Vec<String>>,
+    pub name: String,
+    pub iterator: bool,
+    pub instanceof: bool,
+    pub typescript_type: Option<String>,
+}
+
+pub struct ImportedEnum {
+    pub module: Option<String>,
+    pub js_namespace: Option<Vec<String>>,
+    pub name: String,
+    pub variants: Vec<String>,
+    pub non_exhaustive: bool,
+    /// Whether the conversions to and from wasm have been generated
+    pub exposed: bool,
 }
 
 #[derive(Default)]
 pub struct ExportedClass {
     pub contents: String,
     pub typescript: String,
+    /// The JS and TypeScript constructors calling the method marked
+    /// `constructor`, if any
+    pub constructor: Option<(String, String)>,
+    /// Names and `Object.defineProperty` descriptors of the accessors for
+    /// the struct's fields
+    pub properties: Vec<(String, String)>,
+    /// Other names this same class is exported under, so `instanceof` works
+    /// whichever one is used
+    pub aliases: Vec<String>,
 }
 
 pub struct SubContext<'a, 'b: 'a> {
@@ -33,20 +78,318 @@ pub struct SubContext<'a, 'b: 'a> {
 }
 
 impl<'a> Context<'a> {
-    pub fn add_custom_type_names(&mut self, program: &shared::Program) {
+    pub fn add_custom_type_names(&mut self, program: &shared::Program)
+        -> Result<(), Error>
+    {
         for custom in program.custom_type_names.iter() {
             let prev = self.custom_type_names.insert(custom.descriptor,
                                                      custom.name.clone());
             if let Some(prev) = prev {
-                assert_eq!(prev, custom.name);
+                if prev != custom.name {
+                    return Err(descriptor_collision(&prev, &custom.name,
+                                                    custom.descriptor))
+                }
+            }
+        }
+        for import in program.imports.iter() {
+            let ty = match import.kind {
+                shared::ImportKind::Type(ref t) => t,
+                shared::ImportKind::Enum(ref e) => {
+                    if let Some(prev) = self.custom_type_names.get(&e.descriptor) {
+                        if *prev != e.name {
+                            return Err(descriptor_collision(prev, &e.name, e.descriptor))
+                        }
+                    }
+                    let prev = self.imported_enums.insert(e.descriptor, ImportedEnum {
+                        module: import.module.clone(),
+                        js_namespace: import.js_namespace.clone(),
+                        name: e.name.clone(),
+                        variants: e.variants.clone(),
+                        non_exhaustive: e.non_exhaustive,
+                        exposed: false,
+                    });
+                    if let Some(prev) = prev {
+                        if prev.name != e.name {
+                            return Err(descriptor_collision(&prev.name, &e.name, e.descriptor))
+                        }
+                    }
+                    continue
+                }
+                _ => continue,
+            };
+            let descriptor = match ty.descriptor {
+                Some(d) => d,
+                None => continue,
+            };
+            let custom = descriptor & !shared::TYPE_CUSTOM_REF_FLAG;
+            if let Some(prev) = self.custom_type_names.get(&custom) {
+                if *prev != ty.name {
+                    return Err(descriptor_collision(prev, &ty.name, custom))
+                }
+            }
+            // The same type may be declared more than once, but two
+            // different ones can't share a name in the JS glue
+            let clash = self.imported_types.values().find(|prev| {
+                prev.name == ty.name &&
+                    (prev.module != import.module || prev.js_namespace != import.js_namespace)
+            });
+            if let Some(prev) = clash {
+                return Err(Error(format!("the type `{}` is imported from both {} \
+                                          and {}", ty.name,
+                                         import_location(prev.module.as_ref(),
+                                                         prev.js_namespace.as_ref()),
+                                         import_location(import.module.as_ref(),
+                                                         import.js_namespace.as_ref()))))
+            }
+            let prev = self.imported_types.insert(descriptor, ImportedType {
+                module: import.module.clone(),
+                js_namespace: import.js_namespace.clone(),
+                name: ty.name.clone(),
+                iterator: ty.iterator,
+                instanceof: ty.instanceof,
+                typescript_type: ty.typescript_type.clone(),
+            });
+            if let Some(prev) = prev {
+                if prev.name != ty.name {
+                    return Err(descriptor_collision(&prev.name, &ty.name, custom))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records which methods are the constructors of exported classes,
+    /// checking no class has more than one.
+    pub fn add_constructors(&mut self, program: &shared::Program) -> Result<(), Error> {
+        for export in program.exports.iter().filter(|e| e.constructor) {
+            let class = match export.class {
+                Some(ref class) => class,
+                None => continue,
+            };
+            let name = &export.function.name;
+            if let Some(prev) = self.constructors.insert(class.clone(), name.clone()) {
+                return Err(Error(format!("both `{}` and `{}` are marked as the \
+                                          constructor of `{}`", prev, name, class)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Deno has no package resolution, so every module imported from has to
+    /// be a URL or a path.
+    pub fn check_deno_modules(&mut self, program: &shared::Program) -> Result<(), Error> {
+        for import in program.imports.iter() {
+            let module = match import.module {
+                Some(ref m) => m,
+                None => continue,
+            };
+            let path = module.starts_with("./") ||
+                module.starts_with("../") ||
+                module.starts_with("/");
+            if !path && !module.contains("://") {
+                return Err(Error(format!("Deno can't import from the module `{}`, \
+                                          which has to be a URL or a relative path",
+                                         module)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that imported functions sharing a shim, which happens when the
+    /// same Rust function is declared more than once, are the same import.
+    /// Different Rust functions binding one JS function each get their own.
+    pub fn add_import_shims(&mut self, program: &shared::Program) -> Result<(), Error> {
+        for import in program.imports.iter() {
+            let f = match import.kind {
+                shared::ImportKind::Function(ref f) => f,
+                _ => continue,
+            };
+            let mut path = import.js_namespace.clone().unwrap_or(Vec::new());
+            if let Some(ref class) = f.class {
+                path.push(class.clone());
+            }
+            path.push(f.function.name.clone());
+            let mut what = format!("`{}`", path.join("."));
+            if let Some(ref module) = import.module {
+                what.push_str(&format!(" from `{}`", module));
+            }
+            let signature = format!("{} with arguments {:?} returning {:?}",
+                                    what, f.function.arguments, f.function.ret);
+            let prev = self.import_shims.insert(f.shim.clone(), signature.clone());
+            if let Some(prev) = prev {
+                if prev != signature {
+                    return Err(Error(format!("the imports of {} and {} are both bound \
+                                              to the shim `{}`, try giving one of \
+                                              them a different Rust name",
+                                             prev, signature, f.shim)))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the exported class to be the default export, which has to be
+    /// the only one.
+    pub fn add_default_export(&mut self) -> Result<(), Error> {
+        let mut names = self.custom_type_names.values().collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        match names.len() {
+            0 => Err(Error(format!("`--default-export` needs an exported \
+                                    struct but none were found"))),
+            1 => {
+                self.default_export = Some(names[0].clone());
+                Ok(())
+            }
+            _ => {
+                let names = names.iter()
+                    .map(|n| format!("`{}`", n))
+                    .collect::<Vec<_>>();
+                Err(Error(format!("`--default-export` needs exactly one \
+                                   exported struct but found {}",
+                                  names.join(", "))))
+            }
+        }
+    }
+
+    /// Imported JS types carry their own descriptor so exports can check
+    /// their arguments with `instanceof`, but everywhere else they're just a
+    /// `JsValue`. The same goes for `JsFuture`s, which are promises, and
+    /// `JsDate`s, and a `&Closure` is a borrowed JS function. Narrow and
+    /// non-zero integers are likewise numbers like any other once their range
+    /// has been checked.
+    fn js_value_descriptor(&self, ty: shared::Type) -> shared::Type {
+        if ty == shared::TYPE_PROMISE || ty == shared::TYPE_DATE {
+            return shared::TYPE_JS_OWNED
+        }
+        if ty == shared::TYPE_CLOSURE {
+            return shared::TYPE_JS_REF
+        }
+        if integer_range(ty).is_some() {
+            return shared::TYPE_NUMBER
+        }
+        if ty == shared::TYPE_NONZERO_U32 {
+            return shared::TYPE_U32
+        }
+        if ty == shared::TYPE_NONZERO_U64 {
+            return shared::TYPE_U64
+        }
+        let flag = ty & shared::TYPE_CUSTOM_REF_FLAG;
+        if !self.imported_types.contains_key(&(ty & !flag)) {
+            return ty
+        }
+        if flag != 0 {
+            shared::TYPE_JS_REF
+        } else {
+            shared::TYPE_JS_OWNED
+        }
+    }
+
+    /// The TypeScript type of values described by `ty` which are passed as
+    /// `JsValue`s, which is `any` unless it's an imported type that says
+    /// otherwise.
+    fn js_value_ts_type(&self, ty: shared::Type) -> &str {
+        if ty == shared::TYPE_PROMISE {
+            return "Promise<any>"
+        }
+        if ty == shared::TYPE_DATE {
+            return "Date"
+        }
+        self.imported_types.get(&(ty & !shared::TYPE_CUSTOM_REF_FLAG))
+            .and_then(|t| t.typescript_type.as_ref())
+            .map(|s| &s[..])
+            .unwrap_or("any")
+    }
+
+    fn import_name(&mut self,
+                   module: Option<&str>,
+                   js_namespace: Option<&[String]>,
+                   item: &str) -> String {
+        let mut path = js_namespace.map(|ns| ns.to_vec()).unwrap_or(Vec::new());
+        path.push(item.to_string());
+        if let Some(module) = module {
+            // Only the outermost object of a namespace is imported, and if
+            // that's `default` it's the module's default export, which is
+            // given a name of its own.
+            if path[0] == "default" {
+                let next = self.default_imports.len();
+                let name = self.default_imports.entry(module.to_string())
+                    .or_insert_with(|| format!("__wbg_default{}", next))
+                    .clone();
+                if self.imported_names.insert(name.clone()) {
+                    if self.config.nodejs {
+                        self.imports.push_str(&format!("
+                            const {} = require('{}').default;
+                        ", name, module));
+                    } else {
+                        self.imports.push_str(&format!("
+                            import {} from '{}';
+                        ", name, module));
+                    }
+                }
+                path[0] = name;
+                return js_path(&path)
+            }
+            let name = &path[0];
+
+            if self.imported_names.insert(name.to_string()) {
+                if self.config.nodejs {
+                    self.imports.push_str(&format!("
+                        const {} = require('{}').{};
+                    ", name, module, name));
+                } else {
+                    self.imports.push_str(&format!("
+                        import {{ {} }} from '{}';
+                    ", name, module));
+                }
             }
         }
+        js_path(&path)
+    }
+
+    /// Like `import_name`, but for an expression evaluated only when it's
+    /// needed. Nothing is imported from `module` by name, so it need not
+    /// provide `item` yet when the JS glue is loaded, and a global that isn't
+    /// defined evaluates to `undefined` rather than throwing.
+    fn lazy_import_name(&mut self,
+                        module: Option<&str>,
+                        js_namespace: Option<&[String]>,
+                        item: &str) -> String {
+        let mut path = js_namespace.map(|ns| ns.to_vec()).unwrap_or(Vec::new());
+        path.push(item.to_string());
+        let module = match module {
+            Some(module) => module,
+            None => {
+                return format!("(typeof({}) === 'undefined' ? undefined : {})",
+                               path[0], js_path(&path))
+            }
+        };
+        let base = if self.config.nodejs {
+            format!("require('{}')", module)
+        } else {
+            let next = self.lazy_modules.len();
+            let name = self.lazy_modules.entry(module.to_string())
+                .or_insert_with(|| format!("__wbg_lazy_module{}", next))
+                .clone();
+            if self.imported_names.insert(name.clone()) {
+                self.imports.push_str(&format!("
+                    import * as {} from '{}';
+                ", name, module));
+            }
+            name
+        };
+        path.insert(0, base);
+        js_path(&path)
     }
 
     fn export(&mut self, name: &str, contents: &str) {
         let contents = contents.trim();
         let global = if self.config.nodejs {
-            format!("module.exports.{} = {};\n", name, contents)
+            if self.config.verbose_shims && contents.starts_with("function(") {
+                format!("module.exports.{0} = function {0}{1};\n", name, &contents[8..])
+            } else {
+                format!("module.exports.{} = {};\n", name, contents)
+            }
         } else {
             if contents.starts_with("function") {
                 format!("export function {} {}\n", name, &contents[8..])
@@ -59,6 +402,14 @@ impl<'a> Context<'a> {
         self.globals.push_str(&global);
     }
 
+    /// Writes a `// ...` comment describing the next generated item, if
+    /// descriptive shims were requested.
+    fn shim_comment(&mut self, comment: &str) {
+        if self.config.verbose_shims {
+            self.globals.push_str(&format!("// {}\n", comment));
+        }
+    }
+
     pub fn finalize(&mut self, module_name: &str) -> (String, String) {
         self.write_classes();
         {
@@ -197,6 +548,218 @@ impl<'a> Context<'a> {
                 }")
             });
 
+            bind("__wbindgen_is_string", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    return typeof(getObject(i)) === 'string' ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_is_number", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    return typeof(getObject(i)) === 'number' ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_is_boolean", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    return typeof(getObject(i)) === 'boolean' ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_is_function", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    return typeof(getObject(i)) === 'function' ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_is_object", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    const v = getObject(i);
+                    return typeof(v) === 'object' && v !== null ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_is_array", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    return Array.isArray(getObject(i)) ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_iterator_next", &|me| {
+                me.expose_get_object();
+                me.expose_add_heap_object();
+                me.expose_uint8_memory();
+                String::from("function(i, done) {
+                    const next = getObject(i).next();
+                    if (next.done) {
+                        getUint8Memory()[done] = 1;
+                        return 0;
+                    }
+                    return addHeapObject(next.value);
+                }")
+            });
+
+            bind("__wbindgen_iterator_return", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    const iter = getObject(i);
+                    if (typeof(iter.return) === 'function')
+                        iter.return();
+                }")
+            });
+
+            bind("__wbindgen_array_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("function() {
+                    return addHeapObject([]);
+                }")
+            });
+
+            bind("__wbindgen_array_push", &|me| {
+                me.expose_get_object();
+                me.expose_take_object();
+                String::from("function(a, i) {
+                    getObject(a).push(takeObject(i));
+                }")
+            });
+
+            bind("__wbindgen_object_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("function() {
+                    return addHeapObject({});
+                }")
+            });
+
+            bind("__wbindgen_object_set", &|me| {
+                me.expose_get_object();
+                me.expose_take_object();
+                me.expose_get_string_from_wasm();
+                // Defined rather than assigned so keys like `__proto__` are
+                // just properties too
+                String::from("function(o, ptr, len, i) {
+                    Object.defineProperty(getObject(o), getStringFromWasm(ptr, len), {
+                        value: takeObject(i),
+                        writable: true,
+                        enumerable: true,
+                        configurable: true,
+                    });
+                }")
+            });
+
+            bind("__wbindgen_closure_new", &|me| {
+                me.expose_add_heap_object();
+                me.expose_make_closure();
+                me.expose_closure_invalidators();
+                String::from("function(ptr, arity, mutable) {
+                    const [f, drop] = makeClosure(ptr, arity, mutable !== 0,
+                                                  'closure invoked after being dropped');
+                    closureInvalidators.set(f, drop);
+                    return addHeapObject(f);
+                }")
+            });
+
+            bind("__wbindgen_closure_drop", &|me| {
+                me.expose_get_object();
+                me.expose_closure_invalidators();
+                String::from("function(i) {
+                    const f = getObject(i);
+                    closureInvalidators.get(f)();
+                    closureInvalidators.delete(f);
+                }")
+            });
+
+            bind("__wbindgen_date_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("function(ms) { return addHeapObject(new Date(ms)); }")
+            });
+
+            bind("__wbindgen_date_get_time", &|me| {
+                me.expose_get_object();
+                // Imports may hand back anything, which is as good as an
+                // invalid date if it isn't a `Date`
+                String::from("function(i) {
+                    const date = getObject(i);
+                    return date instanceof Date ? date.getTime() : NaN;
+                }")
+            });
+
+            bind("__wbindgen_promise_then", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                me.required_internal_exports.insert("__wbindgen_future_settle");
+                String::from("function(promise, ptr) {
+                    const waiter = { ptr };
+                    const settle = ok => value => {
+                        if (waiter.ptr === 0)
+                            return;
+                        const ptr = waiter.ptr;
+                        waiter.ptr = 0;
+                        wasm.__wbindgen_future_settle(ptr, ok, addHeapObject(value));
+                    };
+                    Promise.resolve(getObject(promise)).then(settle(1), settle(0));
+                    return addHeapObject(waiter);
+                }")
+            });
+
+            bind("__wbindgen_promise_forget", &|me| {
+                me.expose_get_object();
+                String::from("function(i) {
+                    getObject(i).ptr = 0;
+                }")
+            });
+
+            bind("__wbindgen_promise_new", &|me| {
+                me.expose_add_heap_object();
+                me.expose_uint32_memory();
+                me.expose_promise_controls();
+                String::from("function(ptr) {
+                    const ctl = {};
+                    const promise = new Promise((resolve, reject) => {
+                        ctl.resolve = resolve;
+                        ctl.reject = reject;
+                    });
+                    // Only the promise keeps its resolvers alive, so the
+                    // future is dropped once nothing's waiting on it
+                    promiseControls.set(promise, ctl);
+                    const weak = typeof(WeakRef) === 'undefined' ?
+                        { deref: () => ctl } :
+                        new WeakRef(ctl);
+                    getUint32Memory()[ptr / 4] = addHeapObject(weak);
+                    return addHeapObject(promise);
+                }")
+            });
+
+            bind("__wbindgen_promise_on_drop", &|me| {
+                me.expose_get_object();
+                me.expose_promise_finalizers();
+                String::from("function(i, task) {
+                    const ctl = getObject(i).deref();
+                    ctl.task = task;
+                    promiseFinalizers.register(ctl, task, ctl);
+                }")
+            });
+
+            bind("__wbindgen_promise_settle", &|me| {
+                me.expose_get_object();
+                me.expose_take_object();
+                me.expose_promise_finalizers();
+                String::from("function(i, ok, value) {
+                    const v = takeObject(value);
+                    const ctl = getObject(i).deref();
+                    if (ctl === undefined)
+                        return;
+                    promiseFinalizers.unregister(ctl);
+                    wasm.__wbindgen_future_drop(ctl.task);
+                    (ok ? ctl.resolve : ctl.reject)(v);
+                }")
+            });
+
             bind("__wbindgen_throw", &|me| {
                 me.expose_get_string_from_wasm();
                 format!("
@@ -206,6 +769,23 @@ impl<'a> Context<'a> {
                 ")
             });
 
+            bind("__wbindgen_error_field", &|me| {
+                me.expose_pass_string_to_wasm();
+                me.expose_get_object();
+                me.expose_uint32_memory();
+                String::from("function(i, field, len_ptr) {
+                    const obj = getObject(i);
+                    if (obj === null || obj === undefined)
+                        return 0;
+                    const val = field === 0 ? obj.name : obj.message;
+                    if (typeof(val) !== 'string')
+                        return 0;
+                    const [ptr, len] = passStringToWasm(val);
+                    getUint32Memory()[len_ptr / 4] = len;
+                    return ptr;
+                }")
+            });
+
             bind("__wbindgen_string_get", &|me| {
                 me.expose_pass_string_to_wasm();
                 me.expose_get_object();
@@ -227,9 +807,26 @@ impl<'a> Context<'a> {
             self.footer.push_str(&format!("wasm = require('./{}_bg');",
                                           module_name));
             format!("var wasm;")
+        } else if self.config.deno {
+            // The wasm imports are this very module's exports, which are only
+            // all defined once the module has been evaluated
+            self.footer.push_str(&format!("
+                const wasmUrl = new URL('./{name}_bg.wasm', import.meta.url);
+                const wasmBytes = await (await fetch(wasmUrl)).arrayBuffer();
+                const wasmImports = {{ '{module}': __wbg_self }};
+                wasm = (await WebAssembly.instantiate(wasmBytes, wasmImports)).instance.exports;
+            ", name = module_name, module = self.config.import_module(module_name)));
+            format!("
+                import * as __wbg_self from './{}.js';
+                let wasm;
+            ", module_name)
         } else {
             format!("import * as wasm from './{}_bg';", module_name)
         };
+        if self.start {
+            self.required_internal_exports.insert(shared::START_FUNCTION);
+            self.footer.push_str(&format!("\nwasm.{}();", shared::START_FUNCTION));
+        }
 
         let js = format!("
             /* tslint:disable */
@@ -258,49 +855,92 @@ impl<'a> Context<'a> {
             ts_dst.push_str("
                 public ptr: number;
             ");
-            if self.config.debug {
-                self.expose_check_token();
-                dst.push_str(&format!("
-                    constructor(ptr, sym) {{
-                        _checkToken(sym);
-                        this.ptr = ptr;
-                    }}
-                "));
-                ts_dst.push_str("constructor(ptr: number, sym: Symbol);\n");
-
-                let new_name = shared::new_function(&class);
-                if self.wasm_import_needed(&new_name) {
-                    self.export(&new_name, &format!("
-                        function(ptr) {{
-                            return addHeapObject(new {class}(ptr, token));
-                        }}
-                    ", class = class));
+            let registry = format!("{}Finalization", class);
+            let free_on_gc = self.config.free_on_gc;
+            if free_on_gc {
+                self.globals.push_str(&format!("
+                    const {registry} = typeof(FinalizationRegistry) === 'undefined'
+                        ? {{ register: () => {{}}, unregister: () => {{}} }}
+                        : new FinalizationRegistry(ptr => wasm.{free}(ptr));
+                ", registry = registry, free = shared::free_function(&class)));
+            }
+            let register = |obj: &str| {
+                if free_on_gc {
+                    format!("{}.register({1}, ptr, {1});", registry, obj)
+                } else {
+                    String::new()
                 }
+            };
+            let unregister = if free_on_gc {
+                format!("{}.unregister(this);", registry)
             } else {
-                dst.push_str(&format!("
-                    constructor(ptr) {{
-                        this.ptr = ptr;
-                    }}
-                "));
-                ts_dst.push_str("constructor(ptr: number);\n");
-
-                let new_name = shared::new_function(&class);
-                if self.wasm_import_needed(&new_name) {
-                    self.export(&new_name, &format!("
-                        function(ptr) {{
-                            return addHeapObject(new {class}(ptr));
+                String::new()
+            };
+            match exports.constructor {
+                // Wrappers are created with `__wrap` so the constructor is
+                // free to call into Rust instead of taking a pointer
+                Some((ref js, ref ts)) => {
+                    dst.push_str(js);
+                    dst.push_str("\n");
+                    ts_dst.push_str(ts);
+                    ts_dst.push_str("\n");
+                }
+                None if self.config.debug => {
+                    self.expose_check_token();
+                    dst.push_str(&format!("
+                        constructor(ptr, sym) {{
+                            _checkToken(sym);
+                            this.ptr = ptr;
+                            {}
                         }}
-                    ", class = class));
+                    ", register("this")));
+                    ts_dst.push_str("constructor(ptr: number, sym: Symbol);\n");
+                }
+                None => {
+                    dst.push_str(&format!("
+                        constructor(ptr) {{
+                            this.ptr = ptr;
+                            {}
+                        }}
+                    ", register("this")));
+                    ts_dst.push_str("constructor(ptr: number);\n");
                 }
             }
 
             dst.push_str(&format!("
-                free() {{
+                static __wrap(ptr) {{
+                    const obj = Object.create({class}.prototype);
+                    obj.ptr = ptr;
+                    {register}
+                    return obj;
+                }}
+            ", class = class, register = register("obj")));
+
+            let new_name = shared::new_function(&class);
+            if self.wasm_import_needed(&new_name) {
+                self.expose_add_heap_object();
+                self.export(&new_name, &format!("
+                    function(ptr) {{
+                        return addHeapObject({class}.__wrap(ptr));
+                    }}
+                ", class = class));
+            }
+
+            // Detaches the wrapper from its Rust object, for when either JS
+            // frees it or Rust takes ownership of it
+            dst.push_str(&format!("
+                __destroy_into_raw() {{
                     const ptr = this.ptr;
                     this.ptr = 0;
+                    {}
+                    return ptr;
+                }}
+
+                free() {{
+                    const ptr = this.__destroy_into_raw();
                     wasm.{}(ptr);
                 }}
-            ", shared::free_function(&class)));
+            ", unregister, shared::free_function(&class)));
             ts_dst.push_str("free(): void;\n");
 
             dst.push_str(&exports.contents);
@@ -310,6 +950,33 @@ impl<'a> Context<'a> {
 
             self.export(&class, &dst);
             self.typescript.push_str(&ts_dst);
+
+            let class_ref = if self.config.nodejs {
+                format!("module.exports.{}", class)
+            } else {
+                class.clone()
+            };
+            for &(ref name, ref descriptor) in exports.properties.iter() {
+                self.globals.push_str(&format!("
+                    Object.defineProperty({}.prototype, '{}', {});
+                ", class_ref, name, descriptor));
+            }
+            for alias in exports.aliases.iter() {
+                self.export(alias, &class_ref);
+                self.typescript.push_str(&format!("
+                    export const {alias}: typeof {class};
+                    export type {alias} = {class};
+                ", alias = alias, class = class));
+            }
+            if self.default_export.as_ref() == Some(&class) {
+                if self.config.nodejs {
+                    self.globals.push_str(&format!("module.exports.default = {};\n",
+                                                   class_ref));
+                } else {
+                    self.globals.push_str(&format!("export default {};\n", class));
+                }
+                self.typescript.push_str(&format!("export default {};\n", class));
+            }
         }
     }
 
@@ -323,6 +990,7 @@ impl<'a> Context<'a> {
         -> Vec<(String, String)>
     {
         let mut math_imports = Vec::new();
+        let import_module = self.config.import_module(module_name);
         let imports = self.module.sections_mut()
             .iter_mut()
             .filter_map(|s| {
@@ -336,8 +1004,7 @@ impl<'a> Context<'a> {
         for import in imports {
             if import.module() == "__wbindgen_placeholder__" {
                 import.module_mut().truncate(0);
-                import.module_mut().push_str("./");
-                import.module_mut().push_str(module_name);
+                import.module_mut().push_str(&import_module);
                 continue
             }
 
@@ -393,8 +1060,7 @@ impl<'a> Context<'a> {
             }
 
             import.module_mut().truncate(0);
-            import.module_mut().push_str("./");
-            import.module_mut().push_str(module_name);
+            import.module_mut().push_str(&import_module);
             *import.field_mut() = renamed_import.clone();
         }
 
@@ -474,38 +1140,190 @@ impl<'a> Context<'a> {
         self.globals.push_str(&format!("let slab = [];"));
     }
 
-    fn expose_global_slab_next(&mut self) {
-        if !self.exposed_globals.insert("slab_next") {
+    fn expose_closure_invalidators(&mut self) {
+        if !self.exposed_globals.insert("closure_invalidators") {
             return
         }
         self.globals.push_str(&format!("
-            let slab_next = 0;
+            const closureInvalidators = new WeakMap();
         "));
     }
 
-    fn expose_get_object(&mut self) {
-        if !self.exposed_globals.insert("get_object") {
+    /// A JS function calling into the boxed Rust closure at `ptr`, along with
+    /// a function dropping it so any later call throws `message` instead.
+    /// Calls in progress are counted here rather than in Rust, which never
+    /// gets to see a call end when an exception is thrown through it, so the
+    /// closure is only freed once the last of them is done. An `FnMut`
+    /// closure, which is `mutable`, can't be called again while it's running.
+    fn expose_make_closure(&mut self) {
+        if !self.exposed_globals.insert("make_closure") {
             return
         }
-        self.expose_global_stack();
-        self.expose_global_slab();
-
-        let get_obj = if self.config.debug {
-            String::from("
-                if (typeof(val) === 'number')
-                    throw new Error('corrupt slab');
-                return val.obj;
-            ")
-        } else {
-            String::from("
-                return val.obj;
-            ")
-        };
+        self.expose_add_heap_object();
+        self.expose_take_object();
+        self.required_internal_exports.insert("__wbindgen_closure_call");
+        self.required_internal_exports.insert("__wbindgen_closure_free");
         self.globals.push_str(&format!("
-            function getObject(idx) {{
-                if ((idx & 1) === 1) {{
-                    return stack[idx >> 1];
-                }} else {{
+            function makeClosure(ptr, arity, mutable, message) {{
+                let depth = 0;
+                let dropped = false;
+                const f = function() {{
+                    if (dropped)
+                        throw new Error(message);
+                    if (mutable && depth > 0)
+                        throw new Error('closure invoked recursively');
+                    const args = [0, 0, 0, 0];
+                    for (let i = 0; i < arity; i++)
+                        args[i] = addHeapObject(arguments[i]);
+                    depth += 1;
+                    try {{
+                        return takeObject(wasm.__wbindgen_closure_call(ptr, ...args));
+                    }} finally {{
+                        depth -= 1;
+                        if (dropped && depth === 0)
+                            wasm.__wbindgen_closure_free(ptr);
+                    }}
+                }};
+                const drop = () => {{
+                    dropped = true;
+                    if (depth === 0)
+                        wasm.__wbindgen_closure_free(ptr);
+                }};
+                return [f, drop];
+            }}
+        "));
+    }
+
+    /// Functions for `&Fn` callbacks passed to imports, which call into the
+    /// boxed Rust closure at `ptr` until they're dropped once the import
+    /// returns.
+    fn expose_make_stack_closure(&mut self) {
+        if !self.exposed_globals.insert("make_stack_closure") {
+            return
+        }
+        self.expose_make_closure();
+        self.globals.push_str(&format!("
+            function makeStackClosure(ptr, arity) {{
+                return makeClosure(ptr, arity, false,
+                    'closure invoked after the call it was passed to returned');
+            }}
+        "));
+    }
+
+    /// The JS side of iterators returned from exports, which advances the
+    /// Rust iterator each time `next` is called and drops it once it's done
+    /// or `return` is called, as `for...of` does when it exits early.
+    fn expose_rust_iterator(&mut self) {
+        if !self.exposed_globals.insert("rust_iterator") {
+            return
+        }
+        self.expose_alloc_frame();
+        self.expose_get_frame_argument();
+        self.expose_take_object();
+        self.required_internal_exports.insert("__wbindgen_rust_iterator_next");
+        self.required_internal_exports.insert("__wbindgen_rust_iterator_drop");
+        let (register, unregister) = if self.config.free_on_gc {
+            self.globals.push_str("
+                const rustIteratorRegistry = typeof(FinalizationRegistry) === 'undefined'
+                    ? { register: () => {}, unregister: () => {} }
+                    : new FinalizationRegistry(ptr => wasm.__wbindgen_rust_iterator_drop(ptr));
+            ");
+            ("rustIteratorRegistry.register(this, ptr, this);",
+             "rustIteratorRegistry.unregister(this);")
+        } else {
+            ("", "")
+        };
+        self.globals.push_str(&format!("
+            class RustIterator {{
+                constructor(ptr) {{
+                    this.ptr = ptr;
+                    {register}
+                }}
+
+                [Symbol.iterator]() {{
+                    return this;
+                }}
+
+                next() {{
+                    if (this.ptr === 0)
+                        return {{ value: undefined, done: true }};
+                    const frame = allocFrame();
+                    try {{
+                        const ret = wasm.__wbindgen_rust_iterator_next(this.ptr, frame);
+                        if (getFrameArgument(frame, 0) === 0)
+                            return this.return();
+                        return {{ value: takeObject(ret), done: false }};
+                    }} finally {{
+                        freeFrame(frame);
+                    }}
+                }}
+
+                return(value) {{
+                    const ptr = this.ptr;
+                    this.ptr = 0;
+                    if (ptr !== 0) {{
+                        {unregister}
+                        wasm.__wbindgen_rust_iterator_drop(ptr);
+                    }}
+                    return {{ value, done: true }};
+                }}
+            }}
+        ", register = register, unregister = unregister));
+    }
+
+    fn expose_promise_controls(&mut self) {
+        if !self.exposed_globals.insert("promise_controls") {
+            return
+        }
+        self.globals.push_str(&format!("
+            const promiseControls = new WeakMap();
+        "));
+    }
+
+    fn expose_promise_finalizers(&mut self) {
+        if !self.exposed_globals.insert("promise_finalizers") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_future_drop");
+        self.globals.push_str(&format!("
+            const promiseFinalizers = typeof(FinalizationRegistry) === 'undefined' ?
+                {{ register: () => {{}}, unregister: () => {{}} }} :
+                new FinalizationRegistry(task => wasm.__wbindgen_future_drop(task));
+        "));
+    }
+
+    fn expose_global_slab_next(&mut self) {
+        if !self.exposed_globals.insert("slab_next") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let slab_next = 0;
+        "));
+    }
+
+    fn expose_get_object(&mut self) {
+        if !self.exposed_globals.insert("get_object") {
+            return
+        }
+        self.expose_global_stack();
+        self.expose_global_slab();
+
+        let get_obj = if self.config.debug {
+            String::from("
+                if (typeof(val) === 'number')
+                    throw new Error('corrupt slab');
+                return val.obj;
+            ")
+        } else {
+            String::from("
+                return val.obj;
+            ")
+        };
+        self.globals.push_str(&format!("
+            function getObject(idx) {{
+                if ((idx & 1) === 1) {{
+                    return stack[idx >> 1];
+                }} else {{
                     const val = slab[idx >> 1];
                     {}
                 }}
@@ -531,9 +1349,9 @@ impl<'a> Context<'a> {
             return
         }
         self.globals.push_str(&format!("
-            function _assertNum(n) {{
+            function _assertNum(n, i) {{
                 if (typeof(n) !== 'number')
-                    throw new Error('expected a number argument');
+                    throw new Error(`expected a number argument at index ${{i}}`);
             }}
         "));
     }
@@ -543,9 +1361,174 @@ impl<'a> Context<'a> {
             return
         }
         self.globals.push_str(&format!("
-            function _assertBoolean(n) {{
+            function _assertBoolean(n, i) {{
                 if (typeof(n) !== 'boolean')
-                    throw new Error('expected a boolean argument');
+                    throw new Error(`expected a boolean argument at index ${{i}}`);
+            }}
+        "));
+    }
+
+    fn expose_assert_string(&mut self) {
+        if !self.exposed_globals.insert("assert_string") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function _assertString(s, i) {{
+                if (typeof(s) !== 'string')
+                    throw new Error(`expected a string argument at index ${{i}}`);
+            }}
+        "));
+    }
+
+    fn expose_assert_range(&mut self) {
+        if !self.exposed_globals.insert("assert_range") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function _assertRange(n, min, max, i) {{
+                if (!Number.isInteger(n) || n < min || n > max)
+                    throw new RangeError(`expected an integer from ${{min}} to ${{max}} at index ${{i}}, found ${{n}}`);
+            }}
+        "));
+    }
+
+    /// Returns the helpers converting values of the imported enum described
+    /// by `ty` to and from the index of their variant, generating them if
+    /// needed, or `None` if `ty` isn't an imported enum.
+    fn expose_imported_enum(&mut self, ty: shared::Type) -> Option<(String, String)> {
+        let (module, js_namespace, name, variants, non_exhaustive) = {
+            let e = self.imported_enums.get_mut(&ty)?;
+            let exposed = mem::replace(&mut e.exposed, true);
+            if exposed {
+                return Some((format!("passEnum{}ToWasm", e.name),
+                             format!("getEnum{}FromWasm", e.name)))
+            }
+            (e.module.clone(), e.js_namespace.clone(), e.name.clone(),
+             e.variants.clone(), e.non_exhaustive)
+        };
+        let values = variants.iter()
+            .map(|v| self.import_name(module.as_ref().map(|s| &**s),
+                                      js_namespace.as_ref().map(|s| &**s),
+                                      v))
+            .collect::<Vec<_>>();
+        // Any other value is the extra `unknown` variant of a non-exhaustive
+        // enum, which has no value to give back to JS
+        let (other, unknown) = if non_exhaustive {
+            (format!("return {};", values.len()),
+             format!("
+                if (i >= {len})
+                    throw new TypeError('unknown values of `{name}` cannot be passed to JS');
+             ", len = values.len(), name = name))
+        } else {
+            (format!("throw new TypeError(v + ' is not a value of `{}`');", name),
+             String::new())
+        };
+        self.globals.push_str(&format!("
+            const enumValues{name} = [{values}];
+
+            function passEnum{name}ToWasm(v) {{
+                const i = enumValues{name}.indexOf(v);
+                if (i !== -1)
+                    return i;
+                {other}
+            }}
+
+            function getEnum{name}FromWasm(i) {{
+                {unknown}
+                return enumValues{name}[i];
+            }}
+        ", name = name, values = values.join(", "), other = other, unknown = unknown));
+        Some((format!("passEnum{}ToWasm", name), format!("getEnum{}FromWasm", name)))
+    }
+
+    fn expose_assert_non_zero(&mut self) {
+        if !self.exposed_globals.insert("assert_non_zero") {
+            return
+        }
+        // `==` so a `BigInt` zero is caught too
+        self.globals.push_str(&format!("
+            function _assertNonZero(n, i) {{
+                if (n == 0)
+                    throw new RangeError(`expected a non-zero number at index ${{i}}`);
+            }}
+        "));
+    }
+
+    fn expose_assert_bigint(&mut self) {
+        if !self.exposed_globals.insert("assert_bigint") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function _assertBigInt(n, i) {{
+                if (typeof(n) !== 'bigint' && !Number.isSafeInteger(n))
+                    throw new Error(`expected a BigInt or safe integer argument at index ${{i}}`);
+            }}
+        "));
+    }
+
+    fn expose_pass_char_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_char_to_wasm") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function passCharToWasm(arg) {{
+                if (typeof(arg) !== 'string')
+                    throw new Error('expected a string argument');
+                const code = arg.codePointAt(0);
+                const len = code > 0xffff ? 2 : 1;
+                if (code === undefined ||
+                    arg.length !== len ||
+                    (code >= 0xd800 && code <= 0xdfff))
+                    throw new Error('expected a single Unicode scalar value');
+                return code;
+            }}
+        "));
+    }
+
+    fn expose_catch_panic(&mut self) {
+        if !self.exposed_globals.insert("catch_panic") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function catchPanic(f) {{
+                try {{
+                    return f();
+                }} catch (e) {{
+                    // Panics abort by trapping, which is the only way a
+                    // `RuntimeError` comes out of wasm
+                    if (!(e instanceof WebAssembly.RuntimeError))
+                        throw e;
+                    const err = new Error(`Rust panicked: ${{e.message}}`);
+                    err.name = 'RustPanic';
+                    err.cause = e;
+                    throw err;
+                }}
+            }}
+        "));
+    }
+
+    fn expose_split_int64(&mut self) {
+        if !self.exposed_globals.insert("split_int64") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function splitInt64(arg) {{
+                const n = BigInt(arg);
+                const low = Number(BigInt.asUintN(32, n));
+                const high = Number(BigInt.asUintN(32, n >> BigInt(32)));
+                return [low, high];
+            }}
+        "));
+    }
+
+    fn expose_join_int64(&mut self) {
+        if !self.exposed_globals.insert("join_int64") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function joinInt64(low, high, signed) {{
+                const n = (BigInt(high >>> 0) << BigInt(32)) | BigInt(low >>> 0);
+                return signed ? BigInt.asIntN(64, n) : n;
             }}
         "));
     }
@@ -649,6 +1632,62 @@ impl<'a> Context<'a> {
         "));
     }
 
+    fn expose_view_of_memory_f64(&mut self) {
+        if !self.exposed_globals.insert("view_of_memory_f64") {
+            return
+        }
+        self.expose_pass_array_f64_to_wasm();
+        self.globals.push_str(&format!("
+            function viewOfMemoryF64(arg) {{
+                if (!(arg instanceof Float64Array) || arg.buffer !== wasm.memory.buffer)
+                    return [0, 0, arg];
+                // A view at the very start would be a null pointer in Rust,
+                // so it's copied out before anything can detach it
+                if (arg.byteOffset === 0 && arg.length !== 0)
+                    return [0, 0, arg.slice()];
+                return [arg.byteOffset, arg.length, null];
+            }}
+        "));
+    }
+
+    fn expose_pass_array_string_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_array_string_to_wasm") {
+            return
+        }
+        self.expose_pass_string_to_wasm();
+        self.expose_pass_array32_to_wasm();
+        self.globals.push_str(&format!("
+            function passArrayStringToWasm(arg) {{
+                const words = new Uint32Array(arg.length * 2);
+                for (let i = 0; i < arg.length; i++) {{
+                    const [ptr, len] = passStringToWasm(arg[i]);
+                    words[2 * i] = ptr;
+                    words[2 * i + 1] = len;
+                }}
+                return passArray32ToWasm(words);
+            }}
+        "));
+    }
+
+    // Frees the strings an array of borrowed strings was copied into along
+    // with the array of their pointers and lengths itself. `len` is the
+    // number of words in the array.
+    fn expose_free_array_string(&mut self) {
+        if !self.exposed_globals.insert("free_array_string") {
+            return
+        }
+        self.expose_uint32_memory();
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.globals.push_str(&format!("
+            function freeArrayString(ptr, len) {{
+                const words = getUint32Memory().slice(ptr / 4, ptr / 4 + len);
+                for (let i = 0; i < len; i += 2)
+                    wasm.__wbindgen_free(words[i], words[i + 1]);
+                wasm.__wbindgen_free(ptr, len * 4);
+            }}
+        "));
+    }
+
     fn expose_text_encoder(&mut self) {
         if !self.exposed_globals.insert("text_encoder") {
             return
@@ -657,7 +1696,7 @@ impl<'a> Context<'a> {
             self.globals.push_str(&format!("
                 const TextEncoder = require('util').TextEncoder;
             "));
-        } else if !self.config.browser {
+        } else if !self.config.browser && !self.config.deno {
             self.globals.push_str(&format!("
                 const TextEncoder = typeof window === 'object' && window.TextEncoder
                     ? window.TextEncoder
@@ -677,7 +1716,7 @@ impl<'a> Context<'a> {
             self.globals.push_str(&format!("
                 const TextDecoder = require('util').TextDecoder;
             "));
-        } else if !self.config.browser {
+        } else if !self.config.browser && !self.config.deno {
             self.globals.push_str(&format!("
                 const TextDecoder = typeof window === 'object' && window.TextDecoder
                     ? window.TextDecoder
@@ -712,15 +1751,55 @@ impl<'a> Context<'a> {
                 function getArrayJsValueFromWasm(ptr, len) {{
                     const mem = getUint32Memory();
                     const slice = mem.slice(ptr / 4, ptr / 4 + len);
-                    const result = []
-                    for (ptr in slice) {{
-                        result.push(getObject(ptr))
+                    const result = [];
+                    for (let i = 0; i < slice.length; i++) {{
+                        result.push(getObject(slice[i]));
                     }}
                     return result;
                 }}
             "));
     }
 
+    fn expose_take_array_js_value_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_array_js_value_from_wasm") {
+            return
+        }
+        self.expose_uint32_memory();
+        self.expose_take_object();
+        self.globals.push_str(&format!("
+            function takeArrayJsValueFromWasm(ptr, len) {{
+                const mem = getUint32Memory();
+                const slice = mem.slice(ptr / 4, ptr / 4 + len);
+                const result = [];
+                for (let i = 0; i < slice.length; i++) {{
+                    result.push(takeObject(slice[i]));
+                }}
+                return result;
+            }}
+        "));
+    }
+
+    fn expose_get_array_string_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("get_array_string_from_wasm") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.expose_get_string_from_wasm();
+        self.expose_uint32_memory();
+        self.globals.push_str(&format!("
+            function getArrayStringFromWasm(ptr, len) {{
+                const mem = getUint32Memory();
+                const slice = mem.slice(ptr / 4, ptr / 4 + len);
+                const result = [];
+                for (let i = 0; i < slice.length; i += 2) {{
+                    result.push(getStringFromWasm(slice[i], slice[i + 1]));
+                    wasm.__wbindgen_free(slice[i], slice[i + 1]);
+                }}
+                return result;
+            }}
+        "));
+    }
+
     fn expose_get_array_i8_from_wasm(&mut self) {
         self.expose_uint8_memory();
         if !self.exposed_globals.insert("get_array_i8_from_wasm") {
@@ -881,9 +1960,9 @@ impl<'a> Context<'a> {
             return
         }
         self.globals.push_str(&format!("
-            function _assertClass(instance, klass) {{
+            function _assertClass(instance, klass, i) {{
                 if (!(instance instanceof klass))
-                    throw new Error(`expected instance of ${{klass.name}}`);
+                    throw new Error(`expected instance of ${{klass.name}} at index ${{i}}`);
                 return instance.ptr;
             }}
         "));
@@ -963,6 +2042,14 @@ impl<'a> Context<'a> {
         &self.custom_type_names[&c]
     }
 
+    /// The name of the exported struct in a vector described by `ty`, if
+    /// `ty` describes a vector of exported structs.
+    fn struct_vector_name(&self, ty: u32) -> Option<&str> {
+        shared::unwrap_vector_descriptor(ty)
+            .and_then(|elem| self.custom_type_names.get(&elem))
+            .map(|s| &s[..])
+    }
+
     fn pass_to_wasm_function(&mut self, ty: &VectorType) -> &'static str {
         match ty.kind {
             VectorKind::String => {
@@ -989,6 +2076,10 @@ impl<'a> Context<'a> {
                 self.expose_pass_array_f64_to_wasm();
                 "passArrayF64ToWasm"
             }
+            VectorKind::StringArray => {
+                self.expose_pass_array_string_to_wasm();
+                "passArrayStringToWasm"
+            }
             VectorKind::JsValue => panic!("Cannot pass Vec<JsValue> to function")
         }
     }
@@ -1031,6 +2122,16 @@ impl<'a> Context<'a> {
                 self.expose_get_array_f64_from_wasm();
                 "getArrayF64FromWasm"
             }
+            VectorKind::StringArray => {
+                self.expose_get_array_string_from_wasm();
+                "getArrayStringFromWasm"
+            }
+            // Rust gives up the values in a vector it hands over, so JS
+            // takes them out of the heap rather than leaving them there
+            VectorKind::JsValue if ty.owned => {
+                self.expose_take_array_js_value_from_wasm();
+                "takeArrayJsValueFromWasm"
+            }
             VectorKind::JsValue => {
                 self.expose_get_array_js_value_from_wasm();
                 "getArrayJsValueFromWasm"
@@ -1038,45 +2139,62 @@ impl<'a> Context<'a> {
         }
     }
 
-    fn expose_set_global_argument(&mut self) {
-        if !self.exposed_globals.insert("set_global_argument") {
+    fn expose_set_frame_argument(&mut self) {
+        if !self.exposed_globals.insert("set_frame_argument") {
             return
         }
         self.expose_uint32_memory();
-        self.expose_global_argument_ptr();
         self.globals.push_str("
-            function setGlobalArgument(arg, i) {
-                const idx = globalArgumentPtr() / 4 + i;
-                getUint32Memory()[idx] = arg;
+            function setFrameArgument(frame, arg, i) {
+                getUint32Memory()[frame / 4 + i] = arg;
             }
         ");
     }
 
-    fn expose_get_global_argument(&mut self) {
-        if !self.exposed_globals.insert("get_global_argument") {
+    fn expose_get_frame_argument(&mut self) {
+        if !self.exposed_globals.insert("get_frame_argument") {
             return
         }
         self.expose_uint32_memory();
-        self.expose_global_argument_ptr();
         self.globals.push_str("
-            function getGlobalArgument(arg) {
-                const idx = globalArgumentPtr() / 4 + arg;
-                return getUint32Memory()[idx];
+            function getFrameArgument(frame, i) {
+                return getUint32Memory()[frame / 4 + i];
+            }
+        ");
+    }
+
+    /// Frames for the extra words of calls to exports are allocated by
+    /// Rust, and kept around afterwards to be reused by later calls.
+    fn expose_alloc_frame(&mut self) {
+        if !self.exposed_globals.insert("alloc_frame") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_frame_alloc");
+        self.globals.push_str("
+            const freeFrames = [];
+            function allocFrame() {
+                if (freeFrames.length > 0)
+                    return freeFrames.pop();
+                return wasm.__wbindgen_frame_alloc();
+            }
+
+            function freeFrame(frame) {
+                freeFrames.push(frame);
             }
         ");
     }
 
-    fn expose_global_argument_ptr(&mut self) {
-        if !self.exposed_globals.insert("global_argument_ptr") {
+    /// Checks that what a `late_bound` import found when it looked itself up
+    /// is a function, binding it to the namespace it was found in, if any.
+    fn expose_get_global_function(&mut self) {
+        if !self.exposed_globals.insert("get_global_function") {
             return
         }
-        self.required_internal_exports.insert("__wbindgen_global_argument_ptr");
         self.globals.push_str("
-            let cachedGlobalArgumentPtr = null;
-            function globalArgumentPtr() {
-                if (cachedGlobalArgumentPtr === null)
-                    cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
-                return cachedGlobalArgumentPtr;
+            function getGlobalFunction(parent, f, path) {
+                if (typeof(f) !== 'function')
+                    throw new TypeError('`' + path + '` is not a function');
+                return parent === undefined ? f : f.bind(parent);
             }
         ");
     }
@@ -1084,6 +2202,12 @@ impl<'a> Context<'a> {
 
 impl<'a, 'b> SubContext<'a, 'b> {
     pub fn generate(&mut self) {
+        // Every struct gets a class to wrap it in JS, if only so it can be
+        // freed, even if it has no methods of its own.
+        for custom in self.program.custom_type_names.iter() {
+            self.cx.exported_classes.entry(custom.name.clone())
+                .or_insert(ExportedClass::default());
+        }
         for f in self.program.exports.iter() {
             self.generate_export(f);
         }
@@ -1093,16 +2217,127 @@ impl<'a, 'b> SubContext<'a, 'b> {
         for e in self.program.enums.iter() {
             self.generate_enum(e);
         }
+        for s in self.program.structs.iter() {
+            self.generate_struct(s);
+        }
+    }
+
+    pub fn generate_struct(&mut self, s: &shared::Struct) {
+        for field in s.fields.iter() {
+            let getter = shared::Function {
+                name: field.name.clone(),
+                arguments: Vec::new(),
+                argument_names: Vec::new(),
+                nullable: Vec::new(),
+                ret: Some(field.ty),
+            };
+            let (get, _) = self.generate_function(
+                "function",
+                &shared::struct_field_get(&s.name, &field.name),
+                true,
+                false,
+                &getter,
+                &[],
+                None,
+                false,
+            );
+            let (ty, _) = self.convert_ret(Some(field.ty), 0);
+            let mut descriptor = format!("{{ get: {},", get);
+            if !field.readonly {
+                let setter = shared::Function {
+                    name: field.name.clone(),
+                    arguments: vec![field.ty],
+                    argument_names: vec!["value".to_string()],
+                    nullable: vec![false],
+                    ret: None,
+                };
+                let (set, _) = self.generate_function(
+                    "function",
+                    &shared::struct_field_set(&s.name, &field.name),
+                    true,
+                    false,
+                    &setter,
+                    &[],
+                    None,
+                    false,
+                );
+                descriptor.push_str(&format!(" set: {},", set));
+            }
+            descriptor.push_str(" configurable: true }");
+
+            let class = self.cx.exported_classes.entry(s.name.clone())
+                .or_insert(ExportedClass::default());
+            if field.readonly {
+                class.typescript.push_str("readonly ");
+            }
+            class.typescript.push_str(&field.name);
+            class.typescript.push_str(&ty);
+            class.typescript.push_str(";\n");
+            class.properties.push((field.name.clone(), descriptor));
+        }
+        if s.js_class_aliases.len() > 0 {
+            self.cx.exported_classes.entry(s.name.clone())
+                .or_insert(ExportedClass::default())
+                .aliases
+                .extend(s.js_class_aliases.iter().cloned());
+        }
     }
 
     pub fn generate_export(&mut self, export: &shared::Export) {
         if let Some(ref class) = export.class {
             return self.generate_export_for_class(class, export)
         }
+        if export.start {
+            self.cx.start = true;
+            return
+        }
+        if export.async_ {
+            return self.generate_async_export(export)
+        }
+        let wasm_name = shared::free_function_export_name(&export.rust_name);
+        let (js, ts) = self.generate_function("function",
+                                              &wasm_name,
+                                              false,
+                                              false,
+                                              &export.function,
+                                              &export.packed_bools,
+                                              export.throws,
+                                              export.catch);
+        self.cx.shim_comment(&format!("Wrapper for the exported Rust function `{}`",
+                                      export.function.name));
+        self.cx.export(&export.function.name, &js);
+        self.cx.globals.push_str("\n");
+        self.cx.typescript.push_str("export ");
+        self.cx.typescript.push_str(&ts);
+        self.cx.typescript.push_str("\n");
+    }
+
+    /// Exported `async fn`s hand back their promise directly, which settles
+    /// with the value of the type `export.function` returns (or is rejected
+    /// with the error) once the Rust future completes.
+    fn generate_async_export(&mut self, export: &shared::Export) {
+        let function = shared::Function {
+            name: export.function.name.clone(),
+            arguments: export.function.arguments.clone(),
+            argument_names: export.function.argument_names.clone(),
+            nullable: export.function.nullable.clone(),
+            ret: Some(shared::TYPE_PROMISE),
+        };
+        let wasm_name = shared::free_function_export_name(&export.rust_name);
         let (js, ts) = self.generate_function("function",
-                                              &export.function.name,
+                                              &wasm_name,
                                               false,
-                                              &export.function);
+                                              false,
+                                              &function,
+                                              &export.packed_bools,
+                                              None,
+                                              false);
+        let output = self.promise_ts_type(export.function.ret);
+        let ts = format!("{}: Promise<{}>;",
+                         ts.trim_end_matches(": Promise<any>;"),
+                         output);
+        self.cx.shim_comment(&format!("Wrapper for the exported Rust `async fn` `{}`",
+                                      export.function.name));
         self.cx.export(&export.function.name, &js);
         self.cx.globals.push_str("\n");
         self.cx.typescript.push_str("export ");
@@ -1110,79 +2345,228 @@ impl<'a, 'b> SubContext<'a, 'b> {
         self.cx.typescript.push_str("\n");
     }
 
+    /// The TypeScript type of the value a promise returned by an `async fn`
+    /// resolves with, where `ret` is what the Rust function returns.
+    fn promise_ts_type(&self, ret: Option<shared::Type>) -> String {
+        let ret = match ret {
+            Some(ret) => ret,
+            None => return "void".to_string(),
+        };
+        match self.cx.js_value_descriptor(ret) {
+            shared::TYPE_ENUM |
+            shared::TYPE_NUMBER |
+            shared::TYPE_U32 |
+            shared::TYPE_F32 |
+            shared::TYPE_F64 => "number".to_string(),
+            shared::TYPE_BOOLEAN => "boolean".to_string(),
+            shared::TYPE_STRING => "string".to_string(),
+            shared::TYPE_JS_OWNED => self.cx.js_value_ts_type(ret).to_string(),
+            other if other >= shared::TYPE_CUSTOM_START &&
+                other < shared::TYPE_ARRAY_OFFSET => {
+                self.cx.custom_type_name(other).to_string()
+            }
+            _ => "any".to_string(),
+        }
+    }
+
     pub fn generate_export_for_class(&mut self, class: &str, export: &shared::Export) {
+        if export.constructor {
+            return self.generate_constructor(class, export)
+        }
         let (js, ts) = self.generate_function(
             "",
-            &shared::struct_function_export_name(class, &export.function.name),
+            &shared::struct_function_export_name(class, &export.rust_name),
             export.method,
+            export.consume,
             &export.function,
+            &export.packed_bools,
+            export.throws,
+            export.catch,
         );
+        let verbose = self.cx.config.verbose_shims;
+        let class_name = class;
         let class = self.cx.exported_classes.entry(class.to_string())
             .or_insert(ExportedClass::default());
+        if verbose {
+            class.contents.push_str(&format!(
+                "// Wrapper for the exported Rust method `{}::{}`\n",
+                class_name,
+                export.function.name,
+            ));
+        }
         if !export.method {
             class.contents.push_str("static ");
             class.typescript.push_str("static ");
         }
-        class.contents.push_str(&export.function.name);
+        class.contents.push_str(&js_key(&export.function.name));
         class.contents.push_str(&js);
         class.contents.push_str("\n");
         class.typescript.push_str(&ts);
         class.typescript.push_str("\n");
     }
 
+    /// Generates the JS `constructor` of an exported class from the method
+    /// marked `#[wasm_bindgen(constructor)]`, which returns the wrapper
+    /// object so it's what `new` evaluates to.
+    fn generate_constructor(&mut self, class: &str, export: &shared::Export) {
+        let function = shared::Function {
+            name: "constructor".to_string(),
+            arguments: export.function.arguments.clone(),
+            argument_names: export.function.argument_names.clone(),
+            nullable: export.function.nullable.clone(),
+            ret: export.function.ret,
+        };
+        let (js, ts) = self.generate_function(
+            "",
+            &shared::struct_function_export_name(class, &export.rust_name),
+            false,
+            false,
+            &function,
+            &export.packed_bools,
+            export.throws,
+            export.catch,
+        );
+        // TypeScript constructors don't declare what they return
+        let ret_ts = format!("): {};", class);
+        let ts = if ts.ends_with(&ret_ts) {
+            format!("{});", &ts[..ts.len() - ret_ts.len()])
+        } else {
+            ts
+        };
+        let class = self.cx.exported_classes.entry(class.to_string())
+            .or_insert(ExportedClass::default());
+        class.constructor = Some((format!("constructor{}", js), ts));
+    }
+
     fn generate_function(&mut self,
                          prefix: &str,
                          wasm_name: &str,
                          is_method: bool,
-                         function: &shared::Function) -> (String, String) {
+                         consume: bool,
+                         function: &shared::Function,
+                         packed_bools: &[(usize, usize)],
+                         throws: Option<shared::Type>,
+                         catch: bool) -> (String, String) {
         let mut dst = String::from("(");
-        let mut dst_ts = format!("{}(", function.name);
+        let mut dst_ts = format!("{}(", js_key(&function.name));
         let mut passed_args = String::new();
         let mut arg_conversions = String::new();
+        // Extra words for the frame, which are written once it's allocated
+        // just before the call
+        let mut frame_args = String::new();
         let mut destructors = String::new();
-
-        if is_method {
+        // Borrowed `Float64Array`s which may be passed to Rust in place are
+        // all checked before anything in the shim can grow the memory and
+        // detach them, and only then are the others copied
+        let mut view_checks = String::new();
+        let mut borrowed_views = String::new();
+        // Lengths of fixed-size arrays, checked before any argument is passed
+        // to wasm so nothing passed already is leaked when one is wrong
+        let mut length_checks = String::new();
+        // Rust may only read a view of its own memory if nothing else in the
+        // call could be writing to that memory through Rust
+        let can_alias = !is_method &&
+            !function.arguments.iter().any(|a| self.borrows_memory(*a));
+
+        if consume {
+            // The wrapper is only detached once the arguments are converted,
+            // so it's still usable if any of them is rejected
+            passed_args.push_str("ptr");
+        } else if is_method {
             passed_args.push_str("this.ptr");
         }
 
         let mut global_idx = 0;
         for (i, arg) in function.arguments.iter().enumerate() {
+            // The JS shim keeps positional names so they can't clash with any
+            // of the locals it declares, only the TypeScript signature uses
+            // the names from the Rust source.
             let name = format!("arg{}", i);
             if i > 0 {
                 dst.push_str(", ");
                 dst_ts.push_str(", ");
             }
             dst.push_str(&name);
-            dst_ts.push_str(&name);
+            match function.argument_names.get(i) {
+                Some(rust_name) => dst_ts.push_str(&ts_argument_name(rust_name)),
+                None => dst_ts.push_str(&name),
+            }
 
-            let mut pass = |arg: &str| {
-                if passed_args.len() > 0 {
-                    passed_args.push_str(", ");
+            if let Some((len, _)) = shared::unwrap_array_descriptor(*arg) {
+                length_checks.push_str(&format!("\
+                    if ({name}.length !== {len})
+                        throw new TypeError('expected an array of length {len}, found ' + \
+                                            {name}.length);
+                ", name = name, len = len));
+            }
+
+            let checks = self.arg_checks(*arg, &name, &i.to_string());
+            let share = checks.len() > 1 ||
+                checks.iter().any(|c| !c.starts_with("_assert"));
+            if self.cx.config.share_conversions && share {
+                let helper = self.shared_arg_check(*arg);
+                arg_conversions.push_str(&format!("{}({}, {});\n", helper, name, i));
+            } else {
+                for check in checks {
+                    arg_conversions.push_str(&check);
+                }
+            }
+
+            let mut pass = |arg: &str| {
+                if passed_args.len() > 0 {
+                    passed_args.push_str(", ");
                 }
                 passed_args.push_str(arg);
             };
-            match *arg {
-                shared::TYPE_ENUM | shared::TYPE_NUMBER => {
+            match self.cx.js_value_descriptor(*arg) {
+                shared::TYPE_ENUM |
+                shared::TYPE_NUMBER |
+                shared::TYPE_U32 |
+                shared::TYPE_F32 |
+                shared::TYPE_F64 => {
                     dst_ts.push_str(": number");
-                    if self.cx.config.debug {
-                        self.cx.expose_assert_num();
-                        arg_conversions.push_str(&format!("_assertNum({});\n", name));
-                    }
                     pass(&name)
                 }
+                shared::TYPE_CHAR => {
+                    dst_ts.push_str(": string");
+                    self.cx.expose_pass_char_to_wasm();
+                    pass(&format!("passCharToWasm({})", name))
+                }
+                shared::TYPE_I64 | shared::TYPE_U64 => {
+                    dst_ts.push_str(": bigint | number");
+                    self.cx.expose_split_int64();
+                    self.cx.expose_set_frame_argument();
+                    arg_conversions.push_str(&format!("\
+                        const [low{i}, high{i}] = splitInt64({arg});
+                    ", i = i, arg = name));
+                    frame_args.push_str(&format!("\
+                        setFrameArgument(frame, high{i}, {global_idx});
+                    ", i = i, global_idx = global_idx));
+                    global_idx += 1;
+                    pass(&format!("low{}", i))
+                }
                 shared::TYPE_BOOLEAN => {
                     dst_ts.push_str(": boolean");
-                    if self.cx.config.debug {
-                        self.cx.expose_assert_bool();
-                        arg_conversions.push_str(&format!("\
-                            _assertBoolean({name});
-                        ", name = name));
-                    } else {
+                    let run = packed_bools.iter()
+                        .find(|&&(start, len)| start <= i && i < start + len);
+                    match run {
+                        Some(&(start, len)) if start == i => {
+                            let bits = (start..start + len)
+                                .map(|j| format!("(arg{} ? {} : 0)", j, 1u32 << (j - start)))
+                                .collect::<Vec<_>>()
+                                .join(" | ");
+                            arg_conversions.push_str(&format!("\
+                                const packed{i} = {bits};
+                            ", i = i, bits = bits));
+                            pass(&format!("packed{}", i))
+                        }
+                        Some(_) => {}
+                        None => pass(&format!("arg{i} ? 1 : 0", i = i)),
                     }
-                    pass(&format!("arg{i} ? 1 : 0", i = i))
                 }
                 shared::TYPE_JS_OWNED => {
-                    dst_ts.push_str(": any");
+                    dst_ts.push_str(": ");
+                    dst_ts.push_str(self.cx.js_value_ts_type(*arg));
                     self.cx.expose_add_heap_object();
                     arg_conversions.push_str(&format!("\
                         const idx{i} = addHeapObject({arg});
@@ -1190,7 +2574,8 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     pass(&format!("idx{}", i));
                 }
                 shared::TYPE_JS_REF => {
-                    dst_ts.push_str(": any");
+                    dst_ts.push_str(": ");
+                    dst_ts.push_str(self.cx.js_value_ts_type(*arg));
                     self.cx.expose_borrowed_objects();
                     arg_conversions.push_str(&format!("\
                         const idx{i} = addBorrowedObject({arg});
@@ -1198,20 +2583,144 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     destructors.push_str("stack.pop();\n");
                     pass(&format!("idx{}", i));
                 }
+                // Nothing is passed for zero-sized types, so whatever the
+                // argument is it's ignored
+                shared::TYPE_UNIT => dst_ts.push_str(": void"),
+                other if self.cx.imported_enums.contains_key(&other) => {
+                    dst_ts.push_str(": number");
+                    let (pass_enum, _) = self.cx.expose_imported_enum(other).unwrap();
+                    pass(&format!("{}({})", pass_enum, name))
+                }
+                other if is_optional_string(other) => {
+                    // `None` is passed as a null pointer without allocating
+                    // anything, and the discriminant and length go on the
+                    // stack like for any other optional value.
+                    dst_ts.push_str(": string | null | undefined");
+                    self.cx.expose_pass_string_to_wasm();
+                    self.cx.expose_set_frame_argument();
+                    arg_conversions.push_str(&format!("\
+                        const some{i} = {arg} !== undefined && {arg} !== null;
+                        let ptr{i} = 0, len{i} = 0;
+                        if (some{i})
+                            [ptr{i}, len{i}] = passStringToWasm({arg});
+                    ", i = i, arg = name));
+                    frame_args.push_str(&format!("\
+                        setFrameArgument(frame, some{i} ? 1 : 0, {global_idx});
+                        setFrameArgument(frame, len{i}, {len_idx});
+                    ", i = i, global_idx = global_idx, len_idx = global_idx + 1));
+                    global_idx += 2;
+                    if other % shared::TYPE_OPTION_OFFSET == shared::TYPE_BORROWED_STR {
+                        destructors.push_str(&format!("\n\
+                            if (some{i})\n\
+                                wasm.__wbindgen_free(ptr{i}, len{i});\n\
+                        ", i = i));
+                        self.cx.required_internal_exports.insert("__wbindgen_free");
+                    }
+                    pass(&format!("ptr{}", i))
+                }
+                other if other >= shared::TYPE_OPTION_OFFSET => {
+                    let (depth, inner) = shared::unwrap_option_descriptor(other);
+                    // The high half of a 64-bit integer comes after the
+                    // discriminants in the frame
+                    let mut high = None;
+                    let (ts_ty, val) = match self.cx.js_value_descriptor(inner) {
+                        shared::TYPE_ENUM |
+                        shared::TYPE_NUMBER |
+                        shared::TYPE_U32 |
+                        shared::TYPE_F32 |
+                        shared::TYPE_F64 => ("number", name.clone()),
+                        shared::TYPE_BOOLEAN => ("boolean", format!("{} ? 1 : 0", name)),
+                        shared::TYPE_I64 | shared::TYPE_U64 => {
+                            self.cx.expose_split_int64();
+                            arg_conversions.push_str(&format!("\
+                                const [low{i}, high{i}] = \
+                                    splitInt64({arg} === undefined || {arg} === null ? 0 : {arg});
+                            ", i = i, arg = name));
+                            high = Some(format!("high{}", i));
+                            ("bigint | number", format!("low{}", i))
+                        }
+                        _ => panic!("unsupported optional argument type"),
+                    };
+                    dst_ts.push_str(&format!(": {} | null | undefined", ts_ty));
+                    // `undefined` is always `None`, and `null` is `None` as
+                    // well unless it's needed to represent `Some(None)`.
+                    let discriminants = match depth {
+                        1 => vec![
+                            format!("{0} === undefined || {0} === null ? 0 : 1", name),
+                        ],
+                        2 => vec![
+                            format!("{} === undefined ? 0 : 1", name),
+                            format!("{0} === undefined || {0} === null ? 0 : 1", name),
+                        ],
+                        _ => panic!("only two levels of `Option` are supported"),
+                    };
+                    self.cx.expose_set_frame_argument();
+                    for discriminant in discriminants.into_iter().chain(high) {
+                        frame_args.push_str(&format!("\
+                            setFrameArgument(frame, {}, {});
+                        ", discriminant, global_idx));
+                        global_idx += 1;
+                    }
+                    pass(&val)
+                }
                 other => {
                     match VectorType::from(other) {
+                        Some(ref ty) if can_alias &&
+                            ty.kind == VectorKind::F64 &&
+                            !ty.owned &&
+                            !ty.mutable =>
+                        {
+                            dst_ts.push_str(": ");
+                            dst_ts.push_str(ty.js_ty());
+                            self.cx.expose_view_of_memory_f64();
+                            self.cx.expose_set_frame_argument();
+                            view_checks.push_str(&format!("\
+                                let [ptr{i}, len{i}, copy{i}] = viewOfMemoryF64({arg});
+                            ", i = i, arg = name));
+                            borrowed_views.push_str(&format!("\
+                                if (copy{i} !== null)
+                                    [ptr{i}, len{i}] = passArrayF64ToWasm(copy{i});
+                            ", i = i));
+                            frame_args.push_str(&format!("\
+                                setFrameArgument(frame, len{i}, {global_idx});
+                            ", i = i, global_idx = global_idx));
+                            global_idx += 1;
+                            pass(&format!("ptr{}", i));
+                            destructors.push_str(&format!("\n\
+                                if (copy{i} !== null)\n\
+                                    wasm.__wbindgen_free(ptr{i}, len{i} * 8);\n\
+                            ", i = i));
+                            self.cx.required_internal_exports.insert("__wbindgen_free");
+                        }
                         Some(ty) => {
                             dst_ts.push_str(": ");
                             dst_ts.push_str(ty.js_ty());
                             let func = self.cx.pass_to_wasm_function(&ty);
-                            self.cx.expose_set_global_argument();
+                            self.cx.expose_set_frame_argument();
                             arg_conversions.push_str(&format!("\
                                 const [ptr{i}, len{i}] = {func}({arg});
-                                setGlobalArgument(len{i}, {global_idx});
-                            ", i = i, func = func, arg = name, global_idx = global_idx));
+                            ", i = i, func = func, arg = name));
+                            frame_args.push_str(&format!("\
+                                setFrameArgument(frame, len{i}, {global_idx});
+                            ", i = i, global_idx = global_idx));
                             global_idx += 1;
                             pass(&format!("ptr{}", i));
-                            if !ty.owned {
+                            // Rust worked on a copy in its own memory, so
+                            // copy the result back into the caller's array
+                            // before that copy is freed.
+                            if ty.mutable {
+                                let f = self.cx.expose_get_vector_from_wasm(&ty);
+                                destructors.push_str(&format!("\n\
+                                    if (len{i} > 0)\n\
+                                        {arg}.set({func}(ptr{i}, len{i}));\n\
+                                ", i = i, arg = name, func = f));
+                            }
+                            if !ty.owned && ty.kind == VectorKind::StringArray {
+                                self.cx.expose_free_array_string();
+                                destructors.push_str(&format!("\n\
+                                    freeArrayString(ptr{i}, len{i});\n\
+                                ", i = i));
+                            } else if !ty.owned {
                                 destructors.push_str(&format!("\n\
                                     wasm.__wbindgen_free(ptr{i}, len{i} * {size});\n\
                                 ", i = i, size = ty.size()));
@@ -1223,13 +2732,6 @@ impl<'a, 'b> SubContext<'a, 'b> {
                         None => {
                             let s = self.cx.custom_type_name(other).to_string();
                             dst_ts.push_str(&format!(": {}", s));
-                            if self.cx.config.debug {
-                                self.cx.expose_assert_class();
-                                arg_conversions.push_str(&format!("\
-                                    _assertClass({arg}, {struct_});
-                                ", arg = name, struct_ = s));
-                            }
-
                             if other & shared::TYPE_CUSTOM_REF_FLAG != 0 {
                                 pass(&format!("{}.ptr", name));
                             } else {
@@ -1237,6 +2739,12 @@ impl<'a, 'b> SubContext<'a, 'b> {
                                     const ptr{i} = {arg}.ptr;
                                     {arg}.ptr = 0;
                                 ", i = i, arg = name));
+                                if self.cx.config.free_on_gc {
+                                    // Rust owns the struct now
+                                    arg_conversions.push_str(&format!("\
+                                        {}Finalization.unregister({});
+                                    ", s, name));
+                                }
                                 pass(&format!("ptr{}", i));
                             }
                         }
@@ -1246,102 +2754,463 @@ impl<'a, 'b> SubContext<'a, 'b> {
         }
         dst.push_str(")");
         dst_ts.push_str(")");
-        let convert_ret = match function.ret {
-            None => {
-                dst_ts.push_str(": void");
-                format!("return ret;")
+        arg_conversions.insert_str(0, &borrowed_views);
+        arg_conversions.insert_str(0, &view_checks);
+        arg_conversions.insert_str(0, &length_checks);
+        if self.cx.config.check_arity {
+            // Trailing `Option` and zero-sized arguments may be left off, as
+            // `undefined`
+            let max = function.arguments.len();
+            let optional = function.arguments.iter()
+                .rev()
+                .take_while(|a| **a >= shared::TYPE_OPTION_OFFSET || **a == shared::TYPE_UNIT)
+                .count();
+            let min = max - optional;
+            let check = if min == max {
+                format!("arguments.length !== {}", max)
+            } else {
+                format!("arguments.length < {} || arguments.length > {}", min, max)
+            };
+            let expected = if min == max {
+                format!("{}", max)
+            } else {
+                format!("{} to {}", min, max)
+            };
+            arg_conversions.insert_str(0, &format!("\
+                if ({check})
+                    throw new TypeError(`{name} expects {expected} argument(s) but got ${{arguments.length}}`);
+            ", check = check, name = function.name, expected = expected));
+        }
+        let ret_base = if throws.is_some() { 1 } else { 0 };
+        let (ret_ts, mut convert_ret) = self.convert_ret(function.ret, ret_base);
+        dst_ts.push_str(&ret_ts);
+        if let Some(err) = throws {
+            let (_, convert_err) = self.convert_ret(Some(err), 2);
+            self.cx.expose_get_frame_argument();
+            convert_ret = format!("
+                if (getFrameArgument(frame, 0) !== 0)
+                    throw (function(ret) {{ {} }})(getFrameArgument(frame, 1));
+                {}
+            ", convert_err, convert_ret);
+        }
+        dst_ts.push_str(";");
+        if consume {
+            arg_conversions.insert_str(0, "\
+                if (this.ptr === 0)
+                    throw new Error('object already consumed');
+            ");
+            arg_conversions.push_str("const ptr = this.__destroy_into_raw();\n");
+        }
+        dst.push_str(" {\n        ");
+        dst.push_str(&arg_conversions);
+        let uses_frame = global_idx > 0 ||
+            throws.is_some() ||
+            function.ret.map(|t| self.uses_frame(t)).unwrap_or(false);
+        if uses_frame {
+            // Anything JS does before the call, like converting arguments,
+            // may call back into Rust so the frame is only taken right
+            // before the call and given back once the return value is read
+            self.cx.expose_alloc_frame();
+            dst.push_str("const frame = allocFrame();\n");
+            if passed_args.len() > 0 {
+                passed_args.push_str(", ");
+            }
+            passed_args.push_str("frame");
+            destructors.insert_str(0, "freeFrame(frame);\n");
+        }
+        let mut call = format!("wasm.{}({})", wasm_name, passed_args);
+        if catch {
+            self.cx.expose_catch_panic();
+            call = format!("catchPanic(() => {})", call);
+        }
+        if destructors.len() == 0 {
+            dst.push_str(&format!("\
+                const ret = {call};
+                {convert_ret}
+            ",
+                call = call,
+                convert_ret = convert_ret,
+            ));
+        } else {
+            dst.push_str(&format!("\
+                try {{
+                    {frame_args}
+                    const ret = {call};
+                    {convert_ret}
+                }} finally {{
+                    {destructors}
+                }}
+            ",
+                frame_args = frame_args,
+                call = call,
+                destructors = destructors,
+                convert_ret = convert_ret,
+            ));
+        }
+        dst.push_str("}");
+        (format!("{} {}", prefix, dst), format!("{} {}", prefix, dst_ts))
+    }
+
+    /// Generates the statements checking an argument `arg` of type `ty` at
+    /// index `i`, which is a JS expression, before it's converted. Debug
+    /// checks are only included with `debug` on.
+    fn arg_checks(&mut self, ty: shared::Type, arg: &str, i: &str) -> Vec<String> {
+        let mut checks = Vec::new();
+        let debug = self.cx.config.debug;
+        let check_ranges = self.cx.config.check_ranges.unwrap_or(debug);
+
+        // Imported enums are checked as they're converted
+        if self.cx.imported_enums.contains_key(&ty) {
+            return checks
+        }
+
+        let imported = self.cx.imported_types
+            .get(&(ty & !shared::TYPE_CUSTOM_REF_FLAG))
+            .map(|t| (t.module.clone(), t.js_namespace.clone(), t.name.clone(),
+                      t.iterator, t.instanceof));
+        if ty == shared::TYPE_DATE {
+            checks.push(format!("\
+                if (!({arg} instanceof Date))
+                    throw new TypeError('expected a `Date`');
+            ", arg = arg));
+        } else if let Some((_, _, ref ty_name, true, _)) = imported {
+            // Iterators aren't instances of any particular class, so just
+            // check they look like one.
+            checks.push(format!("\
+                if ({arg} === null || typeof({arg}) !== 'object' || \
+                        typeof({arg}.next) !== 'function')
+                    throw new TypeError('expected an iterator for `{ty}`');
+            ", arg = arg, ty = ty_name));
+        } else if let Some((module, js_namespace, ty_name, _, true)) = imported {
+            let class = self.cx.import_name(module.as_ref().map(|s| &**s),
+                                            js_namespace.as_ref().map(|s| &**s),
+                                            &ty_name);
+            checks.push(format!("\
+                if (!({arg} instanceof {class}))
+                    throw new TypeError('expected an instance of `{class}`');
+            ", arg = arg, class = class));
+        }
+
+        if let Some((min, max)) = integer_range(ty) {
+            if check_ranges {
+                self.cx.expose_assert_range();
+                checks.push(format!("_assertRange({}, {}, {}, {});\n", arg, min, max, i));
+            }
+        }
+
+        if ty == shared::TYPE_NONZERO_U32 || ty == shared::TYPE_NONZERO_U64 {
+            self.cx.expose_assert_non_zero();
+            checks.push(format!("_assertNonZero({}, {});\n", arg, i));
+        }
+
+        match self.cx.js_value_descriptor(ty) {
+            shared::TYPE_ENUM |
+            shared::TYPE_NUMBER |
+            shared::TYPE_U32 |
+            shared::TYPE_F32 |
+            shared::TYPE_F64 => {
+                if debug {
+                    self.cx.expose_assert_num();
+                    checks.push(format!("_assertNum({}, {});\n", arg, i));
+                }
+            }
+            shared::TYPE_I64 | shared::TYPE_U64 => {
+                if debug {
+                    self.cx.expose_assert_bigint();
+                    checks.push(format!("_assertBigInt({}, {});\n", arg, i));
+                }
+            }
+            shared::TYPE_BOOLEAN => {
+                if debug {
+                    self.cx.expose_assert_bool();
+                    checks.push(format!("_assertBoolean({}, {});\n", arg, i));
+                }
+            }
+            shared::TYPE_CHAR |
+            shared::TYPE_JS_OWNED |
+            shared::TYPE_JS_REF |
+            shared::TYPE_UNIT => {}
+            other if is_optional_string(other) => {}
+            other if other >= shared::TYPE_OPTION_OFFSET => {
+                let (_, inner) = shared::unwrap_option_descriptor(other);
+                if debug {
+                    let assert = match self.cx.js_value_descriptor(inner) {
+                        shared::TYPE_BOOLEAN => {
+                            self.cx.expose_assert_bool();
+                            "_assertBoolean"
+                        }
+                        shared::TYPE_I64 | shared::TYPE_U64 => {
+                            self.cx.expose_assert_bigint();
+                            "_assertBigInt"
+                        }
+                        _ => {
+                            self.cx.expose_assert_num();
+                            "_assertNum"
+                        }
+                    };
+                    checks.push(format!("\
+                        if ({arg} !== undefined && {arg} !== null)
+                            {assert}({arg}, {i});
+                    ", arg = arg, assert = assert, i = i));
+                }
+                if inner == shared::TYPE_NONZERO_U32 || inner == shared::TYPE_NONZERO_U64 {
+                    self.cx.expose_assert_non_zero();
+                    checks.push(format!("\
+                        if ({arg} !== undefined && {arg} !== null)
+                            _assertNonZero({arg}, {i});
+                    ", arg = arg, i = i));
+                }
+                if let Some((min, max)) = integer_range(inner) {
+                    if check_ranges {
+                        self.cx.expose_assert_range();
+                        checks.push(format!("\
+                            if ({arg} !== undefined && {arg} !== null)
+                                _assertRange({arg}, {min}, {max}, {i});
+                        ", arg = arg, min = min, max = max, i = i));
+                    }
+                }
+            }
+            other => {
+                match VectorType::from(other) {
+                    Some(ty) => {
+                        if debug && ty.kind == VectorKind::String {
+                            self.cx.expose_assert_string();
+                            checks.push(format!("_assertString({}, {});\n", arg, i));
+                        }
+                    }
+                    None => {
+                        if debug {
+                            let s = self.cx.custom_type_name(other).to_string();
+                            self.cx.expose_assert_class();
+                            checks.push(format!("_assertClass({}, {}, {});\n", arg, s, i));
+                        }
+                    }
+                }
+            }
+        }
+        checks
+    }
+
+    /// Returns the name of the helper, generating it if needed, which runs
+    /// the checks for arguments of type `ty` with `share_conversions` on.
+    fn shared_arg_check(&mut self, ty: shared::Type) -> String {
+        let name = format!("_checkArg{}", ty);
+        if self.cx.shared_conversions.insert(name.clone()) {
+            let body = self.arg_checks(ty, "arg", "i").concat();
+            self.cx.globals.push_str(&format!("
+                function {}(arg, i) {{
+                    {}
+                }}
+            ", name, body));
+        }
+        name
+    }
+
+    /// Whether values of type `ty` returned from exports, or passed to them,
+    /// come with extra words in the call's frame.
+    fn uses_frame(&self, ty: shared::Type) -> bool {
+        match self.cx.js_value_descriptor(ty) {
+            shared::TYPE_I64 | shared::TYPE_U64 => true,
+            other if other >= shared::TYPE_OPTION_OFFSET => true,
+            other if self.cx.struct_vector_name(other).is_some() => true,
+            other => VectorType::from(other).is_some(),
+        }
+    }
+
+    /// Whether an argument of type `ty` hands Rust something it may write to
+    /// during the call, like a mutable slice or a borrowed struct.
+    fn borrows_memory(&self, ty: shared::Type) -> bool {
+        match self.cx.js_value_descriptor(ty) {
+            other if other >= shared::TYPE_CUSTOM_START &&
+                other < shared::TYPE_ARRAY_OFFSET => {
+                other & shared::TYPE_CUSTOM_REF_FLAG != 0
             }
-            Some(shared::TYPE_ENUM) => {
-                dst_ts.push_str(": number");
+            other if self.cx.struct_vector_name(other).is_some() => false,
+            other => VectorType::from(other).map(|t| t.mutable).unwrap_or(false),
+        }
+    }
+
+    /// Generates the JS statements which convert and `return` the wasm
+    /// return value `ret` of type `ret`, along with its TypeScript type. Any
+    /// extra values are read from the call's `frame` starting at `base`.
+    fn convert_ret(&mut self, ret: Option<shared::Type>, base: usize) -> (String, String) {
+        let mut ts = String::new();
+        let ret_ts = ret.map(|t| self.cx.js_value_ts_type(t).to_string());
+        let ret = ret.map(|t| self.cx.js_value_descriptor(t));
+        let js = match ret {
+            None | Some(shared::TYPE_UNIT) => {
+                ts.push_str(": void");
                 format!("return ret;")
             }
+            Some(shared::TYPE_ENUM) | Some(shared::TYPE_U32) => {
+                // Discriminants and `u32`s are unsigned but come out of wasm
+                // as an `i32`
+                ts.push_str(": number");
+                format!("return ret >>> 0;")
+            }
             Some(shared::TYPE_NUMBER) => {
-                dst_ts.push_str(": number");
+                ts.push_str(": number");
+                format!("return ret;")
+            }
+            Some(shared::TYPE_F32) | Some(shared::TYPE_F64) => {
+                // Floats come back as the wasm function's own float return
+                // value so NaN and -0 make it through untouched.
+                ts.push_str(": number");
                 format!("return ret;")
             }
+            Some(shared::TYPE_I64) | Some(shared::TYPE_U64) => {
+                ts.push_str(": bigint");
+                self.cx.expose_join_int64();
+                self.cx.expose_get_frame_argument();
+                format!("return joinInt64(ret, getFrameArgument(frame, {}), {});",
+                        base, ret == Some(shared::TYPE_I64))
+            }
             Some(shared::TYPE_BOOLEAN) => {
-                dst_ts.push_str(": boolean");
+                ts.push_str(": boolean");
                 format!("return ret !== 0;")
             }
+            Some(shared::TYPE_CHAR) => {
+                ts.push_str(": string");
+                format!("return String.fromCodePoint(ret);")
+            }
             Some(shared::TYPE_JS_OWNED) => {
-                dst_ts.push_str(": any");
+                ts.push_str(": ");
+                ts.push_str(&ret_ts.unwrap());
                 self.cx.expose_take_object();
                 format!("return takeObject(ret);")
             }
             Some(shared::TYPE_JS_REF) => {
-                dst_ts.push_str(": any");
+                ts.push_str(": ");
+                ts.push_str(&ret_ts.unwrap());
                 self.cx.expose_get_object();
                 format!("return getObject(ret);")
             }
+            Some(shared::TYPE_ITERATOR) => {
+                ts.push_str(": IterableIterator<any>");
+                self.cx.expose_rust_iterator();
+                format!("return new RustIterator(ret);")
+            }
+            Some(other) if self.cx.imported_enums.contains_key(&other) => {
+                ts.push_str(": number");
+                let (_, get_enum) = self.cx.expose_imported_enum(other).unwrap();
+                format!("return {}(ret);", get_enum)
+            }
+            Some(other) if is_optional_string(other) => {
+                // Unlike other optional values `None` comes out as `null`
+                // here, as that's how a missing string is typically spelled.
+                ts.push_str(": string | null");
+                self.cx.expose_get_string_from_wasm();
+                self.cx.expose_get_frame_argument();
+                self.cx.required_internal_exports.insert("__wbindgen_free");
+                format!("
+                    if (getFrameArgument(frame, {}) === 0)
+                        return null;
+                    const len = getFrameArgument(frame, {});
+                    const realRet = getStringFromWasm(ret, len);
+                    wasm.__wbindgen_free(ret, len);
+                    return realRet;
+                ", base, base + 1)
+            }
+            Some(other) if other >= shared::TYPE_OPTION_OFFSET => {
+                let (depth, inner) = shared::unwrap_option_descriptor(other);
+                let (ts_ty, val) = match self.cx.js_value_descriptor(inner) {
+                    shared::TYPE_ENUM |
+                    shared::TYPE_U32 => ("number", "ret >>> 0".to_string()),
+                    shared::TYPE_NUMBER |
+                    shared::TYPE_F32 |
+                    shared::TYPE_F64 => ("number", "ret".to_string()),
+                    shared::TYPE_BOOLEAN => ("boolean", "ret !== 0".to_string()),
+                    // The high half comes after the discriminants
+                    desc @ shared::TYPE_I64 | desc @ shared::TYPE_U64 => {
+                        self.cx.expose_join_int64();
+                        ("bigint", format!("joinInt64(ret, getFrameArgument(frame, {}), {})",
+                                           base + depth as usize, desc == shared::TYPE_I64))
+                    }
+                    _ => panic!("unsupported optional return type"),
+                };
+                self.cx.expose_get_frame_argument();
+                let mut js = format!("
+                    if (getFrameArgument(frame, {}) === 0)
+                        return undefined;
+                ", base);
+                match depth {
+                    1 => ts.push_str(&format!(": {} | undefined", ts_ty)),
+                    2 => {
+                        ts.push_str(&format!(": {} | null | undefined", ts_ty));
+                        js.push_str(&format!("
+                            if (getFrameArgument(frame, {}) === 0)
+                                return null;
+                        ", base + 1));
+                    }
+                    _ => panic!("only two levels of `Option` are supported"),
+                }
+                js.push_str(&format!("return {};", val));
+                js
+            }
+            Some(other) if self.cx.struct_vector_name(other).is_some() => {
+                // Each struct is wrapped on its own, so every element is
+                // freed independently of the others
+                let name = self.cx.struct_vector_name(other).unwrap().to_string();
+                ts.push_str(&format!(": {}[]", name));
+                self.cx.expose_get_array_u32_from_wasm();
+                self.cx.expose_get_frame_argument();
+                self.cx.required_internal_exports.insert("__wbindgen_free");
+                format!("
+                    const len = getFrameArgument(frame, {});
+                    const ptrs = getArrayU32FromWasm(ret, len);
+                    wasm.__wbindgen_free(ret, len * 4);
+                    return Array.from(ptrs, ptr => {}.__wrap(ptr));
+                ", base, name)
+            }
             Some(other) => {
                 match VectorType::from(other) {
                     Some(ty) => {
                         if !ty.owned {
                             panic!("cannot return slices yet");
                         }
-                        dst_ts.push_str(": ");
-                        dst_ts.push_str(ty.js_ty());
+                        ts.push_str(": ");
+                        ts.push_str(ty.js_ty());
                         let f = self.cx.expose_get_vector_from_wasm(&ty);
-                        self.cx.expose_get_global_argument();
+                        self.cx.expose_get_frame_argument();
                         self.cx.required_internal_exports.insert(
                             "__wbindgen_free",
                         );
                         format!("
-                            const len = getGlobalArgument(0);
+                            const len = getFrameArgument(frame, {});
                             const realRet = {}(ret, len);
                             wasm.__wbindgen_free(ret, len * {});
                             return realRet;
-                        ", f, ty.size())
+                        ", base, f, ty.size())
                     }
                     None => {
                         if other & shared::TYPE_CUSTOM_REF_FLAG != 0 {
                             panic!("cannot return references yet");
                         }
                         let name = self.cx.custom_type_name(other);
-                        dst_ts.push_str(": ");
-                        dst_ts.push_str(name);
-                        if self.cx.config.debug {
-                            format!("\
-                                return new {name}(ret, token);
-                            ", name = name)
-                        } else {
-                            format!("\
-                                return new {name}(ret);
-                            ", name = name)
-                        }
+                        ts.push_str(": ");
+                        ts.push_str(name);
+                        format!("return {}.__wrap(ret);", name)
                     }
                 }
             }
         };
-        dst_ts.push_str(";");
-        dst.push_str(" {\n        ");
-        dst.push_str(&arg_conversions);
-        if destructors.len() == 0 {
-            dst.push_str(&format!("\
-                const ret = wasm.{}({passed});
-                {convert_ret}
-            ",
-                f = wasm_name,
-                passed = passed_args,
-                convert_ret = convert_ret,
-            ));
-        } else {
-            dst.push_str(&format!("\
-                try {{
-                    const ret = wasm.{f}({passed});
-                    {convert_ret}
-                }} finally {{
-                    {destructors}
-                }}
-            ",
-                f = wasm_name,
-                passed = passed_args,
-                destructors = destructors,
-                convert_ret = convert_ret,
-            ));
-        }
-        dst.push_str("}");
-        (format!("{} {}", prefix, dst), format!("{} {}", prefix, dst_ts))
+        // Conversions which are more than a single line are worth a helper,
+        // which gets the frame in case the value continues there
+        let js = match ret {
+            Some(ty) if self.cx.config.share_conversions && js.trim().contains('\n') => {
+                let name = format!("_convertRet{}_{}", ty, base);
+                if self.cx.shared_conversions.insert(name.clone()) {
+                    self.cx.globals.push_str(&format!("
+                        function {}(ret, frame) {{
+                            {}
+                        }}
+                    ", name, js));
+                }
+                format!("return {}(ret, frame);", name)
+            }
+            _ => js,
+        };
+        (ts, js)
     }
 
     pub fn generate_import(&mut self, import: &shared::Import) {
@@ -1352,7 +3221,60 @@ impl<'a, 'b> SubContext<'a, 'b> {
             shared::ImportKind::Static(ref s) => {
                 self.generate_import_static(import, s)
             }
-            shared::ImportKind::Type(_) => {}
+            shared::ImportKind::Type(_) |
+            shared::ImportKind::Enum(_) => {}
+            shared::ImportKind::Property(ref p) => {
+                self.generate_import_property(import, p)
+            }
+        }
+    }
+
+    /// Properties are read and written through structural getters and
+    /// setters, which access `this.name` directly rather than looking up
+    /// accessors on the class's prototype.
+    pub fn generate_import_property(&mut self,
+                                    info: &shared::Import,
+                                    import: &shared::ImportProperty) {
+        let accessor = |shim: &str, setter: bool| {
+            let mut arguments = vec![import.this];
+            let mut argument_names = vec!["this".to_string()];
+            if setter {
+                arguments.push(import.ty);
+                argument_names.push("value".to_string());
+            }
+            shared::ImportFunction {
+                shim: shim.to_string(),
+                module: None,
+                catch: false,
+                method: true,
+                js_new: false,
+                structural: true,
+                final_: false,
+                symbol: None,
+                variadic: false,
+                indexing: None,
+                returns_self: false,
+                late_bound: false,
+                pure: false,
+                this_arg: false,
+                getter: if setter { None } else { Some(import.name.clone()) },
+                setter: if setter { Some(import.name.clone()) } else { None },
+                class: Some(import.class.clone()),
+                function: shared::Function {
+                    name: import.name.clone(),
+                    // Data properties can be set to `null` like any other value
+                    nullable: (0..arguments.len()).map(|i| i > 0).collect(),
+                    arguments,
+                    argument_names,
+                    ret: if setter { None } else { Some(import.ty) },
+                },
+            }
+        };
+        let getter = accessor(&import.get_shim, false);
+        self.generate_import_function(info, &getter);
+        if let Some(ref shim) = import.set_shim {
+            let setter = accessor(shim, true);
+            self.generate_import_function(info, &setter);
         }
     }
 
@@ -1360,8 +3282,15 @@ impl<'a, 'b> SubContext<'a, 'b> {
                                   info: &shared::Import,
                                   import: &shared::ImportStatic) {
         // TODO: should support more types to import here
-        let obj = self.import_name(info, &import.name);
+        let obj = if import.lazy {
+            self.cx.lazy_import_name(info.module.as_ref().map(|s| &**s),
+                                     info.js_namespace.as_ref().map(|s| &**s),
+                                     &import.name)
+        } else {
+            self.import_name(info, &import.name)
+        };
         self.cx.expose_add_heap_object();
+        self.cx.shim_comment(&format!("Shim for the imported static `{}`", obj));
         self.cx.export(&import.shim, &format!("
             function() {{
                 return addHeapObject({});
@@ -1380,27 +3309,86 @@ impl<'a, 'b> SubContext<'a, 'b> {
 
         let mut extra = String::new();
 
+        let mut null_checks = String::new();
+
         let mut next_global = 0;
+        let mut stack_closures = Vec::new();
         for (i, arg) in import.function.arguments.iter().enumerate() {
+            let descriptor = self.cx.js_value_descriptor(*arg);
+            // Zero-sized types aren't passed from wasm at all
+            if descriptor == shared::TYPE_UNIT {
+                invoc_args.push("undefined".to_string());
+                continue
+            }
             abi_args.push(format!("arg{}", i));
-            let invoc_arg = match *arg {
-                shared::TYPE_NUMBER => format!("arg{}", i),
+            let invoc_arg = match descriptor {
+                shared::TYPE_NUMBER |
+                shared::TYPE_F32 |
+                shared::TYPE_F64 => format!("arg{}", i),
+                shared::TYPE_U32 => format!("arg{} >>> 0", i),
                 shared::TYPE_BOOLEAN => format!("arg{} !== 0", i),
-                shared::TYPE_JS_OWNED => {
-                    self.cx.expose_take_object();
-                    format!("takeObject(arg{})", i)
+                shared::TYPE_CHAR => format!("String.fromCodePoint(arg{})", i),
+                shared::TYPE_I64 | shared::TYPE_U64 => {
+                    self.cx.expose_join_int64();
+                    self.cx.expose_get_frame_argument();
+                    let signed = *arg == shared::TYPE_I64;
+                    next_global += 1;
+                    format!("joinInt64(arg{}, getFrameArgument(frame, {}), {})",
+                            i, next_global - 1, signed)
                 }
-                shared::TYPE_JS_REF => {
-                    self.cx.expose_get_object();
-                    format!("getObject(arg{})", i)
+                shared::TYPE_JS_OWNED | shared::TYPE_JS_REF => {
+                    let value = if descriptor == shared::TYPE_JS_OWNED {
+                        self.cx.expose_take_object();
+                        format!("takeObject(arg{})", i)
+                    } else {
+                        self.cx.expose_get_object();
+                        format!("getObject(arg{})", i)
+                    };
+                    // Values which are allowed to be missing are always
+                    // `null` rather than `undefined`, and ones which aren't
+                    // can't be `null` at all. An explicit `this` is passed
+                    // along as is, as free functions may be called with a
+                    // `null` or `undefined` receiver.
+                    if import.this_arg && i == 0 {
+                        value
+                    } else {
+                        extra.push_str(&format!("let a{} = {};", i, value));
+                        if import.function.nullable.get(i).cloned().unwrap_or(false) {
+                            format!("(a{0} === undefined ? null : a{0})", i)
+                        } else {
+                            null_checks.push_str(&format!("
+                                if (a{i} === null)
+                                    throw new TypeError('argument `{name}` of `{func}` must not be null');
+                            ", i = i, name = import.function.argument_names[i],
+                               func = import.function.name));
+                            format!("a{}", i)
+                        }
+                    }
+                }
+                shared::TYPE_STACK_CLOSURE => {
+                    self.cx.expose_make_stack_closure();
+                    self.cx.expose_get_frame_argument();
+                    extra.push_str(&format!("
+                        const [f{0}, drop{0}] = makeStackClosure(arg{0}, getFrameArgument(frame, {1}));
+                    ", i, next_global));
+                    next_global += 1;
+                    stack_closures.push(i);
+                    format!("f{}", i)
+                }
+                other if self.cx.imported_enums.contains_key(&other) => {
+                    let (_, get_enum) = self.cx.expose_imported_enum(other).unwrap();
+                    format!("{}(arg{})", get_enum, i)
+                }
+                other if other >= shared::TYPE_OPTION_OFFSET => {
+                    panic!("optional arguments aren't supported in imports yet")
                 }
                 other => {
                     match VectorType::from(other) {
                         Some(ty) => {
                             let f = self.cx.expose_get_vector_from_wasm(&ty);
-                            self.cx.expose_get_global_argument();
+                            self.cx.expose_get_frame_argument();
                             extra.push_str(&format!("
-                                let len{0} = getGlobalArgument({next_global});
+                                let len{0} = getFrameArgument(frame, {next_global});
                                 let v{0} = {func}(arg{0}, len{0});
                             ", i, func = f, next_global = next_global));
                             next_global += 1;
@@ -1420,12 +3408,8 @@ impl<'a, 'b> SubContext<'a, 'b> {
                                 panic!("cannot import custom ref types yet")
                             }
                             let s = self.cx.custom_type_name(other).to_string();
-                            let assign = if self.cx.config.debug {
-                                format!("let c{0} = new {class}(arg{0}, token);", i, class = s)
-                            } else {
-                                format!("let c{0} = new {class}(arg{0});", i, class = s)
-                            };
-                            extra.push_str(&assign);
+                            extra.push_str(&format!("let c{0} = {class}.__wrap(arg{0});",
+                                                    i, class = s));
                             format!("c{}", i)
                         }
                     }
@@ -1435,38 +3419,56 @@ impl<'a, 'b> SubContext<'a, 'b> {
         }
 
         let nargs = invoc_args.len();
-        let invoc_args = invoc_args.join(", ");
         let function_name = &import.function.name;
         let invoc = match import.class {
             Some(ref class) if import.js_new => {
                 format!("new {}", self.import_name(info, class))
             }
+            Some(_) if import.indexing.is_some() => {
+                let target = match import.indexing.unwrap() {
+                    shared::Indexing::Getter => "function(y) { return this[y]; }",
+                    shared::Indexing::Setter => "function(y, z) { this[y] = z; }",
+                    shared::Indexing::Deleter => "function(y) { delete this[y]; }",
+                };
+                self.cx.globals.push_str(&format!("
+                    const {}_target = {};
+                ", import.shim, target));
+                format!("{}_target.call", import.shim)
+            }
             Some(ref class) if import.method => {
                 let class = self.import_name(info, class);
-                let target = if let Some(ref g) = import.getter {
+                let prop = import.getter.as_ref()
+                    .or(import.setter.as_ref())
+                    .unwrap_or(function_name);
+                let (key, access) = property_key(prop, &import.symbol);
+                let target = if import.getter.is_some() {
                     if import.structural {
-                        format!("function() {{ return this.{}; }}", g)
+                        format!("function() {{ return this{}; }}", access)
                     } else {
                         format!(
                             "Object.getOwnPropertyDescriptor\
-                                ({}.prototype, '{}').get;",
+                                ({}.prototype, {}).get;",
                             class,
-                            g,
+                            key,
                         )
                     }
-                } else if let Some(ref s) = import.setter {
+                } else if import.setter.is_some() {
                     if import.structural {
-                        format!("function(y) {{ this.{} = y; }}", s)
+                        format!("function(y) {{ this{} = y; }}", access)
                     } else {
                         format!(
                             "Object.getOwnPropertyDescriptor\
-                                ({}.prototype, '{}').set;",
+                                ({}.prototype, {}).set;",
                             class,
-                            s,
+                            key,
                         )
                     }
                 } else {
-                    if import.structural {
+                    if import.structural && import.variadic {
+                        format!("function() {{ \
+                                    return this{}.apply(this, arguments); \
+                                 }}", access)
+                    } else if import.structural {
                         let mut s = format!("function(");
                         for i in 0..nargs - 1 {
                             if i > 0 {
@@ -1474,8 +3476,8 @@ impl<'a, 'b> SubContext<'a, 'b> {
                             }
                             drop(write!(s, "x{}", i));
                         }
-                        s.push_str(") { return this.");
-                        s.push_str(function_name);
+                        s.push_str(") { return this");
+                        s.push_str(&access);
                         s.push_str("(");
                         for i in 0..nargs - 1 {
                             if i > 0 {
@@ -1486,41 +3488,178 @@ impl<'a, 'b> SubContext<'a, 'b> {
                         s.push_str("); }");
                         s
                     } else {
-                        format!("{}.prototype.{}", class, function_name)
+                        format!("{}.prototype{}", class, access)
                     }
                 };
                 self.cx.globals.push_str(&format!("
                     const {}_target = {};
                 ", import.shim, target));
-                format!("{}_target.call", import.shim)
+                if import.final_ {
+                    let kind = if import.getter.is_some() {
+                        "getter"
+                    } else if import.setter.is_some() {
+                        "setter"
+                    } else {
+                        "method"
+                    };
+                    let path = match import.symbol {
+                        Some(_) => format!("{} `{}{}`", kind, class, access),
+                        None => format!("{} `{}.{}`", kind, class, prop),
+                    };
+                    // `call` is bound up front as well so patching
+                    // `Function.prototype.call` later can't intercept calls
+                    self.cx.globals.push_str(&format!("
+                        if (typeof({shim}_target) !== 'function')
+                            throw new Error('{path} does not exist on the prototype');
+                        const {shim}_call = Function.prototype.call.bind({shim}_target);
+                    ", shim = import.shim, path = path));
+                    format!("{}_call", import.shim)
+                } else {
+                    format!("{}_target.call", import.shim)
+                }
             }
             Some(ref class) => {
+                // Static methods like `Promise.resolve` may rely on `this`
                 let class = self.import_name(info, class);
+                let (_, access) = property_key(function_name, &import.symbol);
+                self.cx.globals.push_str(&format!("
+                    const {shim}_target = {class}{access}.bind({class});
+                ", shim = import.shim, class = class, access = access));
+                format!("{}_target", import.shim)
+            }
+            None if import.late_bound => {
+                let mut path = info.js_namespace.clone().unwrap_or(Vec::new());
+                path.push(function_name.to_string());
+                self.cx.expose_get_global_function();
+                // Functions in a namespace are called as its methods, like
+                // `navigator.vibrate`, so it's looked up first and kept
+                match info.js_namespace {
+                    Some(ref ns) => {
+                        let (last, rest) = ns.split_last().unwrap();
+                        let rest = if rest.is_empty() { None } else { Some(rest) };
+                        let parent = self.cx.lazy_import_name(None, rest, last);
+                        let (_, access) = property_key(function_name, &None);
+                        extra.push_str(&format!("const parent = {};", parent));
+                        format!("getGlobalFunction(parent, \
+                                     parent === undefined || parent === null \
+                                        ? undefined : parent{}, '{}')",
+                                access, path.join("."))
+                    }
+                    None => {
+                        let f = self.cx.lazy_import_name(None, None, function_name);
+                        format!("getGlobalFunction(undefined, {}, '{}')", f, path.join("."))
+                    }
+                }
+            }
+            None if import.getter.is_some() || import.setter.is_some() => {
+                let target = if let Some(ref g) = import.getter {
+                    let prop = self.import_name(info, g);
+                    format!("function() {{ return {}; }}", prop)
+                } else {
+                    let s = import.setter.as_ref().unwrap();
+                    if info.module.is_some() && info.js_namespace.is_none() {
+                        panic!("cannot generate a setter for `{}` as imports \
+                                from a module are read-only, try adding \
+                                `js_namespace`", s);
+                    }
+                    let prop = self.import_name(info, s);
+                    format!("function(y) {{ {} = y; }}", prop)
+                };
                 self.cx.globals.push_str(&format!("
-                    const {}_target = {}.{};
-                ", import.shim, class, function_name));
+                    const {}_target = {};
+                ", import.shim, target));
                 format!("{}_target", import.shim)
             }
             None => {
                 let name = self.import_name(info, function_name);
-                if name.contains(".") {
+                if let Some(ref ns) = info.js_namespace {
+                    // Variadic functions are usually methods of their
+                    // namespace, like `console.log`, so they keep it as `this`
+                    let target = if import.variadic {
+                        let (last, rest) = ns.split_last().unwrap();
+                        let rest = if rest.is_empty() { None } else { Some(rest) };
+                        let parent = self.cx.import_name(info.module.as_ref().map(|s| &**s),
+                                                         rest,
+                                                         last);
+                        format!("{}.bind({})", name, parent)
+                    } else {
+                        name.clone()
+                    };
                     self.cx.globals.push_str(&format!("
                         const {}_target = {};
-                    ", import.shim, name));
+                    ", import.shim, target));
                     format!("{}_target", import.shim)
                 } else {
                     name
                 }
             }
         };
-        let invoc = format!("{}({})", invoc, invoc_args);
-        let invoc = match import.function.ret {
-            Some(shared::TYPE_NUMBER) => format!("return {};", invoc),
+        let invoc = if import.variadic {
+            // The trailing slice is spread out into individual arguments
+            let mut args = invoc_args;
+            let spread = args.pop().expect("variadic import without arguments");
+            args.push(format!("...{}", spread));
+            if import.this_arg {
+                format!("{}.call({})", invoc, args.join(", "))
+            } else {
+                format!("{}({})", invoc, args.join(", "))
+            }
+        } else if import.this_arg {
+            format!("{}.call({})", invoc, invoc_args.join(", "))
+        } else {
+            format!("{}({})", invoc, invoc_args.join(", "))
+        };
+        let invoc = if import.pure {
+            format!("/*#__PURE__*/{}", invoc)
+        } else {
+            invoc
+        };
+        let ret = import.function.ret.map(|t| self.cx.js_value_descriptor(t));
+        let invoc = match ret {
+            Some(shared::TYPE_NUMBER) |
+            Some(shared::TYPE_U32) |
+            Some(shared::TYPE_F32) |
+            Some(shared::TYPE_F64) => format!("return {};", invoc),
             Some(shared::TYPE_BOOLEAN) => format!("return {} ? 1 : 0;", invoc),
+            Some(shared::TYPE_CHAR) => {
+                self.cx.expose_pass_char_to_wasm();
+                format!("return passCharToWasm({});", invoc)
+            }
+            Some(shared::TYPE_I64) | Some(shared::TYPE_U64) => {
+                self.cx.expose_split_int64();
+                self.cx.expose_set_frame_argument();
+                format!("
+                    const [retlow, rethigh] = splitInt64({});
+                    setFrameArgument(frame, rethigh, 0);
+                    return retlow;
+                ", invoc)
+            }
+            Some(shared::TYPE_JS_OWNED) if import.returns_self => {
+                // Methods returning `this` share its slot in the slab rather
+                // than allocating a new one
+                self.cx.expose_add_heap_object();
+                self.cx.expose_get_object();
+                format!("
+                    const ret = {};
+                    if (ret === getObject(arg0) && (arg0 & 1) === 0) {{
+                        slab[arg0 >> 1].cnt += 1;
+                        return arg0;
+                    }}
+                    return addHeapObject(ret);
+                ", invoc)
+            }
             Some(shared::TYPE_JS_OWNED) => {
                 self.cx.expose_add_heap_object();
                 format!("return addHeapObject({});", invoc)
             }
+            Some(shared::TYPE_UNIT) => invoc,
+            Some(other) if self.cx.imported_enums.contains_key(&other) => {
+                let (pass_enum, _) = self.cx.expose_imported_enum(other).unwrap();
+                format!("return {}({});", pass_enum, invoc)
+            }
+            Some(other) if other >= shared::TYPE_OPTION_OFFSET => {
+                panic!("optional return values aren't supported in imports yet")
+            }
             Some(other) => {
                 match VectorType::from(other) {
                     Some(ty) => {
@@ -1528,11 +3667,10 @@ impl<'a, 'b> SubContext<'a, 'b> {
                             panic!("cannot return borrowed slices in imports");
                         }
                         let f = self.cx.pass_to_wasm_function(&ty);
-                        self.cx.expose_uint32_memory();
-                        self.cx.expose_set_global_argument();
+                        self.cx.expose_set_frame_argument();
                         format!("
                             const [retptr, retlen] = {}({});
-                            setGlobalArgument(retlen, 0);
+                            setFrameArgument(frame, retlen, 0);
                             return retptr;
                         ", f, invoc)
                     }
@@ -1550,8 +3688,12 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 try {{
                     {}
                 }} catch (e) {{
+                    // A Rust panic traps, unless it came through an export
+                    // tagged with `catch` and was rethrown as a `RustPanic`.
+                    const panic = e instanceof WebAssembly.RuntimeError ||
+                        (e instanceof Error && e.name === 'RustPanic');
                     const view = getUint32Memory();
-                    view[exnptr / 4] = 1;
+                    view[exnptr / 4] = panic ? 2 : 1;
                     view[exnptr / 4 + 1] = addHeapObject(e);
                 }}
             ", invoc)
@@ -1559,60 +3701,161 @@ impl<'a, 'b> SubContext<'a, 'b> {
             invoc
         };
 
+        // Arguments are only checked once every one of them has been taken
+        // out of the slab, so none are leaked if one turns out to be `null`
+        let invoc = format!("{}{}", null_checks, invoc);
+
+        // Borrowed closures are freed however the import finishes, and any
+        // copies JS kept of them throw from then on
+        let invoc = if stack_closures.len() > 0 {
+            let drops = stack_closures.iter()
+                .map(|i| format!("drop{}();", i))
+                .collect::<Vec<_>>();
+            format!("
+                try {{
+                    {}
+                }} finally {{
+                    {}
+                }}
+            ", invoc, drops.join("\n"))
+        } else {
+            invoc
+        };
+
+        // Rust passes the frame for extra words after everything else
+        abi_args.push("frame".to_string());
         dst.push_str(&abi_args.join(", "));
         dst.push_str(") {\n");
         dst.push_str(&extra);
         dst.push_str(&format!("{}\n}}", invoc));
+        if self.cx.config.verbose_shims {
+            let mut item = match import.class {
+                Some(ref class) => format!("{}::{}", class, function_name),
+                None => function_name.to_string(),
+            };
+            if let Some(ref module) = info.module {
+                item.push_str(&format!("` from `{}", module));
+            }
+            self.cx.shim_comment(&format!("Shim for the imported function `{}`", item));
+        }
         self.cx.export(&import.shim, &dst);
     }
 
     pub fn generate_enum(&mut self, enum_: &shared::Enum) {
         let mut variants = String::new();
+        let mut names = String::new();
 
+        // Like a TS enum the object also maps each value back to its name,
+        // but those properties aren't enumerable so only the names show up
+        // in `Object.keys` and the like. Rust doesn't allow two variants to
+        // share a value, but should that ever happen the first one wins.
+        let mut seen = HashSet::new();
         for variant in enum_.variants.iter() {
-            variants.push_str(&format!("{}:{},", variant.name, variant.value));
+            variants.push_str(&format!("{}:{},", js_key(variant.js_name()), variant.value));
+            if seen.insert(variant.value) {
+                names.push_str(&format!("{}:{{value:'{}'}},", variant.value, variant.js_name()));
+            }
         }
-        self.cx.export(&enum_.name, &format!("Object.freeze({{ {} }})", variants));
+        let obj = format!("Object.defineProperties({{ {} }}, {{ {} }})", variants, names);
+        self.cx.export(&enum_.name, &format!("Object.freeze({})", obj));
         self.cx.typescript.push_str(&format!("export enum {} {{", enum_.name));
 
         variants.clear();
         for variant in enum_.variants.iter() {
-            variants.push_str(&format!("{},", variant.name));
+            variants.push_str(&format!("{} = {},", js_key(variant.js_name()), variant.value));
         }
         self.cx.typescript.push_str(&variants);
         self.cx.typescript.push_str("}\n");
     }
 
     fn import_name(&mut self, import: &shared::Import, item: &str) -> String {
-        if let Some(ref module) = import.module {
-            let name = import.js_namespace.as_ref().map(|s| &**s).unwrap_or(item);
+        self.cx.import_name(import.module.as_ref().map(|s| &**s),
+                            import.js_namespace.as_ref().map(|s| &**s),
+                            item)
+    }
+}
 
-            if self.cx.imported_names.insert(name.to_string()) {
-                if self.cx.config.nodejs {
-                    self.cx.imports.push_str(&format!("
-                        const {} = require('{}').{};
-                    ", name, module, name));
-                } else {
-                    self.cx.imports.push_str(&format!("
-                        import {{ {} }} from '{}';
-                    ", name, module));
-                }
-            }
+fn descriptor_collision(a: &str, b: &str, descriptor: u32) -> Error {
+    Error(format!("the types `{}` and `{}` are both assigned the descriptor {}, \
+                   try renaming one of them", a, b, descriptor))
+}
+
+/// Describes where an import comes from for error messages.
+fn import_location(module: Option<&String>, js_namespace: Option<&Vec<String>>) -> String {
+    let module = match module {
+        Some(m) => format!("`{}`", m),
+        None => "the global scope".to_string(),
+    };
+    match js_namespace {
+        Some(ns) => format!("`{}` in {}", ns.join("."), module),
+        None => module,
+    }
+}
+
+/// Returns how a property is written as a key (`'foo'`) and when accessed
+/// (`.foo`) in JS, which differ if it's keyed by a symbol.
+fn property_key(name: &str, symbol: &Option<shared::ImportSymbol>) -> (String, String) {
+    let symbol = match *symbol {
+        Some(shared::ImportSymbol::WellKnown(ref name)) => format!("Symbol.{}", name),
+        Some(shared::ImportSymbol::Registered(ref key)) => format!("Symbol.for(\"{}\")", key),
+        None if shared::is_js_identifier(name) => {
+            return (format!("'{}'", name), format!(".{}", name))
         }
-        match import.js_namespace {
-            Some(ref s) => format!("{}.{}", s, item),
-            None => item.to_string(),
+        None => return (format!("'{}'", name), format!("['{}']", name)),
+    };
+    (symbol.clone(), format!("[{}]", symbol))
+}
+
+/// The JS expression for the property path `path`, whose first segment is
+/// something in scope and the rest any property names.
+fn js_path(path: &[String]) -> String {
+    let mut dst = path[0].clone();
+    for segment in path[1..].iter() {
+        if shared::is_js_identifier(segment) {
+            dst.push_str(&format!(".{}", segment));
+        } else {
+            dst.push_str(&format!("['{}']", segment));
         }
     }
+    dst
+}
+
+/// How `name` is written as the key of a class member or object literal,
+/// quoted if it isn't an identifier.
+fn js_key(name: &str) -> String {
+    if shared::is_js_identifier(name) {
+        name.to_string()
+    } else {
+        format!("'{}'", name)
+    }
+}
+
+/// Rust identifiers that are reserved words in JS and so can't be used as
+/// parameter names in a TypeScript signature.
+const JS_RESERVED: &[&str] = &[
+    "arguments", "case", "catch", "class", "const", "debugger", "default",
+    "delete", "do", "enum", "eval", "export", "extends", "finally", "function",
+    "import", "implements", "instanceof", "interface", "let", "new", "null",
+    "package", "private", "protected", "public", "switch", "this", "throw",
+    "try", "typeof", "var", "void", "with", "yield",
+];
+
+fn ts_argument_name(name: &str) -> String {
+    if JS_RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
 }
 
 #[derive(Debug)]
 struct VectorType {
     owned: bool,
+    mutable: bool,
     kind: VectorKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum VectorKind {
     String,
     I8,
@@ -1623,68 +3866,152 @@ enum VectorKind {
     U32,
     F32,
     F64,
+    StringArray,
     JsValue
 }
 
+/// The range of the narrow integer type described by `ty`, if it's one.
+fn integer_range(ty: shared::Type) -> Option<(i32, i32)> {
+    match ty {
+        shared::TYPE_I8 => Some((i8::min_value() as i32, i8::max_value() as i32)),
+        shared::TYPE_U8 => Some((0, u8::max_value() as i32)),
+        shared::TYPE_I16 => Some((i16::min_value() as i32, i16::max_value() as i32)),
+        shared::TYPE_U16 => Some((0, u16::max_value() as i32)),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is a single level of `Option` around `String` or `&str`.
+fn is_optional_string(ty: u32) -> bool {
+    ty == shared::TYPE_OPTION_OFFSET + shared::TYPE_STRING ||
+        ty == shared::TYPE_OPTION_OFFSET + shared::TYPE_BORROWED_STR
+}
+
 impl VectorType {
     fn from(desc: u32) -> Option<VectorType> {
+        // Fixed-size arrays are passed like a vector of their elements
+        if let Some((_, elem)) = shared::unwrap_array_descriptor(desc) {
+            return VectorType::from(elem)
+        }
+        if let Some(elem) = shared::unwrap_vector_descriptor(desc) {
+            let owned = match elem {
+                shared::TYPE_STRING => true,
+                shared::TYPE_BORROWED_STR => false,
+                _ => panic!("vectors of this type aren't supported yet"),
+            };
+            return Some(VectorType { owned, mutable: false, kind: VectorKind::StringArray })
+        }
         let ty = match desc {
             shared::TYPE_BORROWED_STR => {
-                VectorType { owned: false, kind: VectorKind::String }
+                VectorType { owned: false, mutable: false, kind: VectorKind::String }
             }
             shared::TYPE_STRING => {
-                VectorType { owned: true, kind: VectorKind::String }
+                VectorType { owned: true, mutable: false, kind: VectorKind::String }
             }
             shared::TYPE_VECTOR_U8 => {
-                VectorType { owned: true, kind: VectorKind::U8 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::U8 }
             }
             shared::TYPE_VECTOR_I8 => {
-                VectorType { owned: true, kind: VectorKind::I8 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::I8 }
             }
             shared::TYPE_SLICE_U8 => {
-                VectorType { owned: false, kind: VectorKind::U8 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::U8 }
             }
             shared::TYPE_SLICE_I8 => {
-                VectorType { owned: false, kind: VectorKind::I8 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::I8 }
             }
             shared::TYPE_VECTOR_U16 => {
-                VectorType { owned: true, kind: VectorKind::U16 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::U16 }
             }
             shared::TYPE_VECTOR_I16 => {
-                VectorType { owned: true, kind: VectorKind::I16 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::I16 }
             }
             shared::TYPE_SLICE_U16 => {
-                VectorType { owned: false, kind: VectorKind::U16 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::U16 }
             }
             shared::TYPE_SLICE_I16 => {
-                VectorType { owned: false, kind: VectorKind::I16 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::I16 }
             }
             shared::TYPE_VECTOR_U32 => {
-                VectorType { owned: true, kind: VectorKind::U32 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::U32 }
             }
             shared::TYPE_VECTOR_I32 => {
-                VectorType { owned: true, kind: VectorKind::I32 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::I32 }
             }
             shared::TYPE_SLICE_U32 => {
-                VectorType { owned: false, kind: VectorKind::U32 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::U32 }
             }
             shared::TYPE_SLICE_I32 => {
-                VectorType { owned: false, kind: VectorKind::I32 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::I32 }
             }
             shared::TYPE_VECTOR_F32 => {
-                VectorType { owned: true, kind: VectorKind::F32 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::F32 }
             }
             shared::TYPE_VECTOR_F64 => {
-                VectorType { owned: true, kind: VectorKind::F64 }
+                VectorType { owned: true, mutable: false, kind: VectorKind::F64 }
             }
             shared::TYPE_SLICE_F32 => {
-                VectorType { owned: false, kind: VectorKind::F32 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::F32 }
             }
             shared::TYPE_SLICE_F64 => {
-                VectorType { owned: false, kind: VectorKind::F64 }
+                VectorType { owned: false, mutable: false, kind: VectorKind::F64 }
+            }
+            shared::TYPE_SLICE_MUT_U8 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::U8 }
+            }
+            shared::TYPE_SLICE_MUT_I8 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::I8 }
+            }
+            shared::TYPE_SLICE_MUT_U16 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::U16 }
+            }
+            shared::TYPE_SLICE_MUT_I16 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::I16 }
+            }
+            shared::TYPE_SLICE_MUT_U32 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::U32 }
+            }
+            shared::TYPE_SLICE_MUT_I32 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::I32 }
+            }
+            shared::TYPE_SLICE_MUT_F32 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::F32 }
+            }
+            shared::TYPE_SLICE_MUT_F64 => {
+                VectorType { owned: false, mutable: true, kind: VectorKind::F64 }
             }
             shared::TYPE_VECTOR_JSVALUE => {
-                VectorType { owned: true, kind: VectorKind::JsValue }
+                VectorType { owned: true, mutable: false, kind: VectorKind::JsValue }
+            }
+            shared::TYPE_SLICE_JSVALUE => {
+                VectorType { owned: false, mutable: false, kind: VectorKind::JsValue }
+            }
+            shared::TYPE_BOXED_SLICE_U8 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::U8 }
+            }
+            shared::TYPE_BOXED_SLICE_I8 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::I8 }
+            }
+            shared::TYPE_BOXED_SLICE_U16 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::U16 }
+            }
+            shared::TYPE_BOXED_SLICE_I16 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::I16 }
+            }
+            shared::TYPE_BOXED_SLICE_U32 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::U32 }
+            }
+            shared::TYPE_BOXED_SLICE_I32 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::I32 }
+            }
+            shared::TYPE_BOXED_SLICE_F32 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::F32 }
+            }
+            shared::TYPE_BOXED_SLICE_F64 => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::F64 }
+            }
+            shared::TYPE_BOXED_SLICE_JSVALUE => {
+                VectorType { owned: true, mutable: false, kind: VectorKind::JsValue }
             }
             _ => return None
         };
@@ -1702,6 +4029,7 @@ impl VectorType {
             VectorKind::U32 => "Uint32Array",
             VectorKind::F32 => "Float32Array",
             VectorKind::F64 => "Float64Array",
+            VectorKind::StringArray => "string[]",
             VectorKind::JsValue => "any[]",
         }
     }
@@ -1717,6 +4045,7 @@ impl VectorType {
             VectorKind::U32 => 4,
             VectorKind::F32 => 4,
             VectorKind::F64 => 8,
+            VectorKind::StringArray => 4,
             VectorKind::JsValue => 4,
         }
     }
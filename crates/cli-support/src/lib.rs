@@ -1,5 +1,6 @@
 extern crate parity_wasm;
 extern crate wasm_bindgen_shared as shared;
+#[macro_use]
 extern crate serde_json;
 extern crate wasm_gc;
 
@@ -10,6 +11,7 @@ use std::path::{Path, PathBuf};
 
 use parity_wasm::elements::*;
 
+mod api;
 mod js;
 pub mod wasm2es6js;
 
@@ -17,8 +19,18 @@ pub struct Bindgen {
     path: Option<PathBuf>,
     nodejs: bool,
     browser: bool,
+    deno: bool,
     debug: bool,
     typescript: bool,
+    check_arity: bool,
+    verbose_shims: bool,
+    free_on_gc: bool,
+    emit_descriptors: bool,
+    default_export: bool,
+    check_ranges: Option<bool>,
+    emit_api_schema: bool,
+    share_conversions: bool,
+    env_imports: bool,
 }
 
 #[derive(Debug)]
@@ -36,8 +48,18 @@ impl Bindgen {
             path: None,
             nodejs: false,
             browser: false,
+            deno: false,
             debug: false,
             typescript: false,
+            check_arity: false,
+            verbose_shims: false,
+            free_on_gc: false,
+            emit_descriptors: false,
+            default_export: false,
+            check_ranges: None,
+            emit_api_schema: false,
+            share_conversions: false,
+            env_imports: false,
         }
     }
 
@@ -56,11 +78,86 @@ impl Bindgen {
         self
     }
 
+    /// Whether the generated JS is an ES module for Deno, which loads the wasm
+    /// itself and only imports modules by URL or relative path.
+    pub fn deno(&mut self, deno: bool) -> &mut Bindgen {
+        self.deno = deno;
+        self
+    }
+
     pub fn debug(&mut self, debug: bool) -> &mut Bindgen {
         self.debug = debug;
         self
     }
 
+    /// Whether exported functions throw a `TypeError` when called with the
+    /// wrong number of arguments.
+    pub fn check_arity(&mut self, check_arity: bool) -> &mut Bindgen {
+        self.check_arity = check_arity;
+        self
+    }
+
+    /// Whether the generated JS shims are annotated with comments naming the
+    /// Rust items they bind, and named so they're easy to spot in stack traces.
+    pub fn verbose_shims(&mut self, verbose_shims: bool) -> &mut Bindgen {
+        self.verbose_shims = verbose_shims;
+        self
+    }
+
+    /// Whether exported structs are freed when their JS wrapper object is
+    /// garbage collected, in environments supporting `FinalizationRegistry`.
+    pub fn free_on_gc(&mut self, free_on_gc: bool) -> &mut Bindgen {
+        self.free_on_gc = free_on_gc;
+        self
+    }
+
+    /// Whether the descriptions of the exports and imports embedded in the
+    /// wasm are also written out to a `*_descriptors.json` file, as a JSON
+    /// array whose elements are byte-for-byte what was embedded.
+    pub fn emit_descriptors(&mut self, emit_descriptors: bool) -> &mut Bindgen {
+        self.emit_descriptors = emit_descriptors;
+        self
+    }
+
+    /// Whether a JSON description of the exported and imported API, meant for
+    /// documentation tooling, is also written out to an `*_api.json` file.
+    pub fn emit_api_schema(&mut self, emit_api_schema: bool) -> &mut Bindgen {
+        self.emit_api_schema = emit_api_schema;
+        self
+    }
+
+    /// Whether the only exported struct is also the module's default export.
+    /// Generating bindings fails if there isn't exactly one exported struct.
+    pub fn default_export(&mut self, default_export: bool) -> &mut Bindgen {
+        self.default_export = default_export;
+        self
+    }
+
+    /// Whether exported functions throw a `RangeError` when an argument of a
+    /// narrow integer type, like `u8`, is given a number outside its range.
+    /// Defaults to whether `debug` is on.
+    pub fn check_ranges(&mut self, check_ranges: bool) -> &mut Bindgen {
+        self.check_ranges = Some(check_ranges);
+        self
+    }
+
+    /// Whether argument checks and return value conversions used by more
+    /// than one export are generated once as shared helper functions, rather
+    /// than inline in every shim. This makes the JS smaller when many exports
+    /// take or return the same types.
+    pub fn share_conversions(&mut self, share_conversions: bool) -> &mut Bindgen {
+        self.share_conversions = share_conversions;
+        self
+    }
+
+    /// Whether the wasm imports the functions the generated JS provides it
+    /// from the `env` module, like many non-browser hosts expect, rather than
+    /// from the generated JS module itself, `./foo`.
+    pub fn env_imports(&mut self, env_imports: bool) -> &mut Bindgen {
+        self.env_imports = env_imports;
+        self
+    }
+
     pub fn typescript(&mut self, typescript: bool) -> &mut Bindgen {
         self.typescript = typescript;
         self
@@ -75,11 +172,22 @@ impl Bindgen {
             Some(ref path) => path,
             None => panic!("must have a path input for now"),
         };
+        if self.nodejs && self.deno {
+            return Err(Error(format!("can't generate bindings for both node.js and Deno")))
+        }
+        if self.browser && self.deno {
+            return Err(Error(format!("can't generate bindings for both the browser and Deno")))
+        }
+        // Only the loaders generated for node.js and Deno know to supply the
+        // `env` module, a bundler would resolve it as a package
+        if self.env_imports && !self.nodejs && !self.deno {
+            return Err(Error(format!("`--env-imports` needs `--nodejs` or `--deno`")))
+        }
         let stem = input.file_stem().unwrap().to_str().unwrap();
         let mut module = parity_wasm::deserialize_file(input).map_err(|e| {
             Error(format!("{:?}", e))
         })?;
-        let programs = extract_programs(&mut module);
+        let (programs, raw_programs) = extract_programs(&mut module);
 
         let (js, ts) = {
             let mut cx = js::Context {
@@ -91,12 +199,29 @@ impl Bindgen {
                 required_internal_exports: Default::default(),
                 custom_type_names: Default::default(),
                 imported_names: Default::default(),
+                imported_types: Default::default(),
+                imported_enums: Default::default(),
+                lazy_modules: Default::default(),
+                default_imports: Default::default(),
                 exported_classes: Default::default(),
+                constructors: Default::default(),
+                default_export: None,
+                start: false,
+                import_shims: Default::default(),
+                shared_conversions: Default::default(),
                 config: &self,
                 module: &mut module,
             };
             for program in programs.iter() {
-                cx.add_custom_type_names(program);
+                if self.deno {
+                    cx.check_deno_modules(program)?;
+                }
+                cx.add_custom_type_names(program)?;
+                cx.add_constructors(program)?;
+                cx.add_import_shims(program)?;
+            }
+            if self.default_export {
+                cx.add_default_export()?;
             }
             for program in programs.iter() {
                 js::SubContext {
@@ -117,11 +242,32 @@ impl Bindgen {
                 .write_all(ts.as_bytes()).unwrap();
         }
 
+        if self.emit_descriptors {
+            let path = out_dir.join(format!("{}_descriptors", stem)).with_extension("json");
+            let mut json = Vec::new();
+            json.extend_from_slice(b"[\n");
+            for (i, raw) in raw_programs.iter().enumerate() {
+                if i > 0 {
+                    json.extend_from_slice(b",\n");
+                }
+                json.extend_from_slice(raw);
+            }
+            json.extend_from_slice(b"\n]\n");
+            File::create(&path)?.write_all(&json)?;
+        }
+
+        if self.emit_api_schema {
+            let path = out_dir.join(format!("{}_api", stem)).with_extension("json");
+            let mut json = serde_json::to_vec_pretty(&api::api_schema(&programs))?;
+            json.push(b'\n');
+            File::create(&path)?.write_all(&json)?;
+        }
+
         let wasm_path = out_dir.join(format!("{}_bg", stem)).with_extension("wasm");
 
         if self.nodejs {
             let js_path = wasm_path.with_extension("js");
-            let shim = self.generate_node_wasm_import(&module, &wasm_path);
+            let shim = self.generate_node_wasm_import(&module, &wasm_path, stem);
             File::create(&js_path)?.write_all(shim.as_bytes())?;
         }
 
@@ -135,7 +281,16 @@ impl Bindgen {
         Ok(())
     }
 
-    fn generate_node_wasm_import(&self, m: &Module, path: &Path) -> String {
+    /// The module the wasm imports the generated JS `module_name` as.
+    fn import_module(&self, module_name: &str) -> String {
+        if self.env_imports {
+            "env".to_string()
+        } else {
+            format!("./{}", module_name)
+        }
+    }
+
+    fn generate_node_wasm_import(&self, m: &Module, path: &Path, stem: &str) -> String {
         let mut imports = BTreeSet::new();
         if let Some(i) = m.import_section() {
             for i in i.entries() {
@@ -145,8 +300,16 @@ impl Bindgen {
 
         let mut shim = String::new();
         shim.push_str("let imports = {};\n");
+        let js = self.import_module(stem);
         for module in imports {
-            shim.push_str(&format!("imports['{0}'] = require('{0}');\n", module));
+            // With `env_imports` the generated JS isn't required by the name
+            // the wasm imports it as
+            let path = if module == js {
+                format!("./{}", stem)
+            } else {
+                module.to_string()
+            };
+            shim.push_str(&format!("imports['{}'] = require('{}');\n", module, path));
         }
 
         shim.push_str(&format!("
@@ -160,9 +323,16 @@ impl Bindgen {
     }
 }
 
-fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
+/// Decodes the descriptions embedded by `#[wasm_bindgen]`, returning the
+/// programs along with the raw JSON of each, and removes their custom
+/// sections from `module` as nothing needs them once the JS has been
+/// generated. Every other section is left alone, and as the module is only
+/// written out at the very end a failure to generate the JS leaves no wasm
+/// behind at all.
+fn extract_programs(module: &mut Module) -> (Vec<shared::Program>, Vec<Vec<u8>>) {
     let version = shared::version();
     let mut ret = Vec::new();
+    let mut raw = Vec::new();
 
     module.sections_mut().retain(|s| {
         let custom = match *s {
@@ -182,20 +352,22 @@ fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
                 ((payload[3] as usize) << 24);
             let (a, b) = payload[4..].split_at(len as usize);
             payload = b;
-            let p: shared::Program = match serde_json::from_slice(&a) {
-                Ok(f) => f,
+            // Check the versions before anything else, as a different schema
+            // most likely won't decode as a `Program` at all.
+            let v: shared::ProgramVersion = match serde_json::from_slice(&a) {
+                Ok(v) => v,
                 Err(e) => {
                     panic!("failed to decode what looked like wasm-bindgen data: {}", e)
                 }
             };
-            if p.schema_version != shared::SCHEMA_VERSION {
+            if v.schema_version != shared::SCHEMA_VERSION {
                 panic!("
 
 it looks like the Rust project used to create this wasm file was linked against
 a different version of wasm-bindgen than this binary:
 
-  rust wasm file: {}
-     this binary: {}
+  rust wasm file: {} (schema version {})
+     this binary: {} (schema version {})
 
 Currently the bindgen format is unstable enough that these two version must
 exactly match, so it's required that these two version are kept in sync by
@@ -208,15 +380,24 @@ or you can update the binary with
 
     cargo install -f --git https://github.com/alexcrichton/wasm-bindgen
 
-if this warning fails to go away though and you're not sure what to do feel free
-to open an issue at https://github.com/alexcrichton/wasm-bindgen/issues!
+so the `wasm-bindgen` crate in your Cargo.lock and `wasm-bindgen --version`
+report the same version. If this warning fails to go away though and you're not
+sure what to do feel free to open an issue at
+https://github.com/alexcrichton/wasm-bindgen/issues!
 ",
-    p.version, version);
+    v.version, v.schema_version, version, shared::SCHEMA_VERSION);
             }
+            let p: shared::Program = match serde_json::from_slice(&a) {
+                Ok(f) => f,
+                Err(e) => {
+                    panic!("failed to decode what looked like wasm-bindgen data: {}", e)
+                }
+            };
             ret.push(p);
+            raw.push(a.to_vec());
         }
 
         false
     });
-    return ret
+    return (ret, raw)
 }
@@ -22,8 +22,28 @@ Options:
     --out-dir DIR            Output directory
     --nodejs                 Generate output that only works in node.js
     --browser                Generate output that only works in a browser
+    --deno                   Generate output that only works in Deno
     --typescript             Output a TypeScript definition file
     --debug                  Include otherwise-extraneous debug checks in output
+    --check-arity            Throw when exports are called with the wrong number
+                             of arguments
+    --verbose-shims          Annotate generated shims with the Rust items they
+                             bind, for easier debugging
+    --free-on-gc             Free exported structs once their JS objects are
+                             garbage collected, where supported
+    --emit-descriptors       Also write the embedded descriptions of exports and
+                             imports to a JSON file
+    --emit-api-schema        Also write a JSON description of the exported and
+                             imported API for documentation tooling
+    --default-export         Make the crate's only exported struct the module's
+                             default export
+    --check-ranges           Throw when `u8`, `i16` and the like are given
+                             numbers out of range, the default with `--debug`
+    --no-check-ranges        Don't check ranges even with `--debug`
+    --share-conversions      Generate argument checks and return value
+                             conversions once and share them between shims
+    --env-imports            Have the wasm import what the generated JS provides
+                             from the `env` module
     -V --version             Print the version number of wasm-bindgen
 ";
 
@@ -31,9 +51,20 @@ Options:
 struct Args {
     flag_nodejs: bool,
     flag_browser: bool,
+    flag_deno: bool,
     flag_typescript: bool,
     flag_out_dir: Option<PathBuf>,
     flag_debug: bool,
+    flag_check_arity: bool,
+    flag_verbose_shims: bool,
+    flag_free_on_gc: bool,
+    flag_emit_descriptors: bool,
+    flag_emit_api_schema: bool,
+    flag_default_export: bool,
+    flag_check_ranges: bool,
+    flag_no_check_ranges: bool,
+    flag_share_conversions: bool,
+    flag_env_imports: bool,
     flag_version: bool,
     arg_input: Option<PathBuf>,
 }
@@ -57,8 +88,20 @@ fn main() {
     b.input_path(&input)
      .nodejs(args.flag_nodejs)
      .browser(args.flag_browser)
+     .deno(args.flag_deno)
      .debug(args.flag_debug)
+     .check_arity(args.flag_check_arity)
+     .verbose_shims(args.flag_verbose_shims)
+     .free_on_gc(args.flag_free_on_gc)
+     .emit_descriptors(args.flag_emit_descriptors)
+     .emit_api_schema(args.flag_emit_api_schema)
+     .default_export(args.flag_default_export)
+     .share_conversions(args.flag_share_conversions)
+     .env_imports(args.flag_env_imports)
      .typescript(args.flag_typescript);
+    if args.flag_check_ranges || args.flag_no_check_ranges {
+        b.check_ranges(!args.flag_no_check_ranges);
+    }
 
     let out_dir = match args.flag_out_dir {
         Some(ref p) => p,
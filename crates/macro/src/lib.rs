@@ -9,13 +9,18 @@ extern crate wasm_bindgen_backend as backend;
 extern crate wasm_bindgen_shared as shared;
 
 use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, TokenTree};
 use quote::{ToTokens, Tokens};
 
 #[proc_macro_attribute]
 pub fn wasm_bindgen(attr: TokenStream, input: TokenStream) -> TokenStream {
-    let item = syn::parse::<syn::Item>(input.clone()).expect("expected a valid Rust item");
-    let opts = syn::parse::<backend::ast::BindgenAttrs>(attr)
+    let (input, is_async) = strip_async(input.into());
+    let item = syn::parse2::<syn::Item>(input).expect("expected a valid Rust item");
+    let mut opts = syn::parse::<backend::ast::BindgenAttrs>(attr)
         .expect("invalid arguments to #[wasm_bindgen]");
+    if is_async {
+        opts.set_async();
+    }
 
     let mut ret = Tokens::new();
     let mut program = backend::ast::Program::default();
@@ -26,3 +31,57 @@ pub fn wasm_bindgen(attr: TokenStream, input: TokenStream) -> TokenStream {
 
     ret.into()
 }
+
+/// Removes the `async` keyword of an `async fn`, which `syn` doesn't know
+/// about, returning whether there was one.
+fn strip_async(input: proc_macro2::TokenStream) -> (proc_macro2::TokenStream, bool) {
+    let mut tokens = input.into_iter().collect::<Vec<_>>();
+    let mut prev_async = None;
+    let mut found = None;
+    for (i, token) in tokens.iter().enumerate() {
+        if let TokenTree::Term(ref t) = *token {
+            match t.as_str() {
+                "fn" => {
+                    found = prev_async;
+                    break
+                }
+                "async" => prev_async = Some(i),
+                _ => prev_async = None,
+            }
+        }
+    }
+    if let Some(i) = found {
+        tokens.remove(i);
+    }
+    // The methods of an `impl` would have to keep their `self` borrowed for
+    // as long as the future runs, so they can't be `async`
+    let is_impl = tokens.iter().any(|t| match *t {
+        TokenTree::Term(ref t) => t.as_str() == "impl",
+        _ => false,
+    });
+    if is_impl {
+        for token in tokens.iter() {
+            if let TokenTree::Group(ref g) = *token {
+                if g.delimiter() == Delimiter::Brace && has_async_fn(g.stream()) {
+                    panic!("methods in an `impl` can't be `async`, only free \
+                            functions can be exported as an `async fn`");
+                }
+            }
+        }
+    }
+    (tokens.into_iter().collect(), found.is_some())
+}
+
+/// Whether `async fn` appears directly in `stream`, rather than nested in one
+/// of the groups in it.
+fn has_async_fn(stream: proc_macro2::TokenStream) -> bool {
+    let mut prev_async = false;
+    for token in stream {
+        match token {
+            TokenTree::Term(ref t) if t.as_str() == "fn" && prev_async => return true,
+            TokenTree::Term(ref t) => prev_async = t.as_str() == "async",
+            _ => prev_async = false,
+        }
+    }
+    false
+}
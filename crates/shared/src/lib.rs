@@ -4,22 +4,32 @@ extern crate fnv;
 
 use std::hash::{Hash, Hasher};
 
-pub const SCHEMA_VERSION: &str = "1";
+pub const SCHEMA_VERSION: &str = "47";
 
 #[derive(Deserialize)]
 pub struct Program {
     pub exports: Vec<Export>,
     pub enums: Vec<Enum>,
     pub imports: Vec<Import>,
+    pub structs: Vec<Struct>,
     pub custom_type_names: Vec<CustomTypeName>,
     pub version: String,
     pub schema_version: String,
 }
 
+/// Just the version fields of a `Program`, which can be decoded even when the
+/// rest of the format has changed.
+#[derive(Deserialize)]
+pub struct ProgramVersion {
+    pub version: String,
+    pub schema_version: String,
+}
+
 #[derive(Deserialize)]
 pub struct Import {
     pub module: Option<String>,
-    pub js_namespace: Option<String>,
+    /// Path of objects the import is nested in, outermost first
+    pub js_namespace: Option<Vec<String>>,
     pub kind: ImportKind,
 }
 
@@ -29,6 +39,8 @@ pub enum ImportKind {
     Function(ImportFunction),
     Static(ImportStatic),
     Type(ImportType),
+    Property(ImportProperty),
+    Enum(ImportEnum),
 }
 
 #[derive(Deserialize)]
@@ -39,28 +51,148 @@ pub struct ImportFunction {
     pub method: bool,
     pub js_new: bool,
     pub structural: bool,
+    /// Whether to check that the method exists on the class's prototype when
+    /// the module is loaded
+    pub final_: bool,
+    /// Symbol the method is keyed by in JS instead of its name
+    pub symbol: Option<ImportSymbol>,
+    pub variadic: bool,
+    /// Whether this method is bound to index access on `this` rather than
+    /// to a method call
+    pub indexing: Option<Indexing>,
+    /// Whether this method takes `this` by reference and returns the same
+    /// type, in which case `this` itself may be returned
+    pub returns_self: bool,
+    /// Whether the function is looked up on the global object every time
+    /// it's called, instead of once when the module is loaded
+    pub late_bound: bool,
+    /// Whether calling the function has no side effects, so bundlers may
+    /// drop calls whose results aren't used
+    pub pure: bool,
+    /// Whether the first argument is passed as `this` to a free function
+    /// rather than as an argument
+    pub this_arg: bool,
     pub getter: Option<String>,
     pub setter: Option<String>,
     pub class: Option<String>,
     pub function: Function,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSymbol {
+    /// `Symbol.name`, like `Symbol.iterator`
+    WellKnown(String),
+    /// `Symbol.for(key)`
+    Registered(String),
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Indexing {
+    /// `this[key]`
+    Getter,
+    /// `this[key] = value`
+    Setter,
+    /// `delete this[key]`
+    Deleter,
+}
+
 #[derive(Deserialize)]
 pub struct ImportStatic {
     pub module: Option<String>,
     pub name: String,
     pub shim: String,
+    /// Whether the value is looked up when first accessed, rather than its
+    /// module being imported from when the JS glue is loaded
+    pub lazy: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ImportProperty {
+    pub class: String,
+    pub name: String,
+    /// Descriptor of the `this` the property is read from and written to
+    pub this: Type,
+    pub ty: Type,
+    pub get_shim: String,
+    /// Shim writing the property, or `None` if it's read-only
+    pub set_shim: Option<String>,
+}
+
+/// A Rust enum standing for a set of JS constants, like `Node.ELEMENT_NODE`,
+/// which is passed to wasm as the index of its variant.
+#[derive(Deserialize)]
+pub struct ImportEnum {
+    pub name: String,
+    pub descriptor: Type,
+    /// Names of the constants each variant stands for, in order
+    pub variants: Vec<String>,
+    /// Whether values which aren't any of the constants are passed as an
+    /// extra last variant rather than rejected
+    pub non_exhaustive: bool,
 }
 
 #[derive(Deserialize)]
 pub struct ImportType {
+    pub name: String,
+    /// Descriptor of values of this type, or `None` if it's duck-typed and
+    /// passed around as a plain `JsValue`.
+    pub descriptor: Option<Type>,
+    /// Whether values of this type are JS iterators rather than instances of
+    /// a class named `name`.
+    pub iterator: bool,
+    /// Whether values passed to exports are checked with `instanceof`
+    pub instanceof: bool,
+    /// Names of the imported types this one inherits from, nearest first.
+    pub extends: Vec<String>,
+    /// TypeScript type of values of this type in exported signatures
+    pub typescript_type: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct Export {
     pub class: Option<String>,
     pub method: bool,
+    /// Whether this method takes `self` by value, after which the JS object
+    /// no longer points to anything
+    pub consume: bool,
+    /// Whether a panic in this function is rethrown as a JS `RustPanic` error
+    pub catch: bool,
+    /// Whether this is the module's `start` function, called automatically
+    /// once the module is instantiated instead of being exported to JS
+    pub start: bool,
+    /// Whether this is the method `new` on the class in JS calls
+    pub constructor: bool,
+    /// Whether this is an `async fn`, which returns a promise of what
+    /// `function` says it returns
+    pub async_: bool,
     pub function: Function,
+    /// Name of the Rust function, which the wasm export is named after since
+    /// `function.name` may be any JS name given with `js_name`
+    pub rust_name: String,
+    /// Runs of adjacent `bool` arguments, as `(first index, length)`, which are
+    /// passed to wasm as the bits of a single `u32` rather than one each.
+    pub packed_bools: Vec<(usize, usize)>,
+    /// Descriptor of the error type if the function returns a `Result`, in
+    /// which case an `Err` is thrown rather than returned.
+    pub throws: Option<Type>,
+}
+
+#[derive(Deserialize)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<StructField>,
+    /// Other names the struct's JS class is exported under
+    pub js_class_aliases: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StructField {
+    pub name: String,
+    /// Whether JS can only read the field, with no setter exported
+    pub readonly: bool,
+    pub ty: Type,
 }
 
 #[derive(Deserialize)]
@@ -72,13 +204,24 @@ pub struct Enum {
 #[derive(Deserialize)]
 pub struct EnumVariant {
     pub name: String,
+    /// The key of the variant in the JS enum object if it isn't `name`
+    pub js_name: Option<String>,
     pub value: u32
 }
 
+impl EnumVariant {
+    /// The name of this variant in JS
+    pub fn js_name(&self) -> &str {
+        self.js_name.as_ref().unwrap_or(&self.name)
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Function {
     pub name: String,
     pub arguments: Vec<Type>,
+    pub argument_names: Vec<String>,
+    pub nullable: Vec<bool>,
     pub ret: Option<Type>,
 }
 
@@ -106,6 +249,41 @@ pub fn free_function(struct_name: &str) -> String {
     return name
 }
 
+pub fn struct_field_get(struct_: &str, f: &str) -> String {
+    let mut name = String::from("__wbg_get_");
+    name.extend(struct_
+        .chars()
+        .flat_map(|s| s.to_lowercase()));
+    name.push('_');
+    name.push_str(f);
+    name
+}
+
+pub fn struct_field_set(struct_: &str, f: &str) -> String {
+    let mut name = String::from("__wbg_set_");
+    name.extend(struct_
+        .chars()
+        .flat_map(|s| s.to_lowercase()));
+    name.push('_');
+    name.push_str(f);
+    name
+}
+
+/// The name the `start` function is exported under. Being fixed, a crate
+/// marking two functions as `start` fails to link.
+pub const START_FUNCTION: &str = "__wbindgen_start";
+
+/// Whether `name` can be written as is where JS expects an identifier, rather
+/// than having to be quoted as a property key.
+pub fn is_js_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
 pub fn free_function_export_name(function_name: &str) -> String {
     function_name.to_string()
 }
@@ -146,16 +324,119 @@ pub const TYPE_SLICE_F64: u32 = 20;
 pub const TYPE_VECTOR_F64: u32 = 21;
 pub const TYPE_JS_OWNED: u32 = 22;
 pub const TYPE_JS_REF: u32 = 23;
+pub const TYPE_F32: u32 = 24;
+pub const TYPE_F64: u32 = 25;
+pub const TYPE_I64: u32 = 26;
+pub const TYPE_U64: u32 = 27;
+pub const TYPE_SLICE_JSVALUE: u32 = 28;
+pub const TYPE_CHAR: u32 = 29;
+pub const TYPE_SLICE_MUT_U8: u32 = 30;
+pub const TYPE_SLICE_MUT_I8: u32 = 31;
+pub const TYPE_SLICE_MUT_U16: u32 = 32;
+pub const TYPE_SLICE_MUT_I16: u32 = 33;
+pub const TYPE_SLICE_MUT_U32: u32 = 34;
+pub const TYPE_SLICE_MUT_I32: u32 = 35;
+pub const TYPE_SLICE_MUT_F32: u32 = 36;
+pub const TYPE_SLICE_MUT_F64: u32 = 37;
+// `u32` and `usize`, which JS needs to reinterpret as unsigned when it
+// receives them since they come out of wasm as an `i32`
+pub const TYPE_U32: u32 = 38;
+// `JsFuture`s, which are promises in JS
+pub const TYPE_PROMISE: u32 = 39;
+// Rust iterators returned from exports as `impl Iterator`
+pub const TYPE_ITERATOR: u32 = 40;
+// `JsDate`s, which are only ever passed by value so don't need a ref flag
+pub const TYPE_DATE: u32 = 41;
+// Integers narrower than 32 bits, which are passed like any other number but
+// described separately so JS knows what range they're in
+pub const TYPE_I8: u32 = 42;
+pub const TYPE_U8: u32 = 43;
+pub const TYPE_I16: u32 = 44;
+pub const TYPE_U16: u32 = 45;
+// `NonZeroU32` (and `NonZeroUsize`) and `NonZeroU64`, passed like `u32` and
+// `u64` but never zero
+pub const TYPE_NONZERO_U32: u32 = 46;
+pub const TYPE_NONZERO_U64: u32 = 47;
+// `()` and `PhantomData`, which have no value to pass at all
+pub const TYPE_UNIT: u32 = 48;
+// `&Fn` callbacks passed to imports, which JS may only call until the import
+// returns
+pub const TYPE_STACK_CLOSURE: u32 = 49;
+// `Box<[T]>` of the types with vectors above, which are passed exactly like
+// their vectors but can't grow
+pub const TYPE_BOXED_SLICE_U8: u32 = 50;
+pub const TYPE_BOXED_SLICE_I8: u32 = 51;
+pub const TYPE_BOXED_SLICE_U16: u32 = 52;
+pub const TYPE_BOXED_SLICE_I16: u32 = 53;
+pub const TYPE_BOXED_SLICE_U32: u32 = 54;
+pub const TYPE_BOXED_SLICE_I32: u32 = 55;
+pub const TYPE_BOXED_SLICE_F32: u32 = 56;
+pub const TYPE_BOXED_SLICE_F64: u32 = 57;
+pub const TYPE_BOXED_SLICE_JSVALUE: u32 = 58;
+// `&Closure`, which is passed to imports as the JS function it owns
+pub const TYPE_CLOSURE: u32 = 59;
 
-pub const TYPE_CUSTOM_START: u32 = 24;
+// Kept even so masking off the ref flag never dips below it, and far enough
+// above the last fixed descriptor that adding more of those doesn't move where
+// custom descriptors start (which would reshuffle every one of them).
+pub const TYPE_CUSTOM_START: u32 = 1000;
 pub const TYPE_CUSTOM_REF_FLAG: u32 = 1;
 
+// A fixed-size array of numbers (like `[u8; 32]`) is described by adding its
+// length times 1000, plus the descriptor of a vector of its elements, to this,
+// so custom descriptors must be smaller than this. Custom descriptors are
+// hashed into everything between `TYPE_CUSTOM_START` and this, so the wider
+// that is the less likely two types are to collide.
+pub const TYPE_ARRAY_OFFSET: u32 = 900_000;
+
+// A vector whose elements don't have a dedicated descriptor of their own (like
+// `Vec<String>`) is described by adding this to the element's descriptor, so
+// custom and array descriptors must be smaller than this.
+pub const TYPE_VECTOR_OFFSET: u32 = 1_000_000;
+
+// Each level of `Option` wrapping a type adds this to the descriptor of the
+// type it wraps, so all other descriptors (including vectors) must be smaller
+// than this. Descriptors are written out as eight digits, which leaves room
+// for nine levels.
+pub const TYPE_OPTION_OFFSET: u32 = 10_000_000;
+
 pub fn name_to_descriptor(name: &str) -> u32 {
-    const MAX: u32 = 10_000;
     let mut h = fnv::FnvHasher::default();
     name.hash(&mut h);
-    (((h.finish() as u32) % (MAX - TYPE_CUSTOM_START)) + TYPE_CUSTOM_START) & !1
+    (((h.finish() as u32) % (TYPE_ARRAY_OFFSET - TYPE_CUSTOM_START)) + TYPE_CUSTOM_START) & !1
+}
+
+/// Descriptor of the type `name` imported from `module`, which types of the
+/// same name imported from elsewhere don't share.
+pub fn imported_type_descriptor(module: Option<&str>, name: &str) -> u32 {
+    name_to_descriptor(&format!("{}:{}", module.unwrap_or(""), name))
+}
+
+/// Returns the descriptor of the elements of a vector described by `ty`, if
+/// `ty` describes such a vector.
+pub fn unwrap_vector_descriptor(ty: Type) -> Option<Type> {
+    if (TYPE_VECTOR_OFFSET..TYPE_OPTION_OFFSET).contains(&ty) {
+        Some(ty - TYPE_VECTOR_OFFSET)
+    } else {
+        None
+    }
+}
+
+/// Returns the length of a fixed-size array described by `ty` and the
+/// descriptor of a vector of its elements, if `ty` describes such an array.
+pub fn unwrap_array_descriptor(ty: Type) -> Option<(u32, Type)> {
+    if (TYPE_ARRAY_OFFSET..TYPE_VECTOR_OFFSET).contains(&ty) {
+        let ty = ty - TYPE_ARRAY_OFFSET;
+        Some((ty / 1000, ty % 1000))
+    } else {
+        None
+    }
+}
 
+/// Splits a descriptor into the number of `Option` layers wrapping it and the
+/// descriptor of the innermost type.
+pub fn unwrap_option_descriptor(ty: Type) -> (u32, Type) {
+    (ty / TYPE_OPTION_OFFSET, ty % TYPE_OPTION_OFFSET)
 }
 
 pub fn version() -> String {
@@ -16,6 +16,17 @@ pub struct Project {
     debug: bool,
     js: bool,
     node: bool,
+    deno: bool,
+    browser: bool,
+    check_arity: bool,
+    verbose_shims: bool,
+    free_on_gc: bool,
+    emit_descriptors: bool,
+    emit_api_schema: bool,
+    default_export: bool,
+    check_ranges: Option<bool>,
+    share_conversions: bool,
+    env_imports: bool,
 }
 
 pub fn project() -> Project {
@@ -30,6 +41,17 @@ pub fn project() -> Project {
         debug: true,
         js: false,
         node: false,
+        deno: false,
+        browser: false,
+        check_arity: false,
+        verbose_shims: false,
+        free_on_gc: false,
+        emit_descriptors: false,
+        emit_api_schema: false,
+        default_export: false,
+        check_ranges: None,
+        share_conversions: false,
+        env_imports: false,
         files: vec![
             ("Cargo.toml".to_string(), format!(r#"
                 [package]
@@ -56,7 +78,7 @@ pub fn project() -> Project {
                 const test = import("./test");
 
                 test.then(test => {
-                  test.test();
+                  return test.test();
                 }).catch(error => {
                   console.error(error);
                   process.exit(1);
@@ -143,11 +165,79 @@ impl Project {
         self
     }
 
+    pub fn deno(&mut self, deno: bool) -> &mut Project {
+        self.deno = deno;
+        self
+    }
+
+    pub fn browser(&mut self, browser: bool) -> &mut Project {
+        self.browser = browser;
+        self
+    }
+
+    pub fn check_arity(&mut self, check_arity: bool) -> &mut Project {
+        self.check_arity = check_arity;
+        self
+    }
+
+    pub fn verbose_shims(&mut self, verbose_shims: bool) -> &mut Project {
+        self.verbose_shims = verbose_shims;
+        self
+    }
+
+    pub fn free_on_gc(&mut self, free_on_gc: bool) -> &mut Project {
+        self.free_on_gc = free_on_gc;
+        self
+    }
+
+    pub fn emit_api_schema(&mut self, emit_api_schema: bool) -> &mut Project {
+        self.emit_api_schema = emit_api_schema;
+        self
+    }
+
+    pub fn check_ranges(&mut self, check_ranges: bool) -> &mut Project {
+        self.check_ranges = Some(check_ranges);
+        self
+    }
+
+    pub fn share_conversions(&mut self, share_conversions: bool) -> &mut Project {
+        self.share_conversions = share_conversions;
+        self
+    }
+
+    pub fn env_imports(&mut self, env_imports: bool) -> &mut Project {
+        self.env_imports = env_imports;
+        self
+    }
+
+    pub fn emit_descriptors(&mut self, emit_descriptors: bool) -> &mut Project {
+        self.emit_descriptors = emit_descriptors;
+        self
+    }
+
+    pub fn default_export(&mut self, default_export: bool) -> &mut Project {
+        self.default_export = default_export;
+        self
+    }
+
     pub fn js(&mut self, js: bool) -> &mut Project {
         self.js = js;
         self
     }
 
+    /// Builds the test crate with the given Rust edition rather than 2015
+    pub fn edition(&mut self, edition: &str) -> &mut Project {
+        {
+            let cargo_toml = self.files
+                .iter_mut()
+                .find(|f| f.0 == "Cargo.toml")
+                .expect("should have Cargo.toml file!");
+            let edition = format!("authors = []\n                edition = \"{}\"", edition);
+            cargo_toml.1 = cargo_toml.1.replacen("authors = []", &edition, 1);
+        }
+        self
+    }
+
     pub fn add_local_dependency(&mut self, name: &str, path: &str) -> &mut Project {
         {
             let cargo_toml = self.files
@@ -162,7 +252,90 @@ impl Project {
         self
     }
 
-    pub fn test(&mut self) {
+    /// Builds the project and checks that generating bindings for it fails
+    /// with an error mentioning `expected`.
+    pub fn test_bindgen_error(&mut self, expected: &str) {
+        let (root, as_a_module) = self.build();
+        let err = self.bindgen(&as_a_module)
+            .generate(&root)
+            .err()
+            .expect("generating bindings should have failed");
+        let err = format!("{:?}", err);
+        assert!(err.contains(expected), "unexpected error: {}", err);
+    }
+
+    /// Builds the project and returns the descriptions the macro embedded in
+    /// the wasm, as written out by `emit_descriptors`.
+    pub fn descriptors(&mut self) -> Vec<u8> {
+        let (root, as_a_module) = self.build();
+        cli::Bindgen::new()
+            .input_path(&as_a_module)
+            .emit_descriptors(true)
+            .generate(&root)
+            .expect("failed to run bindgen");
+        let mut descriptors = Vec::new();
+        File::open(root.join("out_descriptors.json")).unwrap()
+            .read_to_end(&mut descriptors).unwrap();
+        descriptors
+    }
+
+    /// Builds the project and returns the JS generated for it, without
+    /// running anything.
+    pub fn generated_js(&mut self) -> String {
+        let (root, as_a_module) = self.build();
+        self.bindgen(&as_a_module)
+            .generate(&root)
+            .expect("failed to run bindgen");
+        let mut js = String::new();
+        File::open(root.join("out.js")).unwrap()
+            .read_to_string(&mut js).unwrap();
+        js
+    }
+
+    /// Builds the project and returns the TypeScript declarations generated
+    /// for it, without running anything.
+    pub fn generated_ts(&mut self) -> String {
+        let (root, as_a_module) = self.build();
+        self.bindgen(&as_a_module)
+            .generate(&root)
+            .expect("failed to run bindgen");
+        let mut ts = String::new();
+        File::open(root.join("out.d.ts")).unwrap()
+            .read_to_string(&mut ts).unwrap();
+        ts
+    }
+
+    fn bindgen(&self, input: &Path) -> cli::Bindgen {
+        let mut b = cli::Bindgen::new();
+        b.input_path(input)
+            .typescript(true)
+            .nodejs(self.node)
+            .deno(self.deno)
+            .browser(self.browser)
+            .debug(self.debug)
+            .check_arity(self.check_arity)
+            .verbose_shims(self.verbose_shims)
+            .free_on_gc(self.free_on_gc)
+            .emit_descriptors(self.emit_descriptors)
+            .emit_api_schema(self.emit_api_schema)
+            .default_export(self.default_export)
+            .check_ranges(self.check_ranges.unwrap_or(self.debug))
+            .share_conversions(self.share_conversions)
+            .env_imports(self.env_imports);
+        b
+    }
+
+    /// Checks that compiling the project fails with an error mentioning
+    /// `expected`.
+    pub fn test_compile_error(&mut self, expected: &str) {
+        let root = self.write_files();
+        let output = cargo_build(&root).output().expect("failed to spawn `cargo`");
+        let err = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "compiling should have failed");
+        assert!(err.contains(expected), "unexpected error: {}", err);
+    }
+
+    fn write_files(&self) -> PathBuf {
         let root = root();
         drop(fs::remove_dir_all(&root));
         for &(ref file, ref contents) in self.files.iter() {
@@ -170,29 +343,28 @@ impl Project {
             fs::create_dir_all(dst.parent().unwrap()).unwrap();
             fs::File::create(&dst).unwrap().write_all(contents.as_ref()).unwrap();
         }
+        root
+    }
 
-        let target_dir = root.parent().unwrap() // chop off test name
-            .parent().unwrap(); // chop off `generated-tests`
+    fn build(&mut self) -> (PathBuf, PathBuf) {
+        let root = self.write_files();
 
-        let mut cmd = Command::new("cargo");
-        cmd.arg("build")
-            .arg("--target")
-            .arg("wasm32-unknown-unknown")
-            .current_dir(&root)
-            .env("CARGO_TARGET_DIR", &target_dir);
-        run(&mut cmd, "cargo");
+        let out = {
+            run(&mut cargo_build(&root), "cargo");
 
-        let idx = IDX.with(|x| *x);
-        let out = target_dir.join(&format!("wasm32-unknown-unknown/debug/test{}.wasm", idx));
+            let idx = IDX.with(|x| *x);
+            target_dir(&root).join(&format!("wasm32-unknown-unknown/debug/test{}.wasm", idx))
+        };
 
         let as_a_module = root.join("out.wasm");
         fs::copy(&out, &as_a_module).unwrap();
+        (root, as_a_module)
+    }
 
-        cli::Bindgen::new()
-            .input_path(&as_a_module)
-            .typescript(true)
-            .nodejs(self.node)
-            .debug(self.debug)
+    pub fn test(&mut self) {
+        let (root, as_a_module) = self.build();
+
+        self.bindgen(&as_a_module)
             .generate(&root)
             .expect("failed to run bindgen");
 
@@ -218,7 +390,7 @@ impl Project {
         symlink_dir(&cwd.join("node_modules"), &root.join("node_modules")).unwrap();
 
         if self.node {
-            let mut cmd = Command::new("node");
+            let mut cmd = node(self.free_on_gc);
             cmd.arg(root.join("run-node.js"))
                 .current_dir(&root);
             run(&mut cmd, "node");
@@ -234,7 +406,7 @@ impl Project {
             cmd.arg("webpack").current_dir(&root);
             run(&mut cmd, "yarn");
 
-            let mut cmd = Command::new("node");
+            let mut cmd = node(self.free_on_gc);
             cmd.arg(root.join("bundle.js"))
                 .current_dir(&root);
             run(&mut cmd, "node");
@@ -242,6 +414,21 @@ impl Project {
     }
 }
 
+fn target_dir(root: &Path) -> &Path {
+    root.parent().unwrap() // chop off test name
+        .parent().unwrap() // chop off `generated-tests`
+}
+
+fn cargo_build(root: &Path) -> Command {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build")
+        .arg("--target")
+        .arg("wasm32-unknown-unknown")
+        .current_dir(root)
+        .env("CARGO_TARGET_DIR", target_dir(root));
+    cmd
+}
+
 #[cfg(unix)]
 fn symlink_dir(a: &Path, b: &Path) -> io::Result<()> {
     use std::os::unix::fs::symlink;
@@ -254,6 +441,15 @@ fn symlink_dir(a: &Path, b: &Path) -> io::Result<()> {
     symlink_dir(a, b)
 }
 
+// Tests of `free_on_gc` need to be able to collect garbage themselves
+fn node(expose_gc: bool) -> Command {
+    let mut cmd = Command::new("node");
+    if expose_gc {
+        cmd.arg("--expose-gc");
+    }
+    cmd
+}
+
 fn run(cmd: &mut Command, program: &str) {
     println!("···················································");
     println!("running {:?}", cmd);
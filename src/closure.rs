@@ -0,0 +1,320 @@
+//! Support for passing Rust closures to JS as callbacks
+//!
+//! A `Closure` owns a Rust closure along with the JS function that calls into
+//! it. The JS function can be handed to imported functions by reference, for
+//! example:
+//!
+//! ```ignore
+//! #[wasm_bindgen]
+//! extern {
+//!     fn setInterval(f: &Closure<FnMut()>, ms: u32) -> f64;
+//! }
+//!
+//! let cb = Closure::new(|| log("tick"));
+//! setInterval(&cb, 1000);
+//! ```
+//!
+//! The Rust closure lives exactly as long as the `Closure` does. Once it's
+//! dropped the JS function stays around but throws an exception if it's
+//! invoked.
+//!
+//! Callbacks only needed for the duration of a call, like the comparator of
+//! `Array.prototype.sort`, can instead be passed to imports as a plain `&Fn`,
+//! which needn't be `'static`:
+//!
+//! ```ignore
+//! #[wasm_bindgen]
+//! extern {
+//!     fn sort_by(array: &JsValue, cmp: &Fn(f64, f64) -> f64);
+//! }
+//!
+//! let descending = true;
+//! sort_by(&array, &|a, b| if descending { b - a } else { a - b });
+//! ```
+//!
+//! JS gets a function for the callback which throws an exception if it's
+//! invoked after the import has returned.
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::vec;
+
+use JsValue;
+use convert::{Descriptor, ToRefWasmBoundary, Stack, DESCRIPTOR_CLOSURE,
+              DESCRIPTOR_STACK_CLOSURE};
+
+/// A Rust closure which can be called from JS.
+///
+/// The type parameter is the closure's signature, such as `Fn(u32)` or
+/// `FnMut(String, bool) -> f64`. Closures can take up to four arguments, each
+/// of which is one of `JsValue`, `bool`, `String` or a number. The same types
+/// (or `()`) can be returned.
+pub struct Closure<T: ?Sized> {
+    js: JsValue,
+    _marker: PhantomData<Box<T>>,
+}
+
+// The boxed closure is owned by the JS function calling it, which frees it
+// with `__wbindgen_closure_free` once it's been dropped and no call to it is
+// still running. That's tracked in JS, as an exception thrown through a call
+// unwinds past Rust without running any of its code. JS likewise refuses to
+// call an `FnMut` again while it's running, so it's erased into an `Fn`.
+struct Inner {
+    arity: u32,
+    f: Box<Fn(vec::IntoIter<JsValue>) -> JsValue>,
+}
+
+impl<T: ?Sized> Closure<T> {
+    /// Creates a new JS function which calls `f` when it's invoked.
+    pub fn new<F>(f: F) -> Closure<T>
+        where T: WasmClosure<F>,
+    {
+        let inner = Box::new(Inner {
+            arity: T::ARITY,
+            f: T::erase(f),
+        });
+        let idx = unsafe {
+            __wbindgen_closure_new(Box::into_raw(inner) as u32, T::ARITY, T::MUTABLE as u32)
+        };
+        Closure {
+            js: JsValue { idx },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Leaks this closure, keeping the JS function callable for the rest of
+    /// the program.
+    ///
+    /// This is useful for callbacks like event handlers which are never
+    /// unregistered.
+    pub fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl<T: ?Sized> Drop for Closure<T> {
+    fn drop(&mut self) {
+        unsafe {
+            __wbindgen_closure_drop(self.js.idx);
+        }
+    }
+}
+
+impl<T: ?Sized> ToRefWasmBoundary for Closure<T> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_CLOSURE;
+
+    fn to_abi_ref(&self, extra: &mut Stack) -> u32 {
+        self.js.to_abi_ref(extra)
+    }
+}
+
+/// Signatures which a `Closure` can be created with.
+///
+/// This is implemented for `Fn` and `FnMut` trait objects with up to four
+/// arguments and isn't typically used directly.
+pub trait WasmClosure<F> {
+    #[doc(hidden)]
+    const ARITY: u32;
+    #[doc(hidden)]
+    const MUTABLE: bool;
+
+    #[doc(hidden)]
+    fn erase(f: F) -> Box<Fn(vec::IntoIter<JsValue>) -> JsValue>;
+}
+
+/// Types which can be received as an argument of a `Closure`.
+pub trait ClosureArg {
+    #[doc(hidden)]
+    fn from_closure_arg(js: JsValue) -> Self;
+}
+
+/// Types which can be returned from a `Closure`.
+pub trait ClosureReturn {
+    #[doc(hidden)]
+    fn into_closure_return(self) -> JsValue;
+}
+
+impl ClosureArg for JsValue {
+    fn from_closure_arg(js: JsValue) -> JsValue {
+        js
+    }
+}
+
+impl ClosureArg for bool {
+    fn from_closure_arg(js: JsValue) -> bool {
+        match js.as_bool() {
+            Some(b) => b,
+            None => ::throw("expected a boolean argument"),
+        }
+    }
+}
+
+impl ClosureArg for String {
+    fn from_closure_arg(js: JsValue) -> String {
+        match js.as_string() {
+            Some(s) => s,
+            None => ::throw("expected a string argument"),
+        }
+    }
+}
+
+impl ClosureReturn for () {
+    fn into_closure_return(self) -> JsValue {
+        JsValue::undefined()
+    }
+}
+
+impl ClosureReturn for JsValue {
+    fn into_closure_return(self) -> JsValue {
+        self
+    }
+}
+
+impl ClosureReturn for bool {
+    fn into_closure_return(self) -> JsValue {
+        JsValue::from_bool(self)
+    }
+}
+
+impl ClosureReturn for String {
+    fn into_closure_return(self) -> JsValue {
+        JsValue::from_str(&self)
+    }
+}
+
+macro_rules! numbers {
+    ($($n:ident)*) => ($(
+        impl ClosureArg for $n {
+            fn from_closure_arg(js: JsValue) -> $n {
+                match js.as_f64() {
+                    Some(n) => n as $n,
+                    None => ::throw("expected a number argument"),
+                }
+            }
+        }
+
+        impl ClosureReturn for $n {
+            fn into_closure_return(self) -> JsValue {
+                JsValue::from_f64(self as f64)
+            }
+        }
+    )*)
+}
+
+numbers! { i8 u8 i16 u16 i32 u32 f32 f64 }
+
+macro_rules! closures {
+    ($(($arity:expr) $($var:ident: $arg:ident)*;)*) => ($(
+        impl<F, R, $($arg),*> WasmClosure<F> for Fn($($arg),*) -> R
+            where F: Fn($($arg),*) -> R + 'static,
+                  R: ClosureReturn,
+                  $($arg: ClosureArg,)*
+        {
+            const ARITY: u32 = $arity;
+            const MUTABLE: bool = false;
+
+            #[allow(unused_variables, unused_mut)]
+            fn erase(f: F) -> Box<Fn(vec::IntoIter<JsValue>) -> JsValue> {
+                Box::new(move |mut args: vec::IntoIter<JsValue>| {
+                    $(let $var = $arg::from_closure_arg(next_arg(&mut args));)*
+                    f($($var),*).into_closure_return()
+                })
+            }
+        }
+
+        impl<'a, R, $($arg),*> ToRefWasmBoundary for Fn($($arg),*) -> R + 'a
+            where R: ClosureReturn,
+                  $($arg: ClosureArg,)*
+        {
+            type Abi = u32;
+            const DESCRIPTOR: Descriptor = DESCRIPTOR_STACK_CLOSURE;
+
+            // Passes a pointer to a boxed `Inner` followed by the arity. JS
+            // frees it with `__wbindgen_closure_free` as soon as the
+            // import returns, while `self` is still borrowed.
+            #[allow(unused_variables, unused_mut)]
+            fn to_abi_ref<'b>(&'b self, extra: &mut Stack) -> u32 {
+                let f: Box<Fn(vec::IntoIter<JsValue>) -> JsValue> = unsafe {
+                    // Erases the lifetime of the borrow, which JS can't use
+                    // past the end of the call
+                    let f: Box<Fn(vec::IntoIter<JsValue>) -> JsValue + 'b> =
+                        Box::new(move |mut args: vec::IntoIter<JsValue>| {
+                            $(let $var = $arg::from_closure_arg(next_arg(&mut args));)*
+                            self($($var),*).into_closure_return()
+                        });
+                    mem::transmute(f)
+                };
+                let inner = Box::new(Inner {
+                    arity: $arity,
+                    f,
+                });
+                extra.push($arity);
+                Box::into_raw(inner) as u32
+            }
+        }
+
+        impl<F, R, $($arg),*> WasmClosure<F> for FnMut($($arg),*) -> R
+            where F: FnMut($($arg),*) -> R + 'static,
+                  R: ClosureReturn,
+                  $($arg: ClosureArg,)*
+        {
+            const ARITY: u32 = $arity;
+            const MUTABLE: bool = true;
+
+            #[allow(unused_variables, unused_mut)]
+            fn erase(f: F) -> Box<Fn(vec::IntoIter<JsValue>) -> JsValue> {
+                let f = UnsafeCell::new(f);
+                Box::new(move |mut args: vec::IntoIter<JsValue>| {
+                    $(let $var = $arg::from_closure_arg(next_arg(&mut args));)*
+                    unsafe { (*f.get())($($var),*).into_closure_return() }
+                })
+            }
+        }
+    )*)
+}
+
+closures! {
+    (0);
+    (1) a: A;
+    (2) a: A b: B;
+    (3) a: A b: B c: C;
+    (4) a: A b: B c: C d: D;
+}
+
+fn next_arg(args: &mut vec::IntoIter<JsValue>) -> JsValue {
+    match args.next() {
+        Some(arg) => arg,
+        None => ::throw("too few arguments passed to closure"),
+    }
+}
+
+/// Invoked from JS through `__wbindgen_closure_call` with the owned heap
+/// indices of the arguments, of which only the first `arity` are valid.
+#[doc(hidden)]
+pub unsafe fn __call(ptr: u32, args: [u32; 4]) -> u32 {
+    let inner = &*(ptr as *const Inner);
+    let args = args[..inner.arity as usize]
+        .iter()
+        .map(|&idx| JsValue { idx })
+        .collect::<Vec<_>>();
+    let ret = (inner.f)(args.into_iter());
+    let idx = ret.idx;
+    mem::forget(ret);
+    return idx
+}
+
+/// Invoked from JS through `__wbindgen_closure_free` once a closure has been
+/// dropped, or the import a `&Fn` was passed to has returned, and it isn't
+/// running any more.
+#[doc(hidden)]
+pub unsafe fn __free(ptr: u32) {
+    drop(Box::from_raw(ptr as *mut Inner));
+}
+
+#[wasm_import_module = "__wbindgen_placeholder__"]
+extern {
+    fn __wbindgen_closure_new(ptr: u32, arity: u32, mutable: u32) -> u32;
+    fn __wbindgen_closure_drop(idx: u32);
+}
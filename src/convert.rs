@@ -1,30 +1,117 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::marker::PhantomData;
 use std::mem::{self, ManuallyDrop};
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::rc::Rc;
 use std::slice;
 use std::str;
+use std::sync::Arc;
+use std::time::Duration;
 
-use super::JsValue;
+use super::{ExceptionSource, JsValue};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub struct Descriptor {
     #[doc(hidden)]
-    pub __x: [u8; 4],
+    pub __x: [u8; 8],
 }
 
 // keep in sync with shared/src/lib.rs TYPE constants
-// pub const DESCRIPTOR_CUSTOM_REF_FLAG: Descriptor = Descriptor { __x: *b"  1", };
-pub const DESCRIPTOR_NUMBER: Descriptor = Descriptor { __x: *b"   2", };
-pub const DESCRIPTOR_BORROWED_STR: Descriptor = Descriptor { __x: *b"   3", };
-pub const DESCRIPTOR_STRING: Descriptor = Descriptor { __x: *b"   4", };
-pub const DESCRIPTOR_BOOLEAN: Descriptor = Descriptor { __x: *b"   5", };
+// pub const DESCRIPTOR_CUSTOM_REF_FLAG: Descriptor = Descriptor { __x: *b"       1", };
+pub const DESCRIPTOR_NUMBER: Descriptor = Descriptor { __x: *b"       2", };
+pub const DESCRIPTOR_BORROWED_STR: Descriptor = Descriptor { __x: *b"       3", };
+pub const DESCRIPTOR_STRING: Descriptor = Descriptor { __x: *b"       4", };
+pub const DESCRIPTOR_BOOLEAN: Descriptor = Descriptor { __x: *b"       5", };
+
+pub const DESCRIPTOR_JS_OWNED: Descriptor = Descriptor { __x: *b"      22", };
+pub const DESCRIPTOR_JS_REF: Descriptor = Descriptor { __x: *b"      23", };
+pub const DESCRIPTOR_F32: Descriptor = Descriptor { __x: *b"      24", };
+pub const DESCRIPTOR_F64: Descriptor = Descriptor { __x: *b"      25", };
+pub const DESCRIPTOR_I64: Descriptor = Descriptor { __x: *b"      26", };
+pub const DESCRIPTOR_U64: Descriptor = Descriptor { __x: *b"      27", };
+pub const DESCRIPTOR_CHAR: Descriptor = Descriptor { __x: *b"      29", };
+pub const DESCRIPTOR_U32: Descriptor = Descriptor { __x: *b"      38", };
+pub const DESCRIPTOR_PROMISE: Descriptor = Descriptor { __x: *b"      39", };
+pub const DESCRIPTOR_ITERATOR: Descriptor = Descriptor { __x: *b"      40", };
+pub const DESCRIPTOR_DATE: Descriptor = Descriptor { __x: *b"      41", };
+pub const DESCRIPTOR_I8: Descriptor = Descriptor { __x: *b"      42", };
+pub const DESCRIPTOR_U8: Descriptor = Descriptor { __x: *b"      43", };
+pub const DESCRIPTOR_I16: Descriptor = Descriptor { __x: *b"      44", };
+pub const DESCRIPTOR_U16: Descriptor = Descriptor { __x: *b"      45", };
+pub const DESCRIPTOR_NONZERO_U32: Descriptor = Descriptor { __x: *b"      46", };
+pub const DESCRIPTOR_NONZERO_U64: Descriptor = Descriptor { __x: *b"      47", };
+pub const DESCRIPTOR_UNIT: Descriptor = Descriptor { __x: *b"      48", };
+pub const DESCRIPTOR_STACK_CLOSURE: Descriptor = Descriptor { __x: *b"      49", };
+pub const DESCRIPTOR_BOXED_SLICE_JSVALUE: Descriptor = Descriptor { __x: *b"      58", };
+pub const DESCRIPTOR_CLOSURE: Descriptor = Descriptor { __x: *b"      59", };
+
+// Descriptors are right-aligned ASCII numbers, and `| 0x10` turns a padding
+// space into a '0' while leaving digits untouched, which lets these add the
+// offsets from shared/src/lib.rs to the descriptor of another type.
+
+// Adds `TYPE_OPTION_OFFSET`.
+macro_rules! option_descriptor {
+    ($d:expr) => (Descriptor {
+        __x: [
+            ($d.__x[0] | 0x10) + 1,
+            $d.__x[1] | 0x10,
+            $d.__x[2] | 0x10,
+            $d.__x[3] | 0x10,
+            $d.__x[4] | 0x10,
+            $d.__x[5] | 0x10,
+            $d.__x[6] | 0x10,
+            $d.__x[7],
+        ],
+    })
+}
 
-pub const DESCRIPTOR_JS_OWNED: Descriptor = Descriptor { __x: *b"  22", };
-pub const DESCRIPTOR_JS_REF: Descriptor = Descriptor { __x: *b"  23", };
+// Adds `TYPE_VECTOR_OFFSET`.
+macro_rules! vector_descriptor {
+    ($d:expr) => (Descriptor {
+        __x: [
+            $d.__x[0],
+            ($d.__x[1] | 0x10) + 1,
+            $d.__x[2] | 0x10,
+            $d.__x[3] | 0x10,
+            $d.__x[4] | 0x10,
+            $d.__x[5] | 0x10,
+            $d.__x[6] | 0x10,
+            $d.__x[7],
+        ],
+    })
+}
+
+// Adds `TYPE_ARRAY_OFFSET` and `$n` times 1000 to the descriptor of a vector
+// of numbers, which is never more than two digits.
+macro_rules! array_descriptor {
+    ($d:expr, $n:expr) => (Descriptor {
+        __x: [
+            $d.__x[0],
+            $d.__x[1],
+            b'9',
+            b'0' + ($n / 10) as u8,
+            b'0' + ($n % 10) as u8,
+            $d.__x[5] | 0x10,
+            $d.__x[6] | 0x10,
+            $d.__x[7],
+        ],
+    })
+}
 
 pub trait WasmBoundary {
     type Abi: WasmAbi;
     const DESCRIPTOR: Descriptor;
 
+    /// The number of values `into_abi` pushes onto, and `from_abi` pops off,
+    /// the `Stack` in addition to the returned `Abi` value.
+    #[doc(hidden)]
+    const STACK_SLOTS: usize = 0;
+
     fn into_abi(self, extra: &mut Stack) -> Self::Abi;
     unsafe fn from_abi(js: Self::Abi, extra: &mut Stack) -> Self;
 }
@@ -33,6 +120,9 @@ pub trait FromRefWasmBoundary {
     type Abi: WasmAbi;
     const DESCRIPTOR: Descriptor;
     type RefAnchor: Deref<Target = Self>;
+    /// See `WasmBoundary::STACK_SLOTS`.
+    #[doc(hidden)]
+    const STACK_SLOTS: usize = 0;
 
     unsafe fn from_abi_ref(js: Self::Abi, extra: &mut Stack) -> Self::RefAnchor;
 }
@@ -41,6 +131,9 @@ pub trait FromRefMutWasmBoundary {
     type Abi: WasmAbi;
     const DESCRIPTOR: Descriptor;
     type RefAnchor: DerefMut<Target = Self>;
+    /// See `WasmBoundary::STACK_SLOTS`.
+    #[doc(hidden)]
+    const STACK_SLOTS: usize = 0;
 
     unsafe fn from_abi_ref_mut(js: Self::Abi, extra: &mut Stack) -> Self::RefAnchor;
 }
@@ -48,10 +141,56 @@ pub trait FromRefMutWasmBoundary {
 pub trait ToRefWasmBoundary {
     type Abi: WasmAbi;
     const DESCRIPTOR: Descriptor;
+    /// See `WasmBoundary::STACK_SLOTS`.
+    #[doc(hidden)]
+    const STACK_SLOTS: usize = 0;
 
     fn to_abi_ref(&self, extra: &mut Stack) -> u32;
 }
 
+/// Types which can be passed from Rust to JS by value, as the return value of
+/// an export or an argument of an import, whether or not they can also come
+/// back from JS.
+///
+/// This is implemented for everything which is `WasmBoundary`, and on its own
+/// for types like tuples which have no conversion out of a JS value, so that
+/// receiving one from JS is a compile error.
+pub trait ToWasmBoundary {
+    type Abi: WasmAbi;
+    const DESCRIPTOR: Descriptor;
+    /// See `WasmBoundary::STACK_SLOTS`.
+    #[doc(hidden)]
+    const STACK_SLOTS: usize = 0;
+
+    fn to_abi(self, extra: &mut Stack) -> Self::Abi;
+}
+
+impl<T: WasmBoundary> ToWasmBoundary for T {
+    type Abi = T::Abi;
+    const DESCRIPTOR: Descriptor = T::DESCRIPTOR;
+    const STACK_SLOTS: usize = T::STACK_SLOTS;
+
+    fn to_abi(self, extra: &mut Stack) -> T::Abi {
+        self.into_abi(extra)
+    }
+}
+
+/// Error types of imports tagged with `#[wasm_bindgen(catch)]`, which are
+/// created from the exception that was caught.
+///
+/// This is implemented for `JsValue`, `JsError` and any other type which can
+/// be converted from a `JsValue`, so a typed Rust error only needs a
+/// `From<JsValue>` implementation to be used as the `Err` of an import.
+pub trait FromCaughtException {
+    fn from_caught_exception(value: JsValue, source: ExceptionSource) -> Self;
+}
+
+impl<E: From<JsValue>> FromCaughtException for E {
+    fn from_caught_exception(value: JsValue, _source: ExceptionSource) -> E {
+        E::from(value)
+    }
+}
+
 pub trait Stack {
     fn push(&mut self, bits: u32);
     fn pop(&mut self) -> u32;
@@ -62,19 +201,22 @@ pub trait Stack {
 ///
 /// This is an unsafe trait to implement as there's no guarantee the type is
 /// actually safe to transfer across the was boundary, it's up to you to
-/// guarantee this so codegen works correctly.
+/// guarantee this so codegen works correctly. Implementors must also be valid
+/// when all zeros, as that's what's passed when there's no value to pass.
 pub unsafe trait WasmAbi {}
 
 unsafe impl WasmAbi for u32 {}
 unsafe impl WasmAbi for u64 {}
 unsafe impl WasmAbi for f32 {}
 unsafe impl WasmAbi for f64 {}
+// Zero-sized arguments are left out of wasm signatures entirely
+unsafe impl WasmAbi for () {}
 
 macro_rules! simple {
-    ($($t:tt)*) => ($(
+    ($($t:tt => $descriptor:ident)*) => ($(
         impl WasmBoundary for $t {
             type Abi = $t;
-            const DESCRIPTOR: Descriptor = DESCRIPTOR_NUMBER;
+            const DESCRIPTOR: Descriptor = $descriptor;
 
             fn into_abi(self, _extra: &mut Stack) -> $t { self }
             unsafe fn from_abi(js: $t, _extra: &mut Stack) -> $t { js }
@@ -82,13 +224,72 @@ macro_rules! simple {
     )*)
 }
 
-simple!(u32 u64 f32 f64);
+// Floats get their own descriptors so they're always passed as actual wasm
+// floats, which keeps NaN payloads and the sign of zero intact.
+simple! {
+    u32 => DESCRIPTOR_U32
+    f32 => DESCRIPTOR_F32
+    f64 => DESCRIPTOR_F64
+}
+
+// A `Duration` is a number of milliseconds in JS, like a `setTimeout` delay.
+// Durations over about 285 millennia lose precision as an `f64`, and going the
+// other way negative and `NaN` durations become zero while ones too long for a
+// `Duration` become the longest one possible.
+impl WasmBoundary for Duration {
+    type Abi = f64;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_F64;
+
+    fn into_abi(self, _extra: &mut Stack) -> f64 {
+        self.as_secs() as f64 * 1000.0 + self.subsec_nanos() as f64 / 1_000_000.0
+    }
+
+    unsafe fn from_abi(js: f64, _extra: &mut Stack) -> Duration {
+        if !(js > 0.0) {
+            return Duration::new(0, 0)
+        }
+        let secs = (js / 1000.0).trunc();
+        if secs >= u64::max_value() as f64 {
+            return Duration::new(u64::max_value(), 999_999_999)
+        }
+        let nanos = ((js - secs * 1000.0) * 1_000_000.0).round().max(0.0) as u32;
+        Duration::new(secs as u64, cmp::min(nanos, 999_999_999))
+    }
+}
+
+// JS can't receive a wasm `i64` so 64-bit integers are passed as their low
+// 32 bits with the high 32 bits pushed onto the stack, and the JS side puts
+// the two halves back together as a `BigInt`.
+macro_rules! sixty_four {
+    ($($t:tt => $descriptor:ident)*) => ($(
+        impl WasmBoundary for $t {
+            type Abi = u32;
+            const DESCRIPTOR: Descriptor = $descriptor;
+            const STACK_SLOTS: usize = 1;
+
+            fn into_abi(self, extra: &mut Stack) -> u32 {
+                extra.push(((self as u64) >> 32) as u32);
+                self as u32
+            }
+
+            unsafe fn from_abi(js: u32, extra: &mut Stack) -> $t {
+                let high = extra.pop() as u64;
+                ((high << 32) | (js as u64)) as $t
+            }
+        }
+    )*)
+}
+
+sixty_four! {
+    i64 => DESCRIPTOR_I64
+    u64 => DESCRIPTOR_U64
+}
 
 macro_rules! as_u32 {
-    ($($t:tt)*) => ($(
+    ($($t:tt => $descriptor:ident)*) => ($(
         impl WasmBoundary for $t {
             type Abi = u32;
-            const DESCRIPTOR: Descriptor = DESCRIPTOR_NUMBER;
+            const DESCRIPTOR: Descriptor = $descriptor;
 
             fn into_abi(self, _extra: &mut Stack) -> u32 { self as u32 }
             unsafe fn from_abi(js: u32, _extra: &mut Stack) -> $t { js as $t }
@@ -96,7 +297,47 @@ macro_rules! as_u32 {
     )*)
 }
 
-as_u32!(i8 u8 i16 u16 i32 isize usize);
+// Everything here is 32 bits or narrower on wasm32, and all but `usize` read
+// the same whether JS takes the `i32` they're passed as to be signed or not.
+as_u32! {
+    i8 => DESCRIPTOR_I8
+    u8 => DESCRIPTOR_U8
+    i16 => DESCRIPTOR_I16
+    u16 => DESCRIPTOR_U16
+    i32 => DESCRIPTOR_NUMBER
+    isize => DESCRIPTOR_NUMBER
+    usize => DESCRIPTOR_U32
+}
+
+// JS refuses to pass zero to an export taking a `NonZero*`, but imports can
+// return anything so it's checked for again here. An `Option` of one is passed
+// like any other `Option` rather than using zero for `None`, as the blanket
+// `Option<T>` impl can't be specialized.
+macro_rules! non_zero {
+    ($($t:ident($inner:ident) => $descriptor:ident)*) => ($(
+        impl WasmBoundary for $t {
+            type Abi = <$inner as WasmBoundary>::Abi;
+            const DESCRIPTOR: Descriptor = $descriptor;
+            const STACK_SLOTS: usize = <$inner as WasmBoundary>::STACK_SLOTS;
+
+            fn into_abi(self, extra: &mut Stack) -> Self::Abi {
+                self.get().into_abi(extra)
+            }
+
+            unsafe fn from_abi(js: Self::Abi, extra: &mut Stack) -> $t {
+                $t::new($inner::from_abi(js, extra))
+                    .unwrap_or_else(|| ::throw(concat!("expected a non-zero value for `",
+                                                       stringify!($t), "`")))
+            }
+        }
+    )*)
+}
+
+non_zero! {
+    NonZeroU32(u32) => DESCRIPTOR_NONZERO_U32
+    NonZeroUsize(usize) => DESCRIPTOR_NONZERO_U32
+    NonZeroU64(u64) => DESCRIPTOR_NONZERO_U64
+}
 
 impl WasmBoundary for bool {
     type Abi = u32;
@@ -106,6 +347,37 @@ impl WasmBoundary for bool {
     unsafe fn from_abi(js: u32, _extra: &mut Stack) -> bool { js != 0 }
 }
 
+impl WasmBoundary for char {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_CHAR;
+
+    fn into_abi(self, _extra: &mut Stack) -> u32 { self as u32 }
+    unsafe fn from_abi(js: u32, _extra: &mut Stack) -> char {
+        char::from_u32(js).expect("invalid Unicode scalar value")
+    }
+}
+
+// Zero-sized types have no value to pass, so like a missing return value they
+// don't use any wasm arguments and JS doesn't convert anything for them. This
+// lets generic code be instantiated with `()` or `PhantomData`.
+impl WasmBoundary for () {
+    type Abi = ();
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_UNIT;
+
+    fn into_abi(self, _extra: &mut Stack) {}
+    unsafe fn from_abi(_js: (), _extra: &mut Stack) {}
+}
+
+impl<T: ?Sized> WasmBoundary for PhantomData<T> {
+    type Abi = ();
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_UNIT;
+
+    fn into_abi(self, _extra: &mut Stack) {}
+    unsafe fn from_abi(_js: (), _extra: &mut Stack) -> PhantomData<T> {
+        PhantomData
+    }
+}
+
 impl<T> WasmBoundary for *const T {
     type Abi = u32;
     const DESCRIPTOR: Descriptor = DESCRIPTOR_NUMBER;
@@ -123,10 +395,11 @@ impl<T> WasmBoundary for *mut T {
 }
 
 macro_rules! vectors {
-    ($($t:ident => ($slice:expr, $owned:expr))*) => ($(
+    ($($t:ident => ($slice:expr, $owned:expr, $slice_mut:expr, $boxed:expr))*) => ($(
         impl WasmBoundary for Box<[$t]> {
             type Abi = u32;
-            const DESCRIPTOR: Descriptor = Descriptor { __x: *$owned };
+            const DESCRIPTOR: Descriptor = Descriptor { __x: *$boxed };
+            const STACK_SLOTS: usize = 1;
 
             fn into_abi(self, extra: &mut Stack) -> u32 {
                 let ptr = self.as_ptr();
@@ -139,13 +412,32 @@ macro_rules! vectors {
             unsafe fn from_abi(js: u32, extra: &mut Stack) -> Box<[$t]> {
                 let ptr = <*mut $t>::from_abi(js, extra);
                 let len = extra.pop() as usize;
+                // See `SliceAnchor::new`, nothing was allocated here anyway
+                if len == 0 {
+                    return Vec::new().into_boxed_slice()
+                }
                 Vec::from_raw_parts(ptr, len, len).into_boxed_slice()
             }
         }
 
+        impl WasmBoundary for Vec<$t> {
+            type Abi = u32;
+            const DESCRIPTOR: Descriptor = Descriptor { __x: *$owned };
+            const STACK_SLOTS: usize = 1;
+
+            fn into_abi(self, extra: &mut Stack) -> u32 {
+                self.into_boxed_slice().into_abi(extra)
+            }
+
+            unsafe fn from_abi(js: u32, extra: &mut Stack) -> Vec<$t> {
+                <Box<[$t]>>::from_abi(js, extra).into()
+            }
+        }
+
         impl ToRefWasmBoundary for [$t] {
             type Abi = u32;
             const DESCRIPTOR: Descriptor = Descriptor { __x: *$slice };
+            const STACK_SLOTS: usize = 1;
 
             fn to_abi_ref(&self, extra: &mut Stack) -> u32 {
                 let ptr = self.as_ptr();
@@ -158,13 +450,24 @@ macro_rules! vectors {
         impl FromRefWasmBoundary for [$t] {
             type Abi = u32;
             const DESCRIPTOR: Descriptor = Descriptor { __x: *$slice };
+            const STACK_SLOTS: usize = 1;
             type RefAnchor = SliceAnchor<$t>;
 
             unsafe fn from_abi_ref(js: u32, extra: &mut Stack) -> SliceAnchor<$t> {
-                SliceAnchor {
-                    ptr: <*mut $t>::from_abi(js, extra),
-                    len: extra.pop() as usize,
-                }
+                let ptr = <*mut $t>::from_abi(js, extra);
+                SliceAnchor::new(ptr, extra.pop() as usize)
+            }
+        }
+
+        impl FromRefMutWasmBoundary for [$t] {
+            type Abi = u32;
+            const DESCRIPTOR: Descriptor = Descriptor { __x: *$slice_mut };
+            const STACK_SLOTS: usize = 1;
+            type RefAnchor = SliceAnchor<$t>;
+
+            unsafe fn from_abi_ref_mut(js: u32, extra: &mut Stack) -> SliceAnchor<$t> {
+                let ptr = <*mut $t>::from_abi(js, extra);
+                SliceAnchor::new(ptr, extra.pop() as usize)
             }
         }
     )*)
@@ -175,6 +478,19 @@ pub struct SliceAnchor<T> {
     len: usize,
 }
 
+impl<T> SliceAnchor<T> {
+    fn new(ptr: *mut T, len: usize) -> SliceAnchor<T> {
+        // An empty array from JS comes from a zero-byte allocation, whose
+        // pointer isn't necessarily aligned for `T`.
+        let ptr = if len == 0 {
+            mem::align_of::<T>() as *mut T
+        } else {
+            ptr
+        };
+        SliceAnchor { ptr, len }
+    }
+}
+
 impl<T> Deref for SliceAnchor<T> {
     type Target = [T];
     fn deref(&self) -> &[T] {
@@ -182,33 +498,284 @@ impl<T> Deref for SliceAnchor<T> {
     }
 }
 
+impl<T> DerefMut for SliceAnchor<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
 vectors! {
-    u8 => (b"   6", b"   7")
-    i8 => (b"   8", b"   9")
-    u16 => (b"  10", b"  11")
-    i16 => (b"  12", b"  13")
-    u32 => (b"  14", b"  15")
-    i32 => (b"  16", b"  17")
-    f32 => (b"  18", b"  19")
-    f64 => (b"  20", b"  21")
+    u8 => (b"       6", b"       7", b"      30", b"      50")
+    i8 => (b"       8", b"       9", b"      31", b"      51")
+    u16 => (b"      10", b"      11", b"      32", b"      52")
+    i16 => (b"      12", b"      13", b"      33", b"      53")
+    u32 => (b"      14", b"      15", b"      34", b"      54")
+    i32 => (b"      16", b"      17", b"      35", b"      55")
+    f32 => (b"      18", b"      19", b"      36", b"      56")
+    f64 => (b"      20", b"      21", b"      37", b"      57")
 }
 
-impl<T> WasmBoundary for Vec<T> where Box<[T]>: WasmBoundary {
-    type Abi = <Box<[T]> as WasmBoundary>::Abi;
-    const DESCRIPTOR: Descriptor = <Box<[T]> as WasmBoundary>::DESCRIPTOR;
+// Strings and structs are described as a vector of their own descriptor, which
+// is what both their vectors and their boxed slices are. Vectors of structs
+// can only be passed to JS, see below.
+impl WasmBoundary for Vec<String> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = <Box<[String]> as WasmBoundary>::DESCRIPTOR;
+    const STACK_SLOTS: usize = 1;
 
-    fn into_abi(self, extra: &mut Stack) -> Self::Abi {
+    fn into_abi(self, extra: &mut Stack) -> u32 {
         self.into_boxed_slice().into_abi(extra)
     }
 
-    unsafe fn from_abi(js: Self::Abi, extra: &mut Stack) -> Self {
-        <Box<[T]>>::from_abi(js, extra).into()
+    unsafe fn from_abi(js: u32, extra: &mut Stack) -> Vec<String> {
+        <Box<[String]>>::from_abi(js, extra).into()
+    }
+}
+
+impl<T: WasmStruct> ToWasmBoundary for Vec<T> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = <Box<[T]> as ToWasmBoundary>::DESCRIPTOR;
+    const STACK_SLOTS: usize = 1;
+
+    fn to_abi(self, extra: &mut Stack) -> u32 {
+        self.into_boxed_slice().to_abi(extra)
+    }
+}
+
+/// The numbers which fixed-size arrays can be passed between Rust and JS of.
+#[doc(hidden)]
+pub trait ArrayElement: Copy + Default {}
+
+impl ArrayElement for u8 {}
+impl ArrayElement for i8 {}
+impl ArrayElement for u16 {}
+impl ArrayElement for i16 {}
+impl ArrayElement for u32 {}
+impl ArrayElement for i32 {}
+impl ArrayElement for f32 {}
+impl ArrayElement for f64 {}
+
+// Fixed-size arrays are passed exactly like a vector of their elements, but
+// with their length in the descriptor so the JS shim can check it before
+// passing anything to Rust. It's still checked here for the values imports
+// return.
+macro_rules! arrays {
+    ($($n:expr)*) => ($(
+        impl<T: ArrayElement> WasmBoundary for [T; $n] where Vec<T>: WasmBoundary {
+            type Abi = <Vec<T> as WasmBoundary>::Abi;
+            const DESCRIPTOR: Descriptor =
+                array_descriptor!(<Vec<T> as WasmBoundary>::DESCRIPTOR, $n);
+            const STACK_SLOTS: usize = <Vec<T> as WasmBoundary>::STACK_SLOTS;
+
+            fn into_abi(self, extra: &mut Stack) -> Self::Abi {
+                self[..].to_vec().into_abi(extra)
+            }
+
+            unsafe fn from_abi(js: Self::Abi, extra: &mut Stack) -> [T; $n] {
+                let v = <Vec<T>>::from_abi(js, extra);
+                if v.len() != $n {
+                    let msg = format!("expected an array of length {}, found {}",
+                                      $n, v.len());
+                    drop(v);
+                    super::throw(&msg);
+                }
+                let mut ret = [T::default(); $n];
+                ret.copy_from_slice(&v);
+                ret
+            }
+        }
+    )*)
+}
+
+arrays! {
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+}
+
+// Strings are passed as a vector of interleaved pointers and lengths, one pair
+// per string, and each string's memory is owned by whoever receives it.
+impl WasmBoundary for Box<[String]> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = vector_descriptor!(DESCRIPTOR_STRING);
+    const STACK_SLOTS: usize = 1;
+
+    fn into_abi(self, extra: &mut Stack) -> u32 {
+        let mut words = Vec::with_capacity(self.len() * 2);
+        for s in self.into_vec() {
+            let s = s.into_boxed_str();
+            words.push(s.as_ptr() as u32);
+            words.push(s.len() as u32);
+            mem::forget(s);
+        }
+        words.into_boxed_slice().into_abi(extra)
+    }
+
+    unsafe fn from_abi(js: u32, extra: &mut Stack) -> Box<[String]> {
+        let words = <Box<[u32]>>::from_abi(js, extra);
+        words.chunks(2)
+            .map(|w| {
+                let len = w[1] as usize;
+                String::from_raw_parts(w[0] as *mut u8, len, len)
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+}
+
+/// What an exported function returning `impl Iterator` hands to JS, which
+/// consumes it through the iterator protocol.
+///
+/// JS advances it with `__wbindgen_rust_iterator_next`, converting each item
+/// into a `JsValue`, and drops it with `__wbindgen_rust_iterator_drop` once
+/// it's exhausted or JS stops iterating early.
+#[doc(hidden)]
+pub struct RustIterator {
+    iter: RefCell<Box<Iterator<Item = JsValue>>>,
+}
+
+impl<I> From<I> for RustIterator
+    where I: Iterator + 'static,
+          I::Item: Into<JsValue>,
+{
+    fn from(iter: I) -> RustIterator {
+        RustIterator {
+            iter: RefCell::new(Box::new(iter.map(Into::into))),
+        }
+    }
+}
+
+impl RustIterator {
+    /// Advances the iterator at `ptr`, returning the heap index of the next
+    /// item and setting the first word of `frame` if there is one.
+    #[doc(hidden)]
+    pub unsafe fn __next(ptr: u32, frame: *mut u32) -> u32 {
+        let me = &*(ptr as *const RustIterator);
+        let mut iter = match me.iter.try_borrow_mut() {
+            Ok(iter) => iter,
+            Err(_) => ::throw("iterator advanced while it was already being advanced"),
+        };
+        match iter.next() {
+            Some(value) => {
+                *frame = 1;
+                let idx = value.idx;
+                mem::forget(value);
+                idx
+            }
+            None => {
+                *frame = 0;
+                0
+            }
+        }
+    }
+
+    #[doc(hidden)]
+    pub unsafe fn __drop(ptr: u32) {
+        let me = Box::from_raw(ptr as *mut RustIterator);
+        if me.iter.try_borrow_mut().is_err() {
+            mem::forget(me);
+            ::throw("iterator dropped while it was being advanced");
+        }
+    }
+}
+
+// JS has no way of handing an iterator back, so it can only be returned
+impl ToWasmBoundary for RustIterator {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_ITERATOR;
+
+    fn to_abi(self, _extra: &mut Stack) -> u32 {
+        Box::into_raw(Box::new(self)) as u32
+    }
+}
+
+/// Structs exported with `#[wasm_bindgen]`, which are passed to JS as the
+/// pointer to their own heap allocation.
+#[doc(hidden)]
+pub trait WasmStruct: WasmBoundary<Abi = u32> {}
+
+// Vectors of structs are passed as a vector of pointers, each of which is
+// wrapped in its own JS object that's responsible for freeing it. They can only
+// be passed to JS, as nothing would check the objects JS passes back are
+// wrappers of the right struct.
+impl<T: WasmStruct> ToWasmBoundary for Box<[T]> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = vector_descriptor!(T::DESCRIPTOR);
+    const STACK_SLOTS: usize = 1;
+
+    fn to_abi(self, extra: &mut Stack) -> u32 {
+        let ptrs = self.into_vec()
+            .into_iter()
+            .map(|value| unsafe { value.into_abi(&mut FrameStack::empty()) })
+            .collect::<Vec<u32>>();
+        ptrs.into_boxed_slice().into_abi(extra)
+    }
+}
+
+// Shared structs are the same class in JS, with every handle given out holding
+// a strong reference of its own. Owned handles passed back are moved into a
+// new allocation.
+impl<T: WasmStruct + 'static> WasmBoundary for Rc<T> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = T::DESCRIPTOR;
+
+    fn into_abi(self, _extra: &mut Stack) -> u32 {
+        Rc::into_raw(self) as u32 | ::__rt::HANDLE_RC
+    }
+
+    unsafe fn from_abi(js: u32, _extra: &mut Stack) -> Rc<T> {
+        ::__rt::take_rc(js)
+    }
+}
+
+impl<T: WasmStruct + 'static> WasmBoundary for Arc<T> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = T::DESCRIPTOR;
+
+    fn into_abi(self, _extra: &mut Stack) -> u32 {
+        Arc::into_raw(self) as u32 | ::__rt::HANDLE_ARC
+    }
+
+    unsafe fn from_abi(js: u32, _extra: &mut Stack) -> Arc<T> {
+        ::__rt::take_arc(js)
+    }
+}
+
+// Borrowed strings are passed the same way, but JS frees both the strings and
+// the array of words once the call returns.
+impl<'a> FromRefWasmBoundary for [&'a str] {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = vector_descriptor!(DESCRIPTOR_BORROWED_STR);
+    type RefAnchor = StrSliceAnchor<'a>;
+    const STACK_SLOTS: usize = 1;
+
+    unsafe fn from_abi_ref(js: u32, extra: &mut Stack) -> StrSliceAnchor<'a> {
+        let words = <[u32]>::from_abi_ref(js, extra);
+        let strs = words.chunks(2)
+            .map(|w| {
+                let bytes = slice::from_raw_parts(w[0] as *const u8, w[1] as usize);
+                str::from_utf8_unchecked(bytes)
+            })
+            .collect();
+        StrSliceAnchor { strs }
+    }
+}
+
+pub struct StrSliceAnchor<'a> {
+    strs: Vec<&'a str>,
+}
+
+impl<'a> Deref for StrSliceAnchor<'a> {
+    type Target = [&'a str];
+
+    fn deref(&self) -> &[&'a str] {
+        &self.strs
     }
 }
 
 impl WasmBoundary for String {
     type Abi = u32;
     const DESCRIPTOR: Descriptor = DESCRIPTOR_STRING;
+    const STACK_SLOTS: usize = 1;
 
     fn into_abi(self, extra: &mut Stack) -> u32 {
         self.into_bytes().into_abi(extra)
@@ -219,9 +786,86 @@ impl WasmBoundary for String {
     }
 }
 
+// An `Option<T>` is passed as `T`'s ABI value (all zeros for `None`) and a
+// discriminant pushed onto the stack ahead of anything `T` pushes. `None` still
+// pushes placeholders for everything `T` would have pushed so the stack layout
+// of every other argument stays the same.
+impl<T: WasmBoundary> WasmBoundary for Option<T> {
+    type Abi = T::Abi;
+    const DESCRIPTOR: Descriptor = option_descriptor!(T::DESCRIPTOR);
+    const STACK_SLOTS: usize = T::STACK_SLOTS + 1;
+
+    fn into_abi(self, extra: &mut Stack) -> T::Abi {
+        match self {
+            Some(val) => {
+                extra.push(1);
+                val.into_abi(extra)
+            }
+            None => {
+                for _ in 0..Self::STACK_SLOTS {
+                    extra.push(0);
+                }
+                unsafe { mem::zeroed() }
+            }
+        }
+    }
+
+    unsafe fn from_abi(js: T::Abi, extra: &mut Stack) -> Option<T> {
+        if extra.pop() == 0 {
+            for _ in 0..T::STACK_SLOTS {
+                extra.pop();
+            }
+            None
+        } else {
+            Some(T::from_abi(js, extra))
+        }
+    }
+}
+
+// `&str` isn't `WasmBoundary` on its own, and neither is an optional one as
+// nothing could bound the lifetime of a string received that way. Returned
+// ones are passed like `Option<String>` with a borrowed descriptor, and are
+// copied as JS frees what it's given. Exports take them through an
+// `OptionStrAnchor` instead, see below.
+impl<'a> ToWasmBoundary for Option<&'a str> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = OptionStrAnchor::DESCRIPTOR;
+    const STACK_SLOTS: usize = OptionStrAnchor::STACK_SLOTS;
+
+    fn to_abi(self, extra: &mut Stack) -> u32 {
+        self.map(String::from).into_abi(extra)
+    }
+}
+
+/// An `Option<&str>` argument of an export, which borrows the string JS
+/// passed in for the length of the call just like a `StrAnchor` does. JS frees
+/// the string once the call returns.
+#[doc(hidden)]
+pub struct OptionStrAnchor {
+    inner: Option<StrAnchor>,
+}
+
+impl OptionStrAnchor {
+    pub const DESCRIPTOR: Descriptor = option_descriptor!(DESCRIPTOR_BORROWED_STR);
+    pub const STACK_SLOTS: usize = 2;
+
+    pub unsafe fn from_abi(js: u32, extra: &mut Stack) -> OptionStrAnchor {
+        if extra.pop() == 0 {
+            extra.pop();
+            return OptionStrAnchor { inner: None }
+        }
+        OptionStrAnchor { inner: Some(<str>::from_abi_ref(js, extra)) }
+    }
+
+    pub fn get(&self) -> Option<&str> {
+        self.inner.as_ref().map(|s| &**s)
+    }
+}
+
 impl ToRefWasmBoundary for str {
     type Abi = <[u8] as ToRefWasmBoundary>::Abi;
     const DESCRIPTOR: Descriptor = DESCRIPTOR_BORROWED_STR;
+    const STACK_SLOTS: usize = 1;
 
     fn to_abi_ref(&self, extra: &mut Stack) -> Self::Abi {
         self.as_bytes().to_abi_ref(extra)
@@ -232,6 +876,7 @@ impl FromRefWasmBoundary for str {
     type Abi = <[u8] as ToRefWasmBoundary>::Abi;
     const DESCRIPTOR: Descriptor = DESCRIPTOR_BORROWED_STR;
     type RefAnchor = StrAnchor;
+    const STACK_SLOTS: usize = 1;
 
     unsafe fn from_abi_ref(js: Self::Abi, extra: &mut Stack) -> Self::RefAnchor {
         StrAnchor { inner: <[u8]>::from_abi_ref(js, extra) }
@@ -283,9 +928,52 @@ impl FromRefWasmBoundary for JsValue {
     }
 }
 
+// Tuples can be returned to JS, where they're arrays. There's no conversion
+// back out of an arbitrary JS value, so they can't be received from JS.
+macro_rules! tuples {
+    ($(($($t:ident)*))*) => ($(
+        impl<$($t: Into<JsValue>),*> ToWasmBoundary for ($($t,)*) {
+            type Abi = u32;
+            const DESCRIPTOR: Descriptor = DESCRIPTOR_JS_OWNED;
+
+            fn to_abi(self, extra: &mut Stack) -> u32 {
+                JsValue::from(self).into_abi(extra)
+            }
+        }
+    )*)
+}
+
+tuples! {
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+}
+
+// Likewise maps can be returned as JS objects but not received.
+impl<V: Into<JsValue>, S: BuildHasher> ToWasmBoundary for HashMap<String, V, S> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_JS_OWNED;
+
+    fn to_abi(self, extra: &mut Stack) -> u32 {
+        JsValue::from(self).into_abi(extra)
+    }
+}
+
+impl<V: Into<JsValue>> ToWasmBoundary for BTreeMap<String, V> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_JS_OWNED;
+
+    fn to_abi(self, extra: &mut Stack) -> u32 {
+        JsValue::from(self).into_abi(extra)
+    }
+}
+
 impl WasmBoundary for Box<[JsValue]> {
     type Abi = u32;
-    const DESCRIPTOR: Descriptor = Descriptor { __x: *b"   0" };
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_BOXED_SLICE_JSVALUE;
+    const STACK_SLOTS: usize = 1;
 
     fn into_abi(self, extra: &mut Stack) -> u32 {
         let ptr = self.as_ptr();
@@ -302,38 +990,95 @@ impl WasmBoundary for Box<[JsValue]> {
     }
 }
 
-pub struct GlobalStack { next: usize }
+impl WasmBoundary for Vec<JsValue> {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = Descriptor { __x: *b"       0" };
+    const STACK_SLOTS: usize = 1;
+
+    fn into_abi(self, extra: &mut Stack) -> u32 {
+        self.into_boxed_slice().into_abi(extra)
+    }
+
+    unsafe fn from_abi(js: u32, extra: &mut Stack) -> Vec<JsValue> {
+        <Box<[JsValue]>>::from_abi(js, extra).into()
+    }
+}
+
+impl ToRefWasmBoundary for [JsValue] {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = Descriptor { __x: *b"      28" };
+    const STACK_SLOTS: usize = 1;
+
+    fn to_abi_ref(&self, extra: &mut Stack) -> u32 {
+        let ptr = self.as_ptr();
+        let len = self.len();
+        extra.push(len as u32);
+        ptr.into_abi(extra)
+    }
+}
+
+/// The extra words passed along with the arguments and return value of a
+/// single call across the boundary, like the lengths of strings.
+///
+/// Every call gets a frame of its own so one made while another is still in
+/// progress, such as a JS callback calling back into Rust, can't clobber
+/// values which haven't been read yet. Imports pass a frame on the Rust stack
+/// as their last argument, and exports are passed one by their JS shim.
+pub struct FrameStack {
+    frame: *mut u32,
+    next: usize,
+}
+
+/// The number of words in a frame.
+pub const FRAME_LEN: usize = 16;
+
+/// A frame for the extra words of a call to an import.
+pub type Frame = [u32; FRAME_LEN];
+
+impl FrameStack {
+    pub unsafe fn new(frame: *mut u32) -> FrameStack {
+        FrameStack { frame, next: 0 }
+    }
+
+    /// A stack for conversions which never need any extra words, like
+    /// those of pointers to exported structs.
+    pub unsafe fn empty() -> FrameStack {
+        FrameStack::new(ptr::null_mut())
+    }
 
-const GLOBAL_STACK_CAP: usize = 16;
-static mut GLOBAL_STACK: [u32; GLOBAL_STACK_CAP] = [0; GLOBAL_STACK_CAP];
+    /// Stores the `Err` of an exported function returning a `Result`. Slot 0
+    /// is set to 1, slot 1 holds `err` itself and any extra slots it needs
+    /// follow, whereas an `Ok` pushes a 0 before the value's own slots.
+    #[doc(hidden)]
+    pub unsafe fn store_err<E>(frame: *mut u32, err: E)
+        where E: WasmBoundary<Abi = u32>,
+    {
+        let mut extra = FrameStack { frame, next: 2 };
+        let abi = err.into_abi(&mut extra);
+        let mut start = FrameStack::new(frame);
+        start.push(1);
+        start.push(abi);
+    }
 
-impl GlobalStack {
-    pub unsafe fn new() -> GlobalStack {
-        GlobalStack { next: 0 }
+    fn slot(&mut self) -> *mut u32 {
+        assert!(!self.frame.is_null(), "no frame was passed for extra values");
+        assert!(self.next < FRAME_LEN);
+        let slot = unsafe { self.frame.offset(self.next as isize) };
+        self.next += 1;
+        slot
     }
 }
 
-impl Stack for GlobalStack {
+impl Stack for FrameStack {
     fn push(&mut self, val: u32) {
         unsafe {
-            assert!(self.next < GLOBAL_STACK_CAP);
-            GLOBAL_STACK[self.next] = val;
-            self.next += 1;
+            *self.slot() = val;
         }
     }
 
     fn pop(&mut self) -> u32 {
         unsafe {
-            assert!(self.next < GLOBAL_STACK_CAP);
-            let ret = GLOBAL_STACK[self.next];
-            self.next += 1;
-            ret
+            *self.slot()
         }
     }
 }
-
-#[doc(hidden)]
-#[no_mangle]
-pub unsafe extern fn __wbindgen_global_argument_ptr() -> *mut u32 {
-    GLOBAL_STACK.as_mut_ptr()
-}
@@ -0,0 +1,107 @@
+//! Support for JS `Date`s
+//!
+//! A `JsDate` is a handle to a JS `Date` object. Exports taking one check that
+//! they're actually given a `Date`, and both exports and imports can return
+//! one:
+//!
+//! ```ignore
+//! #[wasm_bindgen]
+//! pub fn tomorrow(today: JsDate) -> JsDate {
+//!     let ms = today.to_millis().unwrap_or(0.0);
+//!     JsDate::from_millis(ms + 24.0 * 60.0 * 60.0 * 1000.0)
+//! }
+//! ```
+//!
+//! Like in JS a date may be invalid, in which case its time is `NaN`. Rather
+//! than handing out that `NaN` the conversions back to Rust return `None`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use JsValue;
+use convert::{Descriptor, WasmBoundary, Stack, DESCRIPTOR_DATE};
+
+/// A JS `Date`, which is a number of milliseconds since the Unix epoch.
+pub struct JsDate {
+    obj: JsValue,
+}
+
+impl JsDate {
+    /// Creates a `Date` for the time `ms` milliseconds after the Unix epoch,
+    /// like `new Date(ms)` does.
+    ///
+    /// The date is invalid if `ms` is `NaN`, infinite or outside the range JS
+    /// supports, which is 10^8 days either side of the epoch.
+    pub fn from_millis(ms: f64) -> JsDate {
+        JsDate { obj: JsValue { idx: unsafe { __wbindgen_date_new(ms) } } }
+    }
+
+    /// Returns the number of milliseconds since the Unix epoch, like
+    /// `getTime` does, or `None` if this is an invalid date.
+    pub fn to_millis(&self) -> Option<f64> {
+        let ms = unsafe { __wbindgen_date_get_time(self.obj.idx) };
+        if ms.is_nan() {
+            None
+        } else {
+            Some(ms)
+        }
+    }
+
+    /// Returns the time of this date, or `None` if this is an invalid date.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let ms = self.to_millis()?;
+        let since = millis_to_duration(ms.abs());
+        if ms < 0.0 {
+            UNIX_EPOCH.checked_sub(since)
+        } else {
+            UNIX_EPOCH.checked_add(since)
+        }
+    }
+}
+
+impl From<SystemTime> for JsDate {
+    /// Times outside the range JS supports give an invalid date, and times
+    /// are rounded down to whole milliseconds.
+    fn from(time: SystemTime) -> JsDate {
+        let ms = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => duration_to_millis(d),
+            Err(e) => -duration_to_millis(e.duration()),
+        };
+        JsDate::from_millis(ms)
+    }
+}
+
+impl From<JsDate> for JsValue {
+    fn from(date: JsDate) -> JsValue {
+        date.obj
+    }
+}
+
+impl WasmBoundary for JsDate {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_DATE;
+
+    fn into_abi(self, extra: &mut Stack) -> u32 {
+        self.obj.into_abi(extra)
+    }
+
+    unsafe fn from_abi(js: u32, extra: &mut Stack) -> JsDate {
+        JsDate { obj: JsValue::from_abi(js, extra) }
+    }
+}
+
+fn duration_to_millis(d: Duration) -> f64 {
+    (d.as_secs() as f64) * 1000.0 + (d.subsec_nanos() / 1_000_000) as f64
+}
+
+fn millis_to_duration(ms: f64) -> Duration {
+    // Valid dates are whole milliseconds well within what a `u64` of seconds
+    // can hold
+    let ms = ms as u64;
+    Duration::new(ms / 1000, (ms % 1000) as u32 * 1_000_000)
+}
+
+#[wasm_import_module = "__wbindgen_placeholder__"]
+extern {
+    fn __wbindgen_date_new(ms: f64) -> u32;
+    fn __wbindgen_date_get_time(idx: u32) -> f64;
+}
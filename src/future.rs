@@ -0,0 +1,294 @@
+//! Support for awaiting JS promises from Rust
+//!
+//! A `JsFuture` is a Rust `Future` which resolves once the JS promise it was
+//! created from settles. Imported functions can return one directly, for
+//! example:
+//!
+//! ```ignore
+//! #[wasm_bindgen(module = "./api")]
+//! extern {
+//!     fn fetch_name(id: u32) -> JsFuture;
+//! }
+//!
+//! spawn_local(async {
+//!     match fetch_name(3).await {
+//!         Ok(name) => log(&name),
+//!         Err(e) => log_error(&e),
+//!     }
+//! });
+//! ```
+//!
+//! The future resolves to `Ok` with the promise's value if it's fulfilled and
+//! to `Err` with the reason if it's rejected. Dropping the future before then
+//! detaches it from the promise, which is then free to be garbage collected.
+//!
+//! Going the other way, an exported `async fn` returns a promise to JS which
+//! settles once the Rust future completes.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use JsValue;
+use convert::{Descriptor, WasmBoundary, Stack, DESCRIPTOR_PROMISE};
+
+/// A Rust `Future` backed by a JS promise.
+///
+/// The output is `Ok` with the value the promise is resolved with or `Err`
+/// with the value it's rejected with. Anything which isn't a promise is
+/// treated like one that's already resolved with that value.
+pub struct JsFuture {
+    promise: JsValue,
+    // The object JS keeps around until the promise settles, which points back
+    // at `state` until this future is dropped
+    waiter: JsValue,
+    state: Box<State>,
+}
+
+struct State {
+    result: RefCell<Option<Result<JsValue, JsValue>>>,
+    waker: RefCell<Option<Waker>>,
+    done: Cell<bool>,
+}
+
+impl From<JsValue> for JsFuture {
+    fn from(promise: JsValue) -> JsFuture {
+        let state = Box::new(State {
+            result: RefCell::new(None),
+            waker: RefCell::new(None),
+            done: Cell::new(false),
+        });
+        let idx = unsafe {
+            __wbindgen_promise_then(promise.idx, &*state as *const State as u32)
+        };
+        JsFuture {
+            promise,
+            waiter: JsValue { idx },
+            state,
+        }
+    }
+}
+
+impl Future for JsFuture {
+    type Output = Result<JsValue, JsValue>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if self.state.done.get() {
+            ::throw("`JsFuture` polled after it completed");
+        }
+        if let Some(result) = self.state.result.borrow_mut().take() {
+            self.state.done.set(true);
+            return Poll::Ready(result)
+        }
+        *self.state.waker.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for JsFuture {
+    fn drop(&mut self) {
+        unsafe {
+            __wbindgen_promise_forget(self.waiter.idx);
+        }
+    }
+}
+
+impl WasmBoundary for JsFuture {
+    type Abi = u32;
+    const DESCRIPTOR: Descriptor = DESCRIPTOR_PROMISE;
+
+    fn into_abi(mut self, extra: &mut Stack) -> u32 {
+        let promise = mem::replace(&mut self.promise, JsValue::undefined());
+        promise.into_abi(extra)
+    }
+
+    unsafe fn from_abi(js: u32, extra: &mut Stack) -> JsFuture {
+        JsFuture::from(JsValue::from_abi(js, extra))
+    }
+}
+
+/// Invoked from JS through `__wbindgen_future_settle` once the promise of the
+/// future whose state is at `ptr` settles, with the owned heap index of the
+/// value it settled with.
+#[doc(hidden)]
+pub unsafe fn __settle(ptr: u32, ok: bool, idx: u32) {
+    let state = &*(ptr as *const State);
+    let value = JsValue { idx };
+    *state.result.borrow_mut() = Some(if ok { Ok(value) } else { Err(value) });
+    let waker = state.waker.borrow_mut().take();
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Runs `future` to completion on the current thread.
+///
+/// The future is polled right away and then again each time it's woken, such
+/// as when a `JsFuture` it's waiting on settles.
+pub fn spawn_local<F>(future: F)
+    where F: Future<Output = ()> + 'static,
+{
+    Task::run(&Task::new(future));
+}
+
+/// Runs the future returned by an exported `async fn`, returning the heap
+/// index of the promise which `settle` decides the outcome of.
+///
+/// The task only holds itself alive through the wakers it leaves behind, so
+/// JS is given a weak reference to it. Should the promise be garbage
+/// collected before it settles JS uses that to drop the future.
+#[doc(hidden)]
+pub fn __export<F, G>(future: F, settle: G) -> u32
+    where F: Future + 'static,
+          G: FnOnce(F::Output) -> Result<JsValue, JsValue> + 'static,
+{
+    let mut resolvers = 0;
+    let promise = unsafe { __wbindgen_promise_new(&mut resolvers) };
+    let resolvers = JsValue { idx: resolvers };
+    let idx = resolvers.idx;
+    let task = Task::new(Settle {
+        future,
+        settle: Some(settle),
+        resolvers,
+    });
+    unsafe {
+        __wbindgen_promise_on_drop(idx, Weak::into_raw(Rc::downgrade(&task)) as u32);
+    }
+    Task::run(&task);
+    promise
+}
+
+/// Invoked from JS through `__wbindgen_future_drop` with the weak reference
+/// to an exported future's task once its promise either settles or is
+/// garbage collected.
+#[doc(hidden)]
+pub unsafe fn __drop(ptr: u32) {
+    let task = match Weak::from_raw(ptr as *const Task).upgrade() {
+        Some(task) => task,
+        None => return,
+    };
+    // A task that's running is the one settling its own promise, and it's
+    // finished with its future anyway
+    let future = match task.future.try_borrow_mut() {
+        Ok(mut future) => future.take(),
+        Err(_) => None,
+    };
+    drop(future);
+}
+
+struct Settle<F, G> {
+    future: F,
+    settle: Option<G>,
+    resolvers: JsValue,
+}
+
+impl<F, G> Future for Settle<F, G>
+    where F: Future,
+          G: FnOnce(F::Output) -> Result<JsValue, JsValue>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        // `future` is never moved out of `self`, so it stays pinned
+        let me = unsafe { self.get_unchecked_mut() };
+        let output = match unsafe { Pin::new_unchecked(&mut me.future) }.poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        let settle = me.settle.take().expect("exported future polled after it completed");
+        let (ok, value) = match settle(output) {
+            Ok(value) => (1, value),
+            Err(value) => (0, value),
+        };
+        unsafe {
+            __wbindgen_promise_settle(me.resolvers.idx, ok, value.idx);
+        }
+        mem::forget(value);
+        Poll::Ready(())
+    }
+}
+
+struct Task {
+    future: RefCell<Option<Pin<Box<Future<Output = ()>>>>>,
+    running: Cell<bool>,
+    woken: Cell<bool>,
+}
+
+impl Task {
+    fn new<F>(future: F) -> Rc<Task>
+        where F: Future<Output = ()> + 'static,
+    {
+        Rc::new(Task {
+            future: RefCell::new(Some(Box::pin(future))),
+            running: Cell::new(false),
+            woken: Cell::new(false),
+        })
+    }
+
+    fn run(task: &Rc<Task>) {
+        // A task woken while it's being polled is polled again once it's done
+        // rather than recursively
+        task.woken.set(true);
+        if task.running.replace(true) {
+            return
+        }
+        while task.woken.replace(false) {
+            let waker = unsafe { Waker::from_raw(raw_waker(task.clone())) };
+            let mut cx = Context::from_waker(&waker);
+            let mut future = task.future.borrow_mut();
+            let ready = match *future {
+                Some(ref mut f) => f.as_mut().poll(&mut cx).is_ready(),
+                None => true,
+            };
+            if ready {
+                *future = None;
+            }
+        }
+        task.running.set(false);
+    }
+}
+
+static TASK_WAKER: RawWakerVTable = RawWakerVTable::new(
+    clone_task,
+    wake_task,
+    wake_task_by_ref,
+    drop_task,
+);
+
+unsafe fn raw_waker(task: Rc<Task>) -> RawWaker {
+    RawWaker::new(Rc::into_raw(task) as *const (), &TASK_WAKER)
+}
+
+unsafe fn clone_task(ptr: *const ()) -> RawWaker {
+    let task = Rc::from_raw(ptr as *const Task);
+    let clone = task.clone();
+    mem::forget(task);
+    raw_waker(clone)
+}
+
+unsafe fn wake_task(ptr: *const ()) {
+    let task = Rc::from_raw(ptr as *const Task);
+    Task::run(&task);
+}
+
+unsafe fn wake_task_by_ref(ptr: *const ()) {
+    let task = Rc::from_raw(ptr as *const Task);
+    Task::run(&task);
+    mem::forget(task);
+}
+
+unsafe fn drop_task(ptr: *const ()) {
+    drop(Rc::from_raw(ptr as *const Task));
+}
+
+#[wasm_import_module = "__wbindgen_placeholder__"]
+extern {
+    fn __wbindgen_promise_then(promise: u32, state: u32) -> u32;
+    fn __wbindgen_promise_forget(waiter: u32);
+    fn __wbindgen_promise_new(resolvers: *mut u32) -> u32;
+    fn __wbindgen_promise_on_drop(resolvers: u32, task: u32);
+    fn __wbindgen_promise_settle(resolvers: u32, ok: u32, value: u32);
+}
@@ -10,6 +10,9 @@
 extern crate wasm_bindgen_macro;
 
 use std::cell::UnsafeCell;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::mem;
 use std::ops::Deref;
 use std::ptr;
 
@@ -23,9 +26,16 @@ use convert::WasmBoundary;
 pub mod prelude {
     pub use wasm_bindgen_macro::wasm_bindgen;
     pub use JsValue;
+    pub use closure::Closure;
+    pub use future::{spawn_local, JsFuture};
+    pub use date::JsDate;
+    pub use JsError;
 }
 
 pub mod convert;
+pub mod closure;
+pub mod future;
+pub mod date;
 
 /// Representation of an object owned by JS.
 ///
@@ -181,6 +191,55 @@ impl JsValue {
             __wbindgen_is_symbol(self.idx) == 1
         }
     }
+
+    /// Tests whether the type of this JS value is `string`
+    pub fn is_string(&self) -> bool {
+        unsafe {
+            __wbindgen_is_string(self.idx) == 1
+        }
+    }
+
+    /// Tests whether the type of this JS value is `number`, which includes
+    /// `NaN`
+    pub fn is_number(&self) -> bool {
+        unsafe {
+            __wbindgen_is_number(self.idx) == 1
+        }
+    }
+
+    /// Tests whether the type of this JS value is `boolean`
+    pub fn is_boolean(&self) -> bool {
+        unsafe {
+            __wbindgen_is_boolean(self.idx) == 1
+        }
+    }
+
+    /// Tests whether the type of this JS value is `function`, which includes
+    /// classes
+    pub fn is_function(&self) -> bool {
+        unsafe {
+            __wbindgen_is_function(self.idx) == 1
+        }
+    }
+
+    /// Tests whether this JS value is an object, including arrays but not
+    /// functions.
+    ///
+    /// Unlike `typeof` in JS this is `false` for `null`.
+    pub fn is_object(&self) -> bool {
+        unsafe {
+            __wbindgen_is_object(self.idx) == 1
+        }
+    }
+
+    /// Tests whether this JS value is an array, like `Array.isArray`.
+    ///
+    /// Typed arrays like `Uint8Array` aren't arrays.
+    pub fn is_array(&self) -> bool {
+        unsafe {
+            __wbindgen_is_array(self.idx) == 1
+        }
+    }
 }
 
 impl<'a> From<&'a str> for JsValue {
@@ -213,6 +272,71 @@ macro_rules! numbers {
 
 numbers! { i8 u8 i16 u16 i32 u32 f32 f64 }
 
+impl From<String> for JsValue {
+    fn from(s: String) -> JsValue {
+        JsValue::from_str(&s)
+    }
+}
+
+// Tuples become JS arrays with one element per field, so nested tuples are
+// nested arrays.
+macro_rules! tuples {
+    ($(($($var:ident: $t:ident)*))*) => ($(
+        impl<$($t: Into<JsValue>),*> From<($($t,)*)> for JsValue {
+            fn from(($($var,)*): ($($t,)*)) -> JsValue {
+                unsafe {
+                    let array = JsValue { idx: __wbindgen_array_new() };
+                    $(
+                        let $var: JsValue = $var.into();
+                        __wbindgen_array_push(array.idx, $var.idx);
+                        mem::forget($var);
+                    )*
+                    array
+                }
+            }
+        }
+    )*)
+}
+
+tuples! {
+    (a: A b: B)
+    (a: A b: B c: C)
+    (a: A b: B c: C d: D)
+    (a: A b: B c: C d: D e: E)
+    (a: A b: B c: C d: D e: E f: F)
+}
+
+// Maps with string keys become plain JS objects with a property per entry.
+// The properties of a `HashMap` are in no particular order, whereas those of
+// a `BTreeMap` are in the map's sorted order, though like any JS object keys
+// that look like array indices always come first.
+impl<V: Into<JsValue>, S: BuildHasher> From<HashMap<String, V, S>> for JsValue {
+    fn from(map: HashMap<String, V, S>) -> JsValue {
+        object_from_entries(map)
+    }
+}
+
+impl<V: Into<JsValue>> From<BTreeMap<String, V>> for JsValue {
+    fn from(map: BTreeMap<String, V>) -> JsValue {
+        object_from_entries(map)
+    }
+}
+
+fn object_from_entries<I, V>(entries: I) -> JsValue
+    where I: IntoIterator<Item = (String, V)>,
+          V: Into<JsValue>,
+{
+    unsafe {
+        let object = JsValue { idx: __wbindgen_object_new() };
+        for (key, value) in entries {
+            let value: JsValue = value.into();
+            __wbindgen_object_set(object.idx, key.as_ptr(), key.len(), value.idx);
+            mem::forget(value);
+        }
+        object
+    }
+}
+
 #[wasm_import_module = "__wbindgen_placeholder__"]
 extern {
     fn __wbindgen_object_clone_ref(idx: u32) -> u32;
@@ -228,8 +352,21 @@ extern {
     fn __wbindgen_boolean_get(idx: u32) -> u32;
     fn __wbindgen_symbol_new(ptr: *const u8, len: usize) -> u32;
     fn __wbindgen_is_symbol(idx: u32) -> u32;
+    fn __wbindgen_is_string(idx: u32) -> u32;
+    fn __wbindgen_is_number(idx: u32) -> u32;
+    fn __wbindgen_is_boolean(idx: u32) -> u32;
+    fn __wbindgen_is_function(idx: u32) -> u32;
+    fn __wbindgen_is_object(idx: u32) -> u32;
+    fn __wbindgen_is_array(idx: u32) -> u32;
     fn __wbindgen_string_get(idx: u32, len: *mut usize) -> *mut u8;
     fn __wbindgen_throw(a: *const u8, b: usize) -> !;
+    fn __wbindgen_iterator_next(idx: u32, done: *mut u8) -> u32;
+    fn __wbindgen_iterator_return(idx: u32);
+    fn __wbindgen_error_field(idx: u32, field: u32, len: *mut usize) -> *mut u8;
+    fn __wbindgen_array_new() -> u32;
+    fn __wbindgen_array_push(array: u32, idx: u32);
+    fn __wbindgen_object_new() -> u32;
+    fn __wbindgen_object_set(object: u32, key: *const u8, len: usize, idx: u32);
 }
 
 impl Clone for JsValue {
@@ -249,6 +386,140 @@ impl Drop for JsValue {
     }
 }
 
+/// An exception caught by an import tagged with `#[wasm_bindgen(catch)]`.
+///
+/// Such imports can return `Result<T, JsError>` instead of `Result<T, JsValue>`
+/// to find out more about what was thrown, namely whether it's a JS exception
+/// or a Rust panic which unwound through JS, and its `name` and `message` if
+/// it's an `Error`.
+pub struct JsError {
+    value: JsValue,
+    source: ExceptionSource,
+    name: Option<String>,
+    message: Option<String>,
+}
+
+/// Where an exception caught by an import came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExceptionSource {
+    /// A value thrown by JS code.
+    Js,
+    /// A Rust panic in a wasm function the import called back into, which
+    /// aborts by trapping. The wasm instance may be left in an inconsistent
+    /// state by the panic.
+    Panic,
+}
+
+impl JsError {
+    /// Returns the value that was thrown.
+    pub fn value(&self) -> &JsValue {
+        &self.value
+    }
+
+    /// Returns the value that was thrown, consuming this error.
+    pub fn into_value(self) -> JsValue {
+        self.value
+    }
+
+    /// Returns where this exception came from.
+    pub fn source(&self) -> ExceptionSource {
+        self.source
+    }
+
+    /// Returns the thrown value's `name` property if it's a string, like
+    /// `"TypeError"` for a `TypeError`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|s| &**s)
+    }
+
+    /// Returns the thrown value's `message` property if it's a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_ref().map(|s| &**s)
+    }
+
+    fn field(value: &JsValue, field: u32) -> Option<String> {
+        unsafe {
+            let mut len = 0;
+            let ptr = __wbindgen_error_field(value.idx, field, &mut len);
+            if ptr.is_null() {
+                None
+            } else {
+                let data = Vec::from_raw_parts(ptr, len, len);
+                Some(String::from_utf8_unchecked(data))
+            }
+        }
+    }
+}
+
+impl convert::FromCaughtException for JsError {
+    fn from_caught_exception(value: JsValue, source: ExceptionSource) -> JsError {
+        JsError {
+            name: JsError::field(&value, 0),
+            message: JsError::field(&value, 1),
+            value,
+            source,
+        }
+    }
+}
+
+impl From<JsError> for JsValue {
+    fn from(err: JsError) -> JsValue {
+        err.value
+    }
+}
+
+/// A Rust `Iterator` over the values produced by a JS iterator.
+///
+/// Each call to `next` calls the `next()` method of the wrapped JS object,
+/// which must implement the iterator protocol, until it returns a result with
+/// a truthy `done`. Types imported with `#[wasm_bindgen(iterator)]` convert
+/// into this type through `IntoIterator`.
+///
+/// If a `JsIterator` is dropped before reaching the end then the JS iterator's
+/// `return()` method, if it has one, is called to let it clean up, just like
+/// breaking out of a `for...of` loop in JS.
+pub struct JsIterator {
+    obj: JsValue,
+    done: bool,
+}
+
+impl JsIterator {
+    /// Wraps a JS object implementing the iterator protocol.
+    pub fn new(obj: JsValue) -> JsIterator {
+        JsIterator { obj, done: false }
+    }
+}
+
+impl Iterator for JsIterator {
+    type Item = JsValue;
+
+    fn next(&mut self) -> Option<JsValue> {
+        if self.done {
+            return None
+        }
+        unsafe {
+            let mut done = 0;
+            let idx = __wbindgen_iterator_next(self.obj.idx, &mut done);
+            if done == 1 {
+                self.done = true;
+                None
+            } else {
+                Some(JsValue { idx })
+            }
+        }
+    }
+}
+
+impl Drop for JsIterator {
+    fn drop(&mut self) {
+        if !self.done {
+            unsafe {
+                __wbindgen_iterator_return(self.obj.idx);
+            }
+        }
+    }
+}
+
 /// Wrapper type for imported statics.
 ///
 /// This type is used whenever a `static` is imported from a JS module, for
@@ -283,11 +554,20 @@ impl<T: WasmBoundary> Deref for JsStatic<T> {
     type Target = T;
     fn deref(&self) -> &T {
         unsafe {
-            (*self.__inner.get()).get_or_insert_with(|| {
-                assert!(T::DESCRIPTOR == JsValue::DESCRIPTOR,
-                        "only JS values can be imported as statics for now");
-                (self.__init)()
-            })
+            if let Some(ref value) = *self.__inner.get() {
+                return value
+            }
+            assert!(T::DESCRIPTOR == JsValue::DESCRIPTOR,
+                    "only JS values can be imported as statics for now");
+            // Initializing calls out to JS, which may call back into Rust and
+            // access this same static, so don't hold on to a borrow of it in
+            // the meantime. If that happens the first value stored wins and
+            // any later one is dropped.
+            let value = (self.__init)();
+            if (*self.__inner.get()).is_none() {
+                *self.__inner.get() = Some(value);
+            }
+            (*self.__inner.get()).as_ref().unwrap()
         }
     }
 }
@@ -310,6 +590,8 @@ pub mod __rt {
     use std::cell::{Cell, UnsafeCell};
     use std::mem;
     use std::ops::{Deref, DerefMut};
+    use std::rc::Rc;
+    use std::sync::Arc;
 
     #[inline]
     pub fn assert_not_null<T>(s: *mut T) {
@@ -318,6 +600,11 @@ pub mod __rt {
         }
     }
 
+    /// Exported struct fields are copied out to JS, so they need to be `Copy`
+    /// unless they're marked `getter_with_clone`.
+    #[inline]
+    pub fn assert_copy<T: Copy>() {}
+
     #[cold]
     #[inline(never)]
     fn throw_null() -> ! {
@@ -443,6 +730,127 @@ pub mod __rt {
                       unsafe aliasing in rust");
     }
 
+    // Exported structs are passed to JS as a pointer to a `WasmRefCell` JS
+    // owns on its own, or if they're shared through an `Rc` or `Arc` as the
+    // pointer to the shared value with one of these tags in its low bits.
+    // Both allocations start with a counter, so those bits are always free.
+    pub const HANDLE_RC: u32 = 1;
+    pub const HANDLE_ARC: u32 = 2;
+    const HANDLE_TAG: u32 = 3;
+
+    /// A borrow of an exported struct through the handle JS has for it.
+    /// Shared values are kept alive by a strong reference of their own for
+    /// as long as they're borrowed, even if JS frees its handle meanwhile.
+    pub enum StructRef<T: 'static> {
+        Owned(Ref<'static, T>),
+        Rc(Rc<T>),
+        Arc(Arc<T>),
+    }
+
+    impl<T> Deref for StructRef<T> {
+        type Target = T;
+
+        #[inline]
+        fn deref(&self) -> &T {
+            match *self {
+                StructRef::Owned(ref r) => r,
+                StructRef::Rc(ref r) => r,
+                StructRef::Arc(ref r) => r,
+            }
+        }
+    }
+
+    pub unsafe fn borrow_struct<T: 'static>(js: u32) -> StructRef<T> {
+        let ptr = js & !HANDLE_TAG;
+        assert_not_null(ptr as *mut T);
+        match js & HANDLE_TAG {
+            HANDLE_RC => {
+                let rc = Rc::from_raw(ptr as *const T);
+                let ret = rc.clone();
+                mem::forget(rc);
+                StructRef::Rc(ret)
+            }
+            HANDLE_ARC => {
+                let arc = Arc::from_raw(ptr as *const T);
+                let ret = arc.clone();
+                mem::forget(arc);
+                StructRef::Arc(ret)
+            }
+            _ => StructRef::Owned((*(ptr as *mut WasmRefCell<T>)).borrow()),
+        }
+    }
+
+    pub unsafe fn borrow_struct_mut<T: 'static>(js: u32) -> RefMut<'static, T> {
+        if js & HANDLE_TAG != 0 {
+            shared_mut_fail();
+        }
+        let js = js as *mut WasmRefCell<T>;
+        assert_not_null(js);
+        (*js).borrow_mut()
+    }
+
+    /// Takes the value out of a handle JS is giving up, which for shared
+    /// values only works if it's the last reference to them.
+    pub unsafe fn take_struct<T: 'static>(js: u32) -> T {
+        let value = match js & HANDLE_TAG {
+            HANDLE_RC => Rc::try_unwrap(take_rc(js)).ok(),
+            HANDLE_ARC => Arc::try_unwrap(take_arc(js)).ok(),
+            _ => return take_cell(js).into_inner(),
+        };
+        match value {
+            Some(value) => value,
+            None => super::throw("cannot take ownership of a value which is still shared"),
+        }
+    }
+
+    pub unsafe fn take_rc<T: 'static>(js: u32) -> Rc<T> {
+        match js & HANDLE_TAG {
+            HANDLE_RC => {
+                let ptr = js & !HANDLE_TAG;
+                assert_not_null(ptr as *mut T);
+                Rc::from_raw(ptr as *const T)
+            }
+            HANDLE_ARC => super::throw("cannot convert a shared `Arc` into an `Rc`"),
+            _ => Rc::new(take_cell(js).into_inner()),
+        }
+    }
+
+    pub unsafe fn take_arc<T: 'static>(js: u32) -> Arc<T> {
+        match js & HANDLE_TAG {
+            HANDLE_ARC => {
+                let ptr = js & !HANDLE_TAG;
+                assert_not_null(ptr as *mut T);
+                Arc::from_raw(ptr as *const T)
+            }
+            HANDLE_RC => super::throw("cannot convert a shared `Rc` into an `Arc`"),
+            _ => Arc::new(take_cell(js).into_inner()),
+        }
+    }
+
+    unsafe fn take_cell<T: 'static>(js: u32) -> Box<WasmRefCell<T>> {
+        let js = js as *mut WasmRefCell<T>;
+        assert_not_null(js);
+        let js = Box::from_raw(js);
+        js.borrow_mut(); // make sure no one's borrowing
+        js
+    }
+
+    /// Frees a handle JS has for a struct, which only drops shared values
+    /// once it was their last reference.
+    pub unsafe fn free_struct<T: 'static>(js: u32) {
+        match js & HANDLE_TAG {
+            HANDLE_RC => drop(take_rc::<T>(js)),
+            HANDLE_ARC => drop(take_arc::<T>(js)),
+            _ => drop(take_cell::<T>(js)),
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn shared_mut_fail() -> ! {
+        super::throw("cannot mutably borrow a value shared through an `Rc` or `Arc`");
+    }
+
     #[no_mangle]
     pub extern fn __wbindgen_malloc(size: usize) -> *mut u8 {
         let mut ret = Vec::new();
@@ -459,5 +867,58 @@ pub mod __rt {
         drop(Vec::<u8>::from_raw_parts(ptr, 0, size));
     }
 
+    /// Allocates a frame for the extra words of a call to an export. JS
+    /// keeps frames around to reuse, so they're never freed.
+    #[no_mangle]
+    pub extern fn __wbindgen_frame_alloc() -> *mut u32 {
+        let frame: Box<::convert::Frame> = Box::new([0; ::convert::FRAME_LEN]);
+        Box::into_raw(frame) as *mut u32
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_closure_call(ptr: u32,
+                                                 a: u32,
+                                                 b: u32,
+                                                 c: u32,
+                                                 d: u32) -> u32 {
+        super::closure::__call(ptr, [a, b, c, d])
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_closure_free(ptr: u32) {
+        super::closure::__free(ptr)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_future_settle(ptr: u32, ok: u32, idx: u32) {
+        super::future::__settle(ptr, ok != 0, idx)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_future_drop(ptr: u32) {
+        super::future::__drop(ptr)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_rust_iterator_next(ptr: u32, frame: *mut u32) -> u32 {
+        ::convert::RustIterator::__next(ptr, frame)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_rust_iterator_drop(ptr: u32) {
+        ::convert::RustIterator::__drop(ptr)
+    }
+
+    pub unsafe fn caught_exception<E>(source: u32, idx: u32) -> E
+        where E: ::convert::FromCaughtException,
+    {
+        let source = if source == 2 {
+            super::ExceptionSource::Panic
+        } else {
+            super::ExceptionSource::Js
+        };
+        E::from_caught_exception(super::JsValue { idx }, source)
+    }
+
     pub fn link_this_library() {}
 }
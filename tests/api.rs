@@ -142,3 +142,54 @@ fn works() {
         .test();
 }
 
+
+#[test]
+fn type_checks() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn types_of(a: &JsValue) -> String {
+                let mut ret = Vec::new();
+                if a.is_string() { ret.push("string") }
+                if a.is_number() { ret.push("number") }
+                if a.is_boolean() { ret.push("boolean") }
+                if a.is_function() { ret.push("function") }
+                if a.is_object() { ret.push("object") }
+                if a.is_array() { ret.push("array") }
+                if a.is_null() { ret.push("null") }
+                if a.is_undefined() { ret.push("undefined") }
+                if a.is_symbol() { ret.push("symbol") }
+                ret.join(" ")
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.types_of("a"), "string");
+                assert.strictEqual(wasm.types_of(""), "string");
+                assert.strictEqual(wasm.types_of(1.5), "number");
+                assert.strictEqual(wasm.types_of(NaN), "number");
+                assert.strictEqual(wasm.types_of(false), "boolean");
+                assert.strictEqual(wasm.types_of(() => 1), "function");
+                assert.strictEqual(wasm.types_of(class {}), "function");
+                assert.strictEqual(wasm.types_of({}), "object");
+                assert.strictEqual(wasm.types_of(new Date()), "object");
+                assert.strictEqual(wasm.types_of(new Uint8Array(2)), "object");
+                assert.strictEqual(wasm.types_of([]), "object array");
+                assert.strictEqual(wasm.types_of([1, "a"]), "object array");
+                assert.strictEqual(wasm.types_of(null), "null");
+                assert.strictEqual(wasm.types_of(undefined), "undefined");
+                assert.strictEqual(wasm.types_of((Symbol as any)()), "symbol");
+                assert.strictEqual(wasm.types_of(new String("a")), "object");
+            }
+        "#)
+        .test();
+}
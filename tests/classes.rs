@@ -361,3 +361,1061 @@ fn pass_into_js_as_js_class() {
         "#)
         .test();
 }
+
+#[test]
+fn vectors_of_structs() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+            use wasm_bindgen::prelude::*;
+
+            static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+            #[wasm_bindgen]
+            pub struct Item {
+                id: u32,
+            }
+
+            impl Drop for Item {
+                fn drop(&mut self) {
+                    DROPS.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Item {
+                pub fn id(&self) -> u32 {
+                    self.id
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn items(n: u32) -> Vec<Item> {
+                (0..n).map(|id| Item { id }).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn drops() -> u32 {
+                DROPS.load(Ordering::SeqCst) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Item, items, drops } from "./out";
+
+            export function test() {
+                assert.deepStrictEqual(items(0), []);
+
+                const list: Item[] = items(3);
+                assert.strictEqual(list.length, 3);
+                assert.ok(list.every(item => item instanceof Item));
+                assert.deepStrictEqual(list.map(item => item.id()), [0, 1, 2]);
+
+                list[1].free();
+                assert.strictEqual(drops(), 1);
+                assert.strictEqual(list[0].id(), 0);
+                assert.strictEqual(list[2].id(), 2);
+                list[0].free();
+                list[2].free();
+                assert.strictEqual(drops(), 3);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn structs_without_methods() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Opaque {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            pub fn make(contents: u32) -> Opaque {
+                Opaque { contents }
+            }
+
+            #[wasm_bindgen]
+            pub fn contents(a: &Opaque) -> u32 {
+                a.contents
+            }
+
+            #[wasm_bindgen]
+            pub fn consume(a: Opaque) -> u32 {
+                a.contents
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const a = wasm.make(3);
+                assert.strictEqual(a instanceof wasm.Opaque, true);
+                assert.strictEqual(wasm.contents(a), 3);
+                a.free();
+                assert.throws(() => a.free(), /null pointer passed to rust/);
+
+                const b = wasm.make(4);
+                assert.strictEqual(wasm.consume(b), 4);
+                assert.throws(() => b.free(), /null pointer passed to rust/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn public_fields() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Point {
+                pub x: u32,
+                pub y: f64,
+                #[wasm_bindgen(readonly)]
+                pub id: u32,
+                pub visible: bool,
+                hidden: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Point {
+                pub fn new() -> Point {
+                    Point { x: 1, y: 2.5, id: 7, visible: false, hidden: 3 }
+                }
+
+                pub fn sum(&self) -> f64 {
+                    self.x as f64 + self.y + self.hidden as f64
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Point } from "./out";
+
+            export function test() {
+                const p = Point.new();
+                assert.strictEqual(p.x, 1);
+                assert.strictEqual(p.y, 2.5);
+                assert.strictEqual(p.id, 7);
+                assert.strictEqual(p.visible, false);
+                assert.strictEqual((p as any).hidden, undefined);
+
+                p.x = 10;
+                p.y = 0.5;
+                p.visible = true;
+                assert.strictEqual(p.x, 10);
+                assert.strictEqual(p.visible, true);
+                assert.strictEqual(p.sum(), 13.5);
+
+                assert.throws(() => { (p as any).id = 8; }, TypeError);
+                assert.strictEqual(p.id, 7);
+
+                const desc = Object.getOwnPropertyDescriptor(Point.prototype, 'x');
+                assert.strictEqual(typeof desc.get, 'function');
+                assert.strictEqual(typeof desc.set, 'function');
+                const idDesc = Object.getOwnPropertyDescriptor(Point.prototype, 'id');
+                assert.strictEqual(idDesc.set, undefined);
+                assert.strictEqual(Object.keys(p).indexOf('x'), -1);
+                p.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn skipped_fields() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::HashSet;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Tags {
+                pub count: u32,
+                #[wasm_bindgen(skip)]
+                pub seen: HashSet<u32>,
+                #[wasm_bindgen(skip)]
+                pub secret: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Tags {
+                pub fn new() -> Tags {
+                    Tags { count: 0, seen: HashSet::new(), secret: 5 }
+                }
+
+                pub fn add(&mut self, tag: u32) {
+                    if self.seen.insert(tag) {
+                        self.count += 1;
+                    }
+                }
+
+                pub fn secret(&self) -> u32 {
+                    self.secret
+                }
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const { Tags } = require('./out');
+
+            module.exports.test = function() {
+                const t = Tags.new();
+                t.add(1);
+                t.add(1);
+                t.add(2);
+                assert.strictEqual(t.count, 2);
+                assert.strictEqual(t.secret(), 5);
+                assert.strictEqual(Object.getOwnPropertyDescriptor(Tags.prototype, 'seen'), undefined);
+                assert.strictEqual(typeof Object.getOwnPropertyDescriptor(Tags.prototype, 'secret').value,
+                                   'function');
+                t.free();
+
+                const ts = fs.readFileSync(path.join(__dirname, 'out.d.ts'), 'utf8');
+                assert.ok(ts.includes('count'));
+                assert.ok(!ts.includes('seen'));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn skipped_functions() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::HashSet;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Tags {
+                seen: HashSet<u32>,
+            }
+
+            #[wasm_bindgen]
+            impl Tags {
+                pub fn new() -> Tags {
+                    Tags { seen: tags(&[1]) }
+                }
+
+                pub fn count(&self) -> u32 {
+                    self.seen().len() as u32
+                }
+
+                #[wasm_bindgen(skip)]
+                pub fn seen(&self) -> &HashSet<u32> {
+                    &self.seen
+                }
+            }
+
+            #[wasm_bindgen(skip)]
+            pub fn tags(list: &[u32]) -> HashSet<u32> {
+                list.iter().cloned().collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn distinct(list: &[u32]) -> u32 {
+                tags(list).len() as u32
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.distinct(new Uint32Array([1, 2, 1])), 2);
+                assert.strictEqual(wasm.tags, undefined);
+
+                const t = wasm.Tags.new();
+                assert.strictEqual(t.count(), 1);
+                assert.strictEqual(t.seen, undefined);
+                t.free();
+
+                const ts = fs.readFileSync(path.join(__dirname, 'out.d.ts'), 'utf8');
+                assert.ok(ts.includes('distinct'));
+                assert.ok(!ts.includes('tags('));
+                assert.ok(!ts.includes('seen'));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn descriptor_collisions() {
+    // `FooAby` and `FooAho` hash to the same descriptor. Within one
+    // `#[wasm_bindgen]` invocation the macro catches this itself, so they're
+    // declared separately here to leave the check to the CLI.
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct FooAby {}
+
+            #[wasm_bindgen]
+            pub struct FooAho {}
+
+            #[wasm_bindgen]
+            pub fn swap(_a: FooAby) -> FooAho {
+                FooAho {}
+            }
+        "#)
+        .test_bindgen_error("are both assigned the descriptor");
+}
+
+#[test]
+fn js_class_aliases() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(js_class = OldWidget, js_class = LegacyWidget)]
+            pub struct Widget {
+                id: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Widget {
+                pub fn new(id: u32) -> Widget {
+                    Widget { id }
+                }
+
+                pub fn id(&self) -> u32 {
+                    self.id
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn widget_id(w: &Widget) -> u32 {
+                w.id
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Widget, OldWidget, LegacyWidget, widget_id } from "./out";
+
+            export function test() {
+                assert.strictEqual(OldWidget, Widget);
+                assert.strictEqual(LegacyWidget, Widget);
+
+                const a = Widget.new(1);
+                const b: OldWidget = OldWidget.new(2);
+                assert.ok(a instanceof OldWidget);
+                assert.ok(a instanceof LegacyWidget);
+                assert.ok(b instanceof Widget);
+                assert.strictEqual(b.id(), 2);
+                assert.strictEqual(widget_id(b), 2);
+                a.free();
+                b.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn free_on_gc() {
+    test_support::project()
+        .free_on_gc(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+            use wasm_bindgen::prelude::*;
+
+            static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+            #[wasm_bindgen]
+            pub struct Counted {
+                contents: u32,
+            }
+
+            impl Drop for Counted {
+                fn drop(&mut self) {
+                    DROPS.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Counted {
+                pub fn new(contents: u32) -> Counted {
+                    Counted { contents }
+                }
+
+                pub fn contents(&self) -> u32 {
+                    self.contents
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn consume(a: Counted) -> u32 {
+                a.contents
+            }
+
+            #[wasm_bindgen]
+            pub fn drops() -> u32 {
+                DROPS.load(Ordering::SeqCst) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            declare const gc: () => void;
+
+            // Finalizers only run once the current task is done, so keep
+            // collecting on later ones until the drop shows up
+            function collected(tries: number): Promise<void> {
+                gc();
+                return new Promise(resolve => setTimeout(resolve, 0)).then(() => {
+                    if (wasm.drops() < 3 && tries > 0)
+                        return collected(tries - 1);
+                });
+            }
+
+            export function test() {
+                const a = wasm.Counted.new(3);
+                assert.strictEqual(a.contents(), 3);
+                a.free();
+                assert.strictEqual(wasm.drops(), 1);
+
+                const b = wasm.Counted.new(4);
+                assert.strictEqual(wasm.consume(b), 4);
+                assert.strictEqual(wasm.drops(), 2);
+
+                // Left for the garbage collector
+                wasm.Counted.new(5);
+                return collected(100).then(() => assert.strictEqual(wasm.drops(), 3));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn constructors() {
+    test_support::project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Point {
+                x: u32,
+                y: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Point {
+                #[wasm_bindgen(constructor)]
+                pub fn new(x: u32, y: u32) -> Point {
+                    Point { x, y }
+                }
+
+                pub fn origin() -> Point {
+                    Point { x: 0, y: 0 }
+                }
+
+                pub fn sum(&self) -> u32 {
+                    self.x + self.y
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Point } from "./out";
+
+            export function test() {
+                const p = new Point(1, 2);
+                assert.ok(p instanceof Point);
+                assert.strictEqual(p.sum(), 3);
+                p.free();
+
+                const o = Point.origin();
+                assert.ok(o instanceof Point);
+                assert.strictEqual(o.sum(), 0);
+                o.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn two_constructors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            impl Foo {
+                #[wasm_bindgen(constructor)]
+                pub fn new() -> Foo {
+                    Foo {}
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                #[wasm_bindgen(constructor)]
+                pub fn other() -> Foo {
+                    Foo {}
+                }
+            }
+        "#)
+        .test_bindgen_error("both `new` and `other` are marked as the constructor of `Foo`");
+}
+
+#[test]
+fn default_export() {
+    test_support::project()
+        .default_export(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                #[wasm_bindgen(constructor)]
+                pub fn new() -> Counter {
+                    Counter { count: 0 }
+                }
+
+                pub fn incr(&mut self) -> u32 {
+                    self.count += 1;
+                    self.count
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import Counter from "./out";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(Counter, wasm.Counter);
+                const c = new Counter();
+                assert.strictEqual(c.incr(), 1);
+                assert.strictEqual(c.incr(), 2);
+                c.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn default_export_needs_one_struct() {
+    test_support::project()
+        .default_export(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            pub struct Bar {}
+        "#)
+        .test_bindgen_error("`--default-export` needs exactly one exported struct \
+                             but found `Bar`, `Foo`");
+
+    test_support::project()
+        .default_export(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn foo() {}
+        "#)
+        .test_bindgen_error("`--default-export` needs an exported struct but none were found");
+}
+
+#[test]
+fn borrowed_return_values() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greeting() -> &'static str {
+                "hello"
+            }
+
+            #[wasm_bindgen]
+            pub struct Person {
+                name: String,
+                bytes: Vec<u8>,
+            }
+
+            #[wasm_bindgen]
+            impl Person {
+                pub fn new(name: String) -> Person {
+                    let bytes = name.clone().into_bytes();
+                    Person { name, bytes }
+                }
+
+                pub fn name(&self) -> &str {
+                    &self.name
+                }
+
+                pub fn bytes(&self) -> &[u8] {
+                    &self.bytes
+                }
+
+                pub fn initial(&self) -> Result<&str, JsValue> {
+                    match self.name.get(..1) {
+                        Some(s) if !s.is_empty() => Ok(s),
+                        _ => Err("no name".into()),
+                    }
+                }
+
+                pub fn rename(&mut self, name: String) {
+                    self.name = name;
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Person, greeting } from "./out";
+
+            export function test() {
+                assert.strictEqual(greeting(), "hello");
+
+                const p = Person.new("Ferris");
+                const name = p.name();
+                assert.strictEqual(name, "Ferris");
+                assert.deepStrictEqual(Array.from(p.bytes()), [70, 101, 114, 114, 105, 115]);
+                assert.strictEqual(p.initial(), "F");
+
+                // The strings were copied out and the borrow of `p` ended
+                // with each call, so it can be changed straight away
+                p.rename("Corro");
+                assert.strictEqual(name, "Ferris");
+                assert.strictEqual(p.name(), "Corro");
+
+                p.rename("");
+                assert.throws(() => p.initial(), /no name/);
+                p.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn shared_structs() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::cell::{Cell, RefCell};
+            use std::rc::{Rc, Weak};
+            use std::sync::Arc;
+
+            use wasm_bindgen::prelude::*;
+
+            thread_local! {
+                static DROPS: Cell<u32> = Cell::new(0);
+                static KEPT: RefCell<Option<Rc<Shared>>> = RefCell::new(None);
+                static WEAK: RefCell<Weak<Shared>> = RefCell::new(Weak::new());
+            }
+
+            #[wasm_bindgen]
+            pub struct Shared {
+                value: u32,
+            }
+
+            impl Drop for Shared {
+                fn drop(&mut self) {
+                    DROPS.with(|d| d.set(d.get() + 1));
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Shared {
+                pub fn new(value: u32) -> Shared {
+                    Shared { value }
+                }
+
+                pub fn value(&self) -> u32 {
+                    self.value
+                }
+
+                pub fn set_value(&mut self, value: u32) {
+                    self.value = value;
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn make_rc(value: u32) -> Rc<Shared> {
+                Rc::new(Shared { value })
+            }
+
+            #[wasm_bindgen]
+            pub fn make_arc(value: u32) -> Arc<Shared> {
+                Arc::new(Shared { value })
+            }
+
+            #[wasm_bindgen]
+            pub fn keep(s: Rc<Shared>) {
+                WEAK.with(|w| *w.borrow_mut() = Rc::downgrade(&s));
+                KEPT.with(|k| *k.borrow_mut() = Some(s));
+            }
+
+            #[wasm_bindgen]
+            pub fn kept() -> Rc<Shared> {
+                KEPT.with(|k| k.borrow().clone().unwrap())
+            }
+
+            #[wasm_bindgen]
+            pub fn release() {
+                KEPT.with(|k| k.borrow_mut().take());
+            }
+
+            #[wasm_bindgen]
+            pub fn strong_count() -> u32 {
+                WEAK.with(|w| match w.borrow().upgrade() {
+                    Some(s) => Rc::strong_count(&s) as u32 - 1,
+                    None => 0,
+                })
+            }
+
+            #[wasm_bindgen]
+            pub fn drops() -> u32 {
+                DROPS.with(|d| d.get())
+            }
+
+            #[wasm_bindgen]
+            pub fn take(s: Shared) -> u32 {
+                s.value
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                // Owned values are moved into an `Rc` when passed as one
+                wasm.keep(wasm.Shared.new(3));
+                assert.strictEqual(wasm.strong_count(), 1);
+
+                const a = wasm.kept();
+                const b = wasm.kept();
+                assert.strictEqual(wasm.strong_count(), 3);
+                assert.strictEqual(a.value(), 3);
+                assert.strictEqual(b.value(), 3);
+                assert.throws(() => a.set_value(4), /cannot mutably borrow/);
+
+                // Only the last reference frees the value
+                wasm.release();
+                a.free();
+                assert.strictEqual(wasm.strong_count(), 1);
+                assert.strictEqual(wasm.drops(), 0);
+                b.free();
+                assert.strictEqual(wasm.strong_count(), 0);
+                assert.strictEqual(wasm.drops(), 1);
+
+                // The last reference can also be taken by value
+                assert.strictEqual(wasm.take(wasm.make_rc(5)), 5);
+                assert.strictEqual(wasm.drops(), 2);
+
+                const c = wasm.make_arc(6);
+                assert.strictEqual(c.value(), 6);
+                c.free();
+                assert.strictEqual(wasm.drops(), 3);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn getter_with_clone() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Person {
+                #[wasm_bindgen(getter_with_clone)]
+                pub name: String,
+                pub age: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Person {
+                pub fn new(name: String, age: u32) -> Person {
+                    Person { name, age }
+                }
+
+                pub fn name_len(&self) -> u32 {
+                    self.name.len() as u32
+                }
+            }
+
+            #[wasm_bindgen(getter_with_clone)]
+            pub struct Scores {
+                pub values: Vec<u32>,
+                pub label: String,
+            }
+
+            #[wasm_bindgen]
+            impl Scores {
+                pub fn new() -> Scores {
+                    Scores { values: vec![1, 2, 3], label: "scores".to_string() }
+                }
+
+                pub fn sum(&self) -> u32 {
+                    self.values.iter().sum()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const p = wasm.Person.new("Ferris", 3);
+                // Each read gets a fresh copy, leaving the field alone
+                for (let i = 0; i < 3; i++)
+                    assert.strictEqual(p.name, "Ferris");
+                assert.strictEqual(p.name_len(), 6);
+
+                p.name = "Corro";
+                assert.strictEqual(p.name, "Corro");
+                assert.strictEqual(p.name_len(), 5);
+                assert.strictEqual(p.age, 3);
+                p.free();
+
+                const s = wasm.Scores.new();
+                const values = s.values;
+                assert.deepStrictEqual(Array.from(values), [1, 2, 3]);
+                values[0] = 10;
+                assert.deepStrictEqual(Array.from(s.values), [1, 2, 3]);
+                assert.strictEqual(s.sum(), 6);
+                assert.strictEqual(s.label, "scores");
+                s.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn consuming_methods() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            static mut DROPS: u32 = 0;
+
+            #[wasm_bindgen]
+            pub fn drops() -> u32 {
+                unsafe { DROPS }
+            }
+
+            #[wasm_bindgen]
+            pub struct Builder {
+                parts: Vec<String>,
+            }
+
+            impl Drop for Builder {
+                fn drop(&mut self) {
+                    unsafe { DROPS += 1; }
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Builder {
+                pub fn new() -> Builder {
+                    Builder { parts: Vec::new() }
+                }
+
+                pub fn push(&mut self, part: &str) {
+                    self.parts.push(part.to_string());
+                }
+
+                pub fn finish(self, sep: char) -> String {
+                    self.parts.join(&sep.to_string())
+                }
+
+                pub fn into_len(mut self, extra: u32) -> u32 {
+                    self.parts.push(String::new());
+                    self.parts.len() as u32 - 1 + extra
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const b = wasm.Builder.new();
+                b.push("a");
+                b.push("b");
+                // A rejected argument leaves the object usable
+                assert.throws(() => b.finish(3 as any));
+                assert.strictEqual(wasm.drops(), 0);
+                assert.notStrictEqual(b.ptr, 0);
+
+                assert.strictEqual(b.finish(","), "a,b");
+                assert.strictEqual(wasm.drops(), 1);
+                assert.strictEqual(b.ptr, 0);
+                assert.throws(() => b.finish(","), /object already consumed/);
+                assert.throws(() => b.into_len(1), /object already consumed/);
+                assert.strictEqual(wasm.drops(), 1);
+
+                const c = wasm.Builder.new();
+                c.push("x");
+                assert.strictEqual(c.into_len(2), 3);
+                assert.strictEqual(wasm.drops(), 2);
+                assert.throws(() => c.into_len(2), /object already consumed/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_struct_arguments() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                pub fn new(count: u32) -> Counter {
+                    Counter { count }
+                }
+
+                pub fn count(&self) -> u32 {
+                    self.count
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn total(a: &Counter, b: &Counter) -> u32 {
+                a.count + b.count
+            }
+
+            #[wasm_bindgen]
+            pub fn bump(a: &mut Counter, by: &Counter) {
+                a.count += by.count;
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const a = wasm.Counter.new(1);
+                const b = wasm.Counter.new(2);
+                assert.strictEqual(wasm.total(a, b), 3);
+                // Borrowing leaves both wrappers pointing at their structs
+                assert.notStrictEqual(a.ptr, 0);
+                assert.notStrictEqual(b.ptr, 0);
+                assert.strictEqual(wasm.total(a, a), 2);
+
+                wasm.bump(a, b);
+                assert.strictEqual(a.count(), 3);
+                assert.strictEqual(b.count(), 2);
+
+                // Nothing is borrowed yet once the freed handle is rejected
+                b.free();
+                assert.throws(() => wasm.total(b, a), /null pointer passed to rust/);
+                assert.throws(() => wasm.bump(b, a), /null pointer passed to rust/);
+                assert.strictEqual(a.count(), 3);
+                a.free();
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,357 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::cell::Cell;
+            use std::rc::Rc;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn call(a: &Closure<Fn()>);
+                fn call_with(a: &Closure<FnMut(u32, String) -> u32>) -> u32;
+                fn keep(a: &Closure<Fn(bool) -> bool>);
+                fn call_kept(a: bool) -> bool;
+                fn call_wrong(a: &Closure<FnMut(u32) -> u32>) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let hits = Rc::new(Cell::new(0));
+                let hits2 = hits.clone();
+                let a = Closure::new(move || hits2.set(hits2.get() + 1));
+                call(&a);
+                call(&a);
+                assert_eq!(hits.get(), 2);
+
+                let mut total = 0;
+                let b = Closure::new(move |a: u32, s: String| {
+                    total += a + s.len() as u32;
+                    total
+                });
+                assert_eq!(call_with(&b), 13);
+            }
+
+            #[wasm_bindgen]
+            pub fn wrong_argument() {
+                let mut calls = 0;
+                let d = Closure::new(move |a: u32| {
+                    calls += 1;
+                    a + calls
+                });
+                assert_eq!(call_wrong(&d), 6);
+            }
+
+            #[wasm_bindgen]
+            pub fn keep_and_drop() {
+                let c = Closure::new(|b: bool| !b);
+                keep(&c);
+                assert!(call_kept(true) == false);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function call(a: any) {
+                a();
+            }
+
+            export function call_with(a: any): number {
+                assert.strictEqual(a(1, "ab"), 3);
+                return a(7, "abc");
+            }
+
+            export function call_wrong(a: any): number {
+                assert.throws(() => a("five"), /expected a number argument/);
+                return a(5);
+            }
+
+            let KEPT: any = null;
+
+            export function keep(a: any) {
+                KEPT = a;
+            }
+
+            export function call_kept(a: boolean): boolean {
+                return KEPT(a);
+            }
+
+            export function test() {
+                wasm.run();
+                wasm.wrong_argument();
+                wasm.keep_and_drop();
+                assert.throws(() => KEPT(true), /closure invoked after being dropped/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_callbacks() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::cell::Cell;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn sort_by(values: &JsValue, cmp: &Fn(f64, f64) -> f64);
+                fn call_each(a: &Fn(u32), b: &Fn(String) -> bool) -> u32;
+                fn keep(a: &Fn(bool) -> bool);
+            }
+
+            #[wasm_bindgen]
+            pub fn sort(values: &JsValue, descending: bool) -> u32 {
+                let calls = Cell::new(0);
+                sort_by(values, &|a, b| {
+                    calls.set(calls.get() + 1);
+                    if descending { b - a } else { a - b }
+                });
+                calls.get()
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let total = Cell::new(0);
+                let prefix = String::from("ab");
+                let n = call_each(&|a| total.set(total.get() + a),
+                                  &|s| s.starts_with(&prefix));
+                assert_eq!(n, 2);
+                assert_eq!(total.get(), 7);
+
+                let flips = Cell::new(0);
+                keep(&|b| {
+                    flips.set(flips.get() + 1);
+                    !b
+                });
+                assert_eq!(flips.get(), 1);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function sort_by(values: number[], cmp: any) {
+                values.sort(cmp);
+            }
+
+            export function call_each(a: any, b: any): number {
+                a(3);
+                a(4);
+                let n = 0;
+                for (const s of ["abc", "b", "ab"])
+                    if (b(s))
+                        n += 1;
+                return n;
+            }
+
+            let KEPT: any = null;
+
+            export function keep(a: any) {
+                KEPT = a;
+                assert.strictEqual(a(true), false);
+            }
+
+            export function test() {
+                const values = [3, 1, 2];
+                assert.ok(wasm.sort(values, false) > 0);
+                assert.deepStrictEqual(values, [1, 2, 3]);
+                wasm.sort(values, true);
+                assert.deepStrictEqual(values, [3, 2, 1]);
+
+                wasm.run();
+                assert.throws(() => KEPT(true),
+                              /closure invoked after the call it was passed to returned/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn reentrancy() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn store(a: &Closure<Fn(u32) -> u32>);
+                fn store_mut(a: &Closure<FnMut(u32) -> u32>);
+                fn with_stack(a: &Fn(u32) -> u32, n: u32) -> u32;
+                fn recurse(n: u32) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn nested() -> u32 {
+                let a = Closure::new(|n: u32| if n == 0 { 0 } else { n + recurse(n - 1) });
+                store(&a);
+                recurse(3)
+            }
+
+            #[wasm_bindgen]
+            pub fn nested_stack() -> u32 {
+                with_stack(&|n| if n == 0 { 0 } else { n + recurse(n - 1) }, 4)
+            }
+
+            #[wasm_bindgen]
+            pub fn nested_mut() -> u32 {
+                let mut calls = 0;
+                let a = Closure::new(move |n: u32| {
+                    calls += 1;
+                    if n == 0 { calls } else { recurse(n - 1) }
+                });
+                store_mut(&a);
+                recurse(0) + recurse(1)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            let STORED: any = null;
+
+            export function store(a: any) {
+                STORED = a;
+            }
+
+            export function store_mut(a: any) {
+                STORED = a;
+            }
+
+            export function with_stack(a: any, n: number): number {
+                STORED = a;
+                return a(n);
+            }
+
+            export function recurse(n: number): number {
+                return STORED(n);
+            }
+
+            export function test() {
+                // `Fn` closures, borrowed or not, may call themselves
+                assert.strictEqual(wasm.nested(), 6);
+                assert.strictEqual(wasm.nested_stack(), 10);
+
+                // while an `FnMut` can't, as that would alias it
+                assert.throws(() => wasm.nested_mut(), /closure invoked recursively/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn throwing() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::cell::RefCell;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn call_throwing(a: &Closure<FnMut(bool) -> u32>) -> u32;
+                fn fail();
+                fn keep(a: &Closure<FnMut()>);
+                fn call_kept();
+            }
+
+            #[wasm_bindgen]
+            pub fn throws_then_drops() -> u32 {
+                let mut calls = 0;
+                let a = Closure::new(move |fail: bool| {
+                    calls += 1;
+                    if fail {
+                        wasm_bindgen::throw("closure failed");
+                    }
+                    calls
+                });
+                call_throwing(&a)
+            }
+
+            #[wasm_bindgen]
+            pub fn import_throws() {
+                let a = Closure::new(|fail: bool| {
+                    if fail {
+                        fail_import();
+                    }
+                    1
+                });
+                call_throwing(&a);
+            }
+
+            fn fail_import() {
+                fail();
+            }
+
+            thread_local!(static ONCE: RefCell<Option<Closure<FnMut()>>> = RefCell::new(None));
+
+            #[wasm_bindgen]
+            pub fn drops_itself() {
+                let hits = vec![1, 2, 3];
+                let a = Closure::new(move || {
+                    // Freeing the closure waits until this call is done, so
+                    // what it captured is still around afterwards
+                    drop(ONCE.with(|once| once.borrow_mut().take()));
+                    assert_eq!(hits.len(), 3);
+                });
+                keep(&a);
+                ONCE.with(|once| *once.borrow_mut() = Some(a));
+                call_kept();
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function call_throwing(a: any): number {
+                assert.throws(() => a(true));
+                return a(false);
+            }
+
+            export function fail() {
+                throw new Error("import failed");
+            }
+
+            let KEPT: any = null;
+
+            export function keep(a: any) {
+                KEPT = a;
+            }
+
+            export function call_kept() {
+                KEPT();
+            }
+
+            export function test() {
+                // The closure can be called again after throwing, and is then
+                // dropped at the end of the function as usual
+                assert.strictEqual(wasm.throws_then_drops(), 2);
+                wasm.import_throws();
+
+                wasm.drops_itself();
+                assert.throws(() => KEPT(), /closure invoked after being dropped/);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,84 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::time::{Duration, UNIX_EPOCH};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn epoch() -> JsDate;
+                fn not_a_date() -> JsDate;
+                fn is_same(a: JsDate, ms: f64) -> bool;
+            }
+
+            #[wasm_bindgen]
+            pub fn tomorrow(today: JsDate) -> JsDate {
+                let ms = today.to_millis().unwrap();
+                JsDate::from_millis(ms + 24.0 * 60.0 * 60.0 * 1000.0)
+            }
+
+            #[wasm_bindgen]
+            pub fn millis(date: JsDate) -> f64 {
+                date.to_millis().unwrap_or(-1.0)
+            }
+
+            #[wasm_bindgen]
+            pub fn invalid() -> JsDate {
+                JsDate::from_millis(1.0 / 0.0)
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(epoch().to_millis(), Some(0.0));
+                assert_eq!(epoch().to_system_time(), Some(UNIX_EPOCH));
+                assert_eq!(not_a_date().to_millis(), None);
+                assert_eq!(not_a_date().to_system_time(), None);
+                assert_eq!(JsDate::from_millis(0.0 / 0.0).to_millis(), None);
+
+                let later = UNIX_EPOCH + Duration::new(1, 2_500_000);
+                assert!(is_same(later.into(), 1002.0));
+                let earlier = UNIX_EPOCH - Duration::from_millis(1500);
+                assert!(is_same(earlier.into(), -1500.0));
+                assert_eq!(JsDate::from(earlier).to_system_time(), Some(earlier));
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function epoch(): Date {
+                return new Date(0);
+            }
+
+            export function not_a_date(): any {
+                return "2018-01-01";
+            }
+
+            export function is_same(a: Date, ms: number): boolean {
+                return a instanceof Date && a.getTime() === ms;
+            }
+
+            export function test() {
+                const day = new Date(2018, 4, 1);
+                const next = wasm.tomorrow(day);
+                assert.ok(next instanceof Date);
+                assert.strictEqual(next.getTime() - day.getTime(), 24 * 60 * 60 * 1000);
+
+                assert.strictEqual(wasm.millis(new Date(5)), 5);
+                assert.strictEqual(wasm.millis(new Date(NaN)), -1);
+                assert.ok(isNaN(wasm.invalid().getTime()));
+                assert.throws(() => wasm.millis(5 as any), /expected a `Date`/);
+
+                wasm.run();
+            }
+        "#)
+        .test();
+}
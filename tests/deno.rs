@@ -0,0 +1,125 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    let js = test_support::project()
+        .deno(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "https://deno.land/std/fmt/colors.ts")]
+            extern {
+                fn bold(s: &str) -> String;
+            }
+
+            #[wasm_bindgen(module = "./util.js")]
+            extern {
+                fn shout(s: &str) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str) -> String {
+                bold(&shout(&format!("hello {}", name)))
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                pub fn new() -> Counter {
+                    Counter { count: 0 }
+                }
+            }
+        "#)
+        .generated_js();
+
+    // Modules are imported by the specifiers given, without any resolution
+    assert!(js.contains("import { bold } from 'https://deno.land/std/fmt/colors.ts';"));
+    assert!(js.contains("import { shout } from './util.js';"));
+
+    // The wasm is loaded next to the module, rather than imported as one, and
+    // given this module's exports as its imports
+    assert!(js.contains("import * as __wbg_self from './out.js';"));
+    assert!(js.contains("new URL('./out_bg.wasm', import.meta.url)"));
+    assert!(js.contains("{ './out': __wbg_self }"));
+    assert!(!js.contains("from './out_bg'"));
+
+    // Deno has `TextEncoder` and `TextDecoder` as globals, and no `require`
+    assert!(js.contains("new TextEncoder('utf-8')"));
+    assert!(js.contains("new TextDecoder('utf-8')"));
+    assert!(!js.contains("require("));
+    assert!(!js.contains("module.exports"));
+    assert!(js.contains("export class Counter"));
+}
+
+#[test]
+fn env_imports() {
+    let js = test_support::project()
+        .deno(true)
+        .env_imports(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn hello() -> String {
+                "hello".to_string()
+            }
+        "#)
+        .generated_js();
+    assert!(js.contains("{ 'env': __wbg_self }"));
+}
+
+#[test]
+fn bare_modules_rejected() {
+    test_support::project()
+        .deno(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "left-pad")]
+            extern {
+                fn pad(s: &str) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() -> String {
+                pad("a")
+            }
+        "#)
+        .test_bindgen_error("Deno can't import from the module `left-pad`");
+}
+
+#[test]
+fn browser_rejected() {
+    test_support::project()
+        .deno(true)
+        .browser(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn hello() -> u32 {
+                1
+            }
+        "#)
+        .test_bindgen_error("can't generate bindings for both the browser and Deno");
+}
@@ -37,6 +37,10 @@ fn c_style_enum() {
                 assert.strictEqual(Object.keys(wasm.Color).length, 3);
 
                 assert.strictEqual(wasm.cycle(wasm.Color.Green), wasm.Color.Yellow);
+
+                assert.strictEqual(wasm.Color[0], "Green");
+                assert.strictEqual(wasm.Color[wasm.cycle(wasm.Color.Yellow)], "Red");
+                assert.strictEqual(wasm.Color[3], undefined);
             }
         "#)
         .test();
@@ -75,10 +79,257 @@ fn c_style_enum_with_custom_values() {
             export function test() {
                 assert.strictEqual(wasm.Color.Green, 21);
                 assert.strictEqual(wasm.Color.Yellow, 34);
-                assert.strictEqual(wasm.Color.Red, 2);
+                assert.strictEqual(wasm.Color.Red, 35);
                 assert.strictEqual(Object.keys(wasm.Color).length, 3);
 
                 assert.strictEqual(wasm.cycle(wasm.Color.Green), wasm.Color.Yellow);
+                assert.strictEqual(wasm.cycle(wasm.Color.Yellow), wasm.Color.Red);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn non_contiguous_values() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            // Values past `i32::MAX` need a wider representation than the
+            // default `isize`.
+            #[wasm_bindgen]
+            #[repr(u32)]
+            pub enum Status {
+                Ok = 200,
+                NotFound = 404,
+                Teapot = 418,
+                Huge = 4000000000,
+                Max = 4294967295,
+            }
+
+            #[wasm_bindgen]
+            pub fn identity(status: Status) -> Status {
+                status
+            }
+
+            #[wasm_bindgen]
+            pub fn huge() -> Status {
+                Status::Huge
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.Status.Ok, 200);
+                assert.strictEqual(wasm.Status.NotFound, 404);
+                assert.strictEqual(wasm.Status.Teapot, 418);
+                assert.strictEqual(wasm.Status.Huge, 4000000000);
+                assert.strictEqual(wasm.Status.Max, 4294967295);
+                assert.deepStrictEqual(Object.keys(wasm.Status),
+                                       ["Ok", "NotFound", "Teapot", "Huge", "Max"]);
+
+                assert.strictEqual(wasm.Status[404], "NotFound");
+                assert.strictEqual(wasm.Status[4294967295], "Max");
+
+                assert.strictEqual(wasm.identity(wasm.Status.Teapot), wasm.Status.Teapot);
+                assert.strictEqual(wasm.identity(wasm.Status.Max), wasm.Status.Max);
+                assert.strictEqual(wasm.huge(), 4000000000);
+
+                assert.throws(() => wasm.identity(201), /Could not cast 201 as Status/);
+                assert.throws(() => wasm.identity(0), /Could not cast 0 as Status/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn non_contiguous_declarations() {
+    let ts = test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[repr(u32)]
+            pub enum Status {
+                Ok = 200,
+                Created,
+                NotFound = 404,
+                Max = 4294967295,
+            }
+
+            #[wasm_bindgen]
+            pub fn identity(status: Status) -> Status {
+                status
+            }
+        "#)
+        .generated_ts();
+    assert!(ts.contains("export enum Status {Ok = 200,Created = 201,NotFound = 404,\
+                         Max = 4294967295,}"),
+            "unexpected declarations:\n{}", ts);
+}
+
+#[test]
+fn imported_enums() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module, non_exhaustive)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(imported, module = "./test", js_namespace = NodeKind)]
+            #[derive(Copy, Clone, Debug, PartialEq)]
+            pub enum NodeType {
+                #[wasm_bindgen(js_name = ELEMENT)]
+                Element,
+                #[wasm_bindgen(js_name = TEXT)]
+                Text,
+            }
+
+            #[wasm_bindgen(imported, module = "./test", js_namespace = Taste)]
+            #[non_exhaustive]
+            #[derive(Copy, Clone, Debug, PartialEq)]
+            pub enum Flavor {
+                #[wasm_bindgen(js_name = SWEET)]
+                Sweet,
+                #[wasm_bindgen(js_name = SOUR)]
+                Sour,
+                #[wasm_bindgen(unknown)]
+                Other,
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn kind_of(tag: &str) -> NodeType;
+                fn describe(kind: NodeType) -> String;
+                fn flavor_of(n: u32) -> Flavor;
+            }
+
+            #[wasm_bindgen]
+            pub fn roundtrip(kind: NodeType) -> NodeType {
+                kind
+            }
+
+            #[wasm_bindgen]
+            pub fn is_other(flavor: Flavor) -> bool {
+                flavor == Flavor::Other
+            }
+
+            #[wasm_bindgen]
+            pub fn other() -> Flavor {
+                Flavor::Other
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(kind_of("p"), NodeType::Element);
+                assert_eq!(kind_of("#text"), NodeType::Text);
+                assert_eq!(describe(NodeType::Text), "text");
+                assert_eq!(flavor_of(20), Flavor::Sour);
+                assert_eq!(flavor_of(30), Flavor::Other);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export class NodeKind {
+                static ELEMENT = 1;
+                static TEXT = 3;
+            }
+
+            export const Taste = { SWEET: 10, SOUR: 20 };
+
+            export function kind_of(tag: string): number {
+                return tag === "#text" ? NodeKind.TEXT : NodeKind.ELEMENT;
+            }
+
+            export function describe(kind: number): string {
+                return kind === NodeKind.TEXT ? "text" : "element";
+            }
+
+            export function flavor_of(n: number): number {
+                return n;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.roundtrip(NodeKind.TEXT), 3);
+                assert.strictEqual(wasm.roundtrip(NodeKind.ELEMENT), 1);
+                assert.throws(() => wasm.roundtrip(2), /2 is not a value of `NodeType`/);
+
+                assert.strictEqual(wasm.is_other(Taste.SWEET), false);
+                assert.strictEqual(wasm.is_other(99), true);
+                assert.throws(() => wasm.other(), /unknown values of `Flavor` cannot be passed/);
+
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn variant_js_names() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[allow(non_camel_case_types)]
+            pub enum Shape {
+                #[wasm_bindgen(js_name = Circle)]
+                circle,
+                #[wasm_bindgen(js_name = RoundedBox)]
+                rounded_box = 5,
+                square,
+            }
+
+            #[wasm_bindgen]
+            pub fn sides(shape: Shape) -> u32 {
+                match shape {
+                    Shape::circle => 0,
+                    Shape::rounded_box | Shape::square => 4,
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn rounded() -> Shape {
+                Shape::rounded_box
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.Shape.Circle, 0);
+                assert.strictEqual(wasm.Shape.RoundedBox, 5);
+                assert.strictEqual(wasm.Shape.square, 6);
+                assert.deepStrictEqual(Object.keys(wasm.Shape),
+                                       ["Circle", "RoundedBox", "square"]);
+                assert.strictEqual((wasm.Shape as any).circle, undefined);
+
+                assert.strictEqual(wasm.Shape[0], "Circle");
+                assert.strictEqual(wasm.Shape[5], "RoundedBox");
+                assert.strictEqual(wasm.Shape[6], "square");
+
+                assert.strictEqual(wasm.sides(wasm.Shape.Circle), 0);
+                assert.strictEqual(wasm.sides(wasm.Shape.square), 4);
+                assert.strictEqual(wasm.rounded(), wasm.Shape.RoundedBox);
             }
         "#)
         .test();
@@ -0,0 +1,173 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::future::Future;
+            use std::pin::Pin;
+            use std::task::{Context, Poll};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn resolve_later(v: u32) -> JsFuture;
+                fn reject_later(msg: &str) -> JsFuture;
+                fn never_settled() -> JsFuture;
+                fn plain() -> JsFuture;
+                fn report(ok: JsValue, err: JsValue, plain: JsValue);
+            }
+
+            struct All {
+                futures: Vec<JsFuture>,
+                results: Vec<Option<Result<JsValue, JsValue>>>,
+            }
+
+            impl Future for All {
+                type Output = ();
+
+                fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+                    let me = &mut *self;
+                    for (f, r) in me.futures.iter_mut().zip(me.results.iter_mut()) {
+                        if r.is_none() {
+                            if let Poll::Ready(v) = Pin::new(f).poll(cx) {
+                                *r = Some(v);
+                            }
+                        }
+                    }
+                    if me.results.iter().any(|r| r.is_none()) {
+                        return Poll::Pending
+                    }
+                    let mut results = me.results.drain(..).map(|r| r.unwrap());
+                    let ok = results.next().unwrap().unwrap();
+                    let err = results.next().unwrap().unwrap_err();
+                    let plain = results.next().unwrap().unwrap();
+                    report(ok, err, plain);
+                    Poll::Ready(())
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                // Dropped before the promise settles, which must be harmless
+                drop(never_settled());
+                drop(resolve_later(1));
+
+                spawn_local(All {
+                    futures: vec![resolve_later(3), reject_later("nope"), plain()],
+                    results: vec![None, None, None],
+                });
+            }
+
+            #[wasm_bindgen]
+            pub fn later() -> JsFuture {
+                resolve_later(5)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            let settleNever: any = null;
+            let finish: any = null;
+
+            export function resolve_later(v: number): Promise<number> {
+                return new Promise(resolve => setTimeout(() => resolve(v), 0));
+            }
+
+            export function reject_later(msg: string): Promise<any> {
+                return Promise.reject(new Error(msg));
+            }
+
+            export function never_settled(): Promise<any> {
+                return new Promise(resolve => { settleNever = resolve; });
+            }
+
+            export function plain(): number {
+                return 7;
+            }
+
+            export function report(ok: any, err: any, plain: any) {
+                finish([ok, err, plain]);
+            }
+
+            export function test() {
+                const reported = new Promise(resolve => { finish = resolve; });
+                wasm.run();
+                settleNever(2);
+
+                const p = wasm.later();
+                assert.ok(p instanceof Promise);
+
+                return Promise.all([reported, p]).then(([[ok, err, plain], later]: any) => {
+                    assert.strictEqual(ok, 3);
+                    assert.ok(err instanceof Error);
+                    assert.strictEqual(err.message, 'nope');
+                    assert.strictEqual(plain, 7);
+                    assert.strictEqual(later, 5);
+                });
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn exports() {
+    test_support::project()
+        .edition("2018")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn resolve_later(v: u32) -> JsFuture;
+            }
+
+            #[wasm_bindgen]
+            pub async fn add_later(a: u32, b: u32) -> u32 {
+                let a = resolve_later(a).await.unwrap().as_f64().unwrap() as u32;
+                a + b
+            }
+
+            #[wasm_bindgen]
+            pub async fn fail_later(msg: String) -> Result<(), JsValue> {
+                resolve_later(0).await?;
+                Err(JsValue::from_str(&msg))
+            }
+
+            #[wasm_bindgen]
+            pub async fn nothing() {}
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function resolve_later(v: number): Promise<number> {
+                return new Promise(resolve => setTimeout(() => resolve(v), 0));
+            }
+
+            export function test() {
+                const added: Promise<number> = wasm.add_later(1, 2);
+                const failed: Promise<void> = wasm.fail_later("nope");
+                const nothing: Promise<void> = wasm.nothing();
+                assert.ok(added instanceof Promise);
+
+                return Promise.all([
+                    added.then(v => assert.strictEqual(v, 3)),
+                    failed.then(() => assert.fail("should reject"),
+                                e => assert.strictEqual(e, "nope")),
+                    nothing.then(v => assert.strictEqual(v, undefined)),
+                ]);
+            }
+        "#)
+        .test();
+}
@@ -0,0 +1,85 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn roundtrip_i64(a: i64) -> i64;
+                fn roundtrip_u64(a: u64) -> u64;
+            }
+
+            #[wasm_bindgen]
+            pub fn i64_min() -> i64 { i64::min_value() }
+
+            #[wasm_bindgen]
+            pub fn u64_max() -> u64 { u64::max_value() }
+
+            #[wasm_bindgen]
+            pub fn i64_add(a: i64, b: i64) -> i64 { a.wrapping_add(b) }
+
+            #[wasm_bindgen]
+            pub fn u64_add(a: u32, b: u64, c: u64) -> u64 {
+                (a as u64).wrapping_add(b).wrapping_add(c)
+            }
+
+            #[wasm_bindgen]
+            pub fn is_i64_min(a: i64) -> bool { a == i64::min_value() }
+
+            #[wasm_bindgen]
+            pub fn is_u64_max(a: u64) -> bool { a == u64::max_value() }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(roundtrip_i64(i64::min_value()), i64::min_value());
+                assert_eq!(roundtrip_i64(-1), -1);
+                assert_eq!(roundtrip_i64(1 << 53), 1 << 53);
+                assert_eq!(roundtrip_u64(u64::max_value()), u64::max_value());
+                assert_eq!(roundtrip_u64(0), 0);
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+
+            module.exports.roundtrip_i64 = function(a) {
+                assert.strictEqual(typeof(a), 'bigint');
+                return a;
+            };
+            module.exports.roundtrip_u64 = function(a) {
+                assert.strictEqual(typeof(a), 'bigint');
+                return a;
+            };
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                const I64_MIN = -(BigInt(2) ** BigInt(63));
+                const U64_MAX = BigInt(2) ** BigInt(64) - BigInt(1);
+                const ABOVE_SAFE = BigInt(Number.MAX_SAFE_INTEGER) + BigInt(2);
+
+                assert.strictEqual(wasm.i64_min(), I64_MIN);
+                assert.strictEqual(wasm.u64_max(), U64_MAX);
+                assert.strictEqual(wasm.is_i64_min(I64_MIN), true);
+                assert.strictEqual(wasm.is_i64_min(BigInt(0)), false);
+                assert.strictEqual(wasm.is_u64_max(U64_MAX), true);
+
+                assert.strictEqual(wasm.i64_add(BigInt(-3), BigInt(1)), BigInt(-2));
+                assert.strictEqual(wasm.i64_add(-3, 1), BigInt(-2));
+                assert.strictEqual(wasm.i64_add(ABOVE_SAFE, BigInt(1)), ABOVE_SAFE + BigInt(1));
+                assert.strictEqual(wasm.i64_add(-ABOVE_SAFE, BigInt(-1)), -ABOVE_SAFE - BigInt(1));
+                assert.strictEqual(wasm.u64_add(1, ABOVE_SAFE, BigInt(2)), ABOVE_SAFE + BigInt(3));
+                assert.strictEqual(wasm.u64_add(0, U64_MAX, BigInt(1)), BigInt(0));
+
+                wasm.run();
+            };
+        "#)
+        .test();
+}
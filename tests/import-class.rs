@@ -200,6 +200,140 @@ fn new_constructors() {
         .test();
 }
 
+#[test]
+fn catching_constructors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Positive;
+                #[wasm_bindgen(constructor, catch)]
+                fn new(arg: i32) -> Result<Positive, JsValue>;
+                #[wasm_bindgen(method)]
+                fn get(this: &Positive) -> i32;
+                fn constructed() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let p = Positive::new(3).unwrap();
+                assert_eq!(p.get(), 3);
+
+                let e = Positive::new(-1).err().unwrap();
+                assert_eq!(e.as_string(), Some("not positive: -1".to_string()));
+                assert_eq!(constructed(), 1);
+
+                assert_eq!(Positive::new(4).unwrap().get(), 4);
+                assert_eq!(constructed(), 2);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function test() {
+                run();
+            }
+        "#)
+        .file("another.ts", r#"
+            let CONSTRUCTED = 0;
+
+            export class Positive {
+                constructor(private field: number) {
+                    if (field <= 0)
+                        throw `not positive: ${field}`;
+                    CONSTRUCTED += 1;
+                }
+
+                get() {
+                    return this.field;
+                }
+            }
+
+            export function constructed(): number {
+                return CONSTRUCTED;
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn chaining_methods() {
+    test_support::project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Builder;
+                #[wasm_bindgen(constructor)]
+                fn new() -> Builder;
+                #[wasm_bindgen(method)]
+                fn add(this: &Builder, n: u32) -> Builder;
+                #[wasm_bindgen(method)]
+                fn fork(this: &Builder) -> Builder;
+                #[wasm_bindgen(method)]
+                fn total(this: &Builder) -> u32;
+                fn same(a: &Builder, b: &Builder) -> bool;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let a = Builder::new();
+                let b = a.add(1).add(2);
+                assert!(same(&a, &b));
+                assert_eq!(b.total(), 3);
+                drop(b);
+                assert_eq!(a.add(4).total(), 7);
+
+                let c = a.fork();
+                assert!(!same(&a, &c));
+                assert_eq!(c.add(1).total(), 1);
+                assert_eq!(a.total(), 7);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function test() {
+                run();
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Builder {
+                private sum = 0;
+
+                add(n: number): Builder {
+                    this.sum += n;
+                    return this;
+                }
+
+                fork(): Builder {
+                    return new Builder();
+                }
+
+                total(): number {
+                    return this.sum;
+                }
+            }
+
+            export function same(a: Builder, b: Builder): boolean {
+                return a === b;
+            }
+        "#)
+        .test();
+}
+
 #[test]
 fn switch_methods() {
     test_support::project()
@@ -392,3 +526,746 @@ fn rename_setter_getter() {
         "#)
         .test();
 }
+
+#[test]
+fn write_only_setter() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Sink;
+
+                #[wasm_bindgen(constructor)]
+                fn new() -> Sink;
+
+                #[wasm_bindgen(setter, method, writeonly)]
+                fn set_level(this: &Sink, level: u32);
+
+                #[wasm_bindgen(setter = level, method, writeonly, structural)]
+                fn assign_level(this: &Sink, level: u32);
+
+                #[wasm_bindgen(method)]
+                fn history(this: &Sink) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let s = Sink::new();
+                s.set_level(3);
+                s.assign_level(7);
+                assert_eq!(s.history(), "3,7");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function test() {
+                run();
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Sink {
+                private levels: number[] = [];
+
+                set level(val: number) {
+                    this.levels.push(val);
+                }
+
+                history(): string {
+                    return this.levels.join(",");
+                }
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn instanceof_guard() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                #[wasm_bindgen(instanceof)]
+                type Foo;
+                #[wasm_bindgen(method)]
+                fn value(this: &Foo) -> u32;
+
+                type Bar;
+                #[wasm_bindgen(method, structural)]
+                fn value(this: &Bar) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn foo_value(a: &Foo) -> u32 {
+                a.value()
+            }
+
+            #[wasm_bindgen]
+            pub fn take_foo(a: Foo) -> Foo {
+                a
+            }
+
+            #[wasm_bindgen]
+            pub fn bar_value(a: &Bar) -> u32 {
+                a.value()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import { Foo } from "./another";
+            import * as assert from "assert";
+
+            export function test() {
+                const foo = new Foo();
+                assert.strictEqual(wasm.foo_value(foo), 3);
+                assert.strictEqual(wasm.take_foo(foo), foo);
+                assert.throws(() => wasm.foo_value({ value() { return 3; } }), TypeError);
+                assert.throws(() => wasm.take_foo(3), /expected an instance of `Foo`/);
+
+                assert.strictEqual(wasm.bar_value({ value() { return 4; } }), 4);
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Foo {
+                value() {
+                    return 3;
+                }
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn same_name_from_two_modules() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            pub mod a {
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "./a")]
+                extern {
+                    pub type Foo;
+                }
+            }
+
+            pub mod b {
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "./b")]
+                extern {
+                    pub type Foo;
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn pass_a(a: a::Foo) -> a::Foo {
+                a
+            }
+
+            #[wasm_bindgen]
+            pub fn pass_b(b: b::Foo) -> b::Foo {
+                b
+            }
+        "#)
+        .test_bindgen_error("the type `Foo` is imported from both `./a` and `./b`");
+}
+
+#[test]
+fn extends() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Node;
+                #[wasm_bindgen(method)]
+                fn name(this: &Node) -> String;
+
+                #[wasm_bindgen(extends = Node)]
+                type Element;
+                #[wasm_bindgen(method)]
+                fn tag(this: &Element) -> String;
+
+                #[wasm_bindgen(extends = Element, extends = Node)]
+                type HtmlElement;
+                #[wasm_bindgen(method)]
+                fn html(this: &HtmlElement) -> String;
+            }
+
+            fn node_name(node: &Node) -> String {
+                node.name()
+            }
+
+            #[wasm_bindgen]
+            pub fn describe(el: &HtmlElement) -> String {
+                let as_node: &Node = el.as_ref();
+                assert_eq!(as_node.name(), node_name(el));
+                format!("{} {} {}", el.name(), el.tag(), el.html())
+            }
+
+            #[wasm_bindgen]
+            pub fn upcast(el: HtmlElement) -> Node {
+                el.into()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import { HtmlElement, Node } from "./another";
+            import * as assert from "assert";
+
+            export function test() {
+                const el = new HtmlElement();
+                assert.strictEqual(wasm.describe(el), "node element html");
+                const node = wasm.upcast(el);
+                assert.strictEqual(node, el);
+                assert.ok(node instanceof Node);
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Node {
+                name() {
+                    return "node";
+                }
+            }
+
+            export class Element extends Node {
+                tag() {
+                    return "element";
+                }
+            }
+
+            export class HtmlElement extends Element {
+                html() {
+                    return "html";
+                }
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn static_methods() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                type Date;
+                #[wasm_bindgen(static_method_of = Date)]
+                fn now() -> f64;
+            }
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Counter;
+                #[wasm_bindgen(static_method_of = Counter)]
+                fn bump() -> u32;
+                #[wasm_bindgen(static_method_of = Counter, catch)]
+                fn fail() -> Result<(), JsValue>;
+            }
+
+            #[wasm_bindgen(module = "./another", js_namespace = nested)]
+            extern {
+                type Inner;
+                #[wasm_bindgen(static_method_of = Inner)]
+                fn value() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert!(Date::now() > 0.0);
+                assert_eq!(Counter::bump(), 1);
+                assert_eq!(Counter::bump(), 2);
+                assert!(Counter::fail().is_err());
+                assert_eq!(Inner::value(), 5);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function test() {
+                run();
+            }
+        "#)
+        .file("another.ts", r#"
+            export class Counter {
+                static count: number = 0;
+
+                static bump(): number {
+                    this.count += 1;
+                    return this.count;
+                }
+
+                static fail() {
+                    throw new Error('failed');
+                }
+            }
+
+            class Inner {
+                static inner_value: number = 5;
+
+                static value(): number {
+                    return this.inner_value;
+                }
+            }
+
+            export const nested = { Inner };
+        "#)
+        .test();
+}
+
+#[test]
+fn final_methods() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Counter;
+                #[wasm_bindgen(constructor)]
+                fn new() -> Counter;
+                #[wasm_bindgen(method, final)]
+                fn bump(this: &Counter) -> u32;
+                #[wasm_bindgen(method, getter, final)]
+                fn count(this: &Counter) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let c = Counter::new();
+                assert_eq!(c.bump(), 1);
+                assert_eq!(c.bump(), 2);
+                assert_eq!(c.count(), 2);
+            }
+        "#)
+        .file("another.js", r#"
+            class Counter {
+                constructor() {
+                    this._count = 0;
+                }
+
+                bump() {
+                    this._count += 1;
+                    return this._count;
+                }
+
+                get count() {
+                    return this._count;
+                }
+            }
+
+            module.exports.Counter = Counter;
+        "#)
+        .file("test.js", r#"
+            const { run } = require('./out');
+
+            module.exports.test = function() {
+                run();
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn final_methods_ignore_patching() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Vault;
+                #[wasm_bindgen(method, final)]
+                fn open(this: &Vault, code: u32) -> bool;
+                #[wasm_bindgen(method, final, variadic)]
+                fn sum(this: &Vault, first: u32, rest: &[JsValue]) -> u32;
+                #[wasm_bindgen(method)]
+                fn close(this: &Vault) -> bool;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(v: &Vault) -> bool {
+                assert_eq!(v.sum(1, &[JsValue::from(2), JsValue::from(3)]), 6);
+                v.open(1234)
+            }
+
+            #[wasm_bindgen]
+            pub fn close(v: &Vault) -> bool {
+                v.close()
+            }
+        "#)
+        .file("another.js", r#"
+            class Vault {
+                open(code) {
+                    return code === 1234;
+                }
+
+                sum(...xs) {
+                    return xs.reduce((a, b) => a + b, 0);
+                }
+
+                close() {
+                    return true;
+                }
+            }
+
+            module.exports.Vault = Vault;
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const { Vault } = require('./another');
+            const { run, close } = require('./out');
+
+            module.exports.test = function() {
+                const v = new Vault();
+                const call = Function.prototype.call;
+                const apply = Function.prototype.apply;
+                Vault.prototype.open = () => { throw new Error('intercepted'); };
+                Vault.prototype.sum = () => { throw new Error('intercepted'); };
+                Vault.prototype.close = () => false;
+                Function.prototype.call = () => { throw new Error('intercepted'); };
+                Function.prototype.apply = () => { throw new Error('intercepted'); };
+                let opened, closed;
+                try {
+                    opened = run(v);
+                } finally {
+                    Function.prototype.call = call;
+                    Function.prototype.apply = apply;
+                }
+                assert.strictEqual(opened, true);
+
+                // Other methods are captured when the module is loaded too
+                assert.strictEqual(close(v), true);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn final_methods_missing() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Counter;
+                #[wasm_bindgen(method, final)]
+                fn bmup(this: &Counter) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(c: &Counter) -> u32 {
+                c.bmup()
+            }
+        "#)
+        .file("another.js", r#"
+            class Counter {
+                bump() {
+                    return 1;
+                }
+            }
+
+            module.exports.Counter = Counter;
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+
+            module.exports.test = function() {
+                assert.throws(() => require('./out'),
+                              /method `Counter.bmup` does not exist on the prototype/);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn symbol_keyed_methods() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                type Array;
+                #[wasm_bindgen(method, symbol = iterator)]
+                fn iter(this: &Array) -> Iterator;
+
+                #[wasm_bindgen(structural)]
+                type Iterator;
+                #[wasm_bindgen(method, structural)]
+                fn next(this: &Iterator) -> IteratorResult;
+
+                #[wasm_bindgen(structural)]
+                type IteratorResult;
+                #[wasm_bindgen(method, getter, structural)]
+                fn done(this: &IteratorResult) -> bool;
+                #[wasm_bindgen(method, getter, structural)]
+                fn value(this: &IteratorResult) -> JsValue;
+            }
+
+            #[wasm_bindgen(module = "./another")]
+            extern {
+                type Tagged;
+                #[wasm_bindgen(constructor)]
+                fn new() -> Tagged;
+                #[wasm_bindgen(method, symbol = "wbg.tag")]
+                fn tag(this: &Tagged) -> u32;
+                #[wasm_bindgen(method, structural, symbol = "wbg.tag")]
+                fn tag_structural(this: &Tagged) -> u32;
+                #[wasm_bindgen(static_method_of = Tagged, symbol = "wbg.count")]
+                fn count() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(a: &Array) -> f64 {
+                let iter = a.iter();
+                let mut sum = 0.0;
+                loop {
+                    let result = iter.next();
+                    if result.done() {
+                        break
+                    }
+                    sum += result.value().as_f64().unwrap();
+                }
+                sum
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let t = Tagged::new();
+                assert_eq!(t.tag(), 7);
+                assert_eq!(t.tag_structural(), 7);
+                assert_eq!(Tagged::count(), 1);
+            }
+        "#)
+        .file("another.js", r#"
+            class Tagged {
+                [Symbol.for('wbg.tag')]() {
+                    return 7;
+                }
+
+                static [Symbol.for('wbg.count')]() {
+                    return 1;
+                }
+            }
+
+            module.exports.Tagged = Tagged;
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const { sum, run } = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(sum([1, 2, 3.5]), 6.5);
+                assert.strictEqual(sum([]), 0);
+                run();
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn typescript_type() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(typescript_type = "Promise<string>")]
+                type Promise;
+            }
+
+            #[wasm_bindgen]
+            pub fn pass(p: Promise) -> Promise {
+                p
+            }
+
+            #[wasm_bindgen]
+            pub fn peek(_p: &Promise) {}
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                const p = Promise.resolve('a');
+                assert.strictEqual(wasm.pass(p), p);
+                wasm.peek(p);
+
+                const ts = fs.readFileSync(path.join(__dirname, 'out.d.ts'), 'utf8');
+                assert.ok(ts.includes('pass(p: Promise<string>): Promise<string>;'));
+                assert.ok(ts.includes('peek(_p: Promise<string>): void;'));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn typescript_generics() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(typescript_generics = "K, V", instanceof)]
+                type Map;
+                #[wasm_bindgen(method, getter)]
+                fn size(this: &Map) -> u32;
+
+                #[wasm_bindgen(typescript_generics = "T = Record<string, number>")]
+                type Set;
+            }
+
+            #[wasm_bindgen]
+            pub fn size(m: &Map) -> u32 {
+                m.size()
+            }
+
+            #[wasm_bindgen]
+            pub fn pass(s: Set) -> Set {
+                s
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.size(new Map([[1, 2]])), 1);
+                const s = new Set();
+                assert.strictEqual(wasm.pass(s), s);
+                assert.throws(() => wasm.size({}), /expected an instance of `Map`/);
+
+                const ts = fs.readFileSync(path.join(__dirname, 'out.d.ts'), 'utf8');
+                assert.ok(ts.includes('size(m: Map<any, any>): number;'));
+                assert.ok(ts.includes('pass(s: Set<Record<string, number>>): Set<Record<string, number>>;'));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn data_properties() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                type Point;
+                #[wasm_bindgen(method, property)]
+                fn x(this: &Point) -> f64;
+                #[wasm_bindgen(method, property, js_name = displayName)]
+                fn name(this: &Point) -> String;
+                #[wasm_bindgen(method, property, readonly)]
+                fn id(this: &Point) -> u32;
+                #[wasm_bindgen(method, property)]
+                fn tag(this: &Point) -> JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(p: &Point) -> u32 {
+                assert_eq!(p.x(), 1.5);
+                p.set_x(p.x() * 2.0);
+                p.set_name(format!("{}!", p.name()));
+                assert!(!p.tag().is_null());
+                p.set_tag(JsValue::null());
+                p.id()
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+            import * as assert from "assert";
+
+            export class Point {
+                x: number;
+                displayName: string;
+                id: number;
+                tag: any;
+            }
+
+            export function test() {
+                const p = new Point();
+                p.x = 1.5;
+                p.displayName = 'p';
+                p.id = 7;
+                p.tag = 'tag';
+                assert.strictEqual(run(p), 7);
+                assert.strictEqual(p.tag, null);
+                assert.strictEqual(p.x, 3);
+                assert.strictEqual(p.displayName, 'p!');
+                assert.strictEqual(Object.getOwnPropertyDescriptor(Point.prototype, 'x'), undefined);
+            }
+        "#)
+        .test();
+}
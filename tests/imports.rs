@@ -249,6 +249,285 @@ fn exn_caught() {
         .test();
 }
 
+#[test]
+fn exn_source() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::ExceptionSource;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(catch)]
+                fn throw_type_error() -> Result<(), JsError>;
+                #[wasm_bindgen(catch)]
+                fn throw_string() -> Result<u32, JsError>;
+                #[wasm_bindgen(catch)]
+                fn call_panic() -> Result<(), JsError>;
+            }
+
+            #[wasm_bindgen]
+            pub fn panic() {
+                panic!("boom");
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let e = throw_type_error().unwrap_err();
+                assert_eq!(e.source(), ExceptionSource::Js);
+                assert_eq!(e.name(), Some("TypeError"));
+                assert_eq!(e.message(), Some("bad type"));
+
+                let e = throw_string().unwrap_err();
+                assert_eq!(e.source(), ExceptionSource::Js);
+                assert_eq!(e.name(), None);
+                assert_eq!(e.message(), None);
+                assert_eq!(e.value().as_string(), Some(String::from("oops")));
+
+                let e = call_panic().unwrap_err();
+                assert_eq!(e.source(), ExceptionSource::Panic);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+
+            export function throw_type_error() {
+                throw new TypeError('bad type');
+            }
+
+            export function throw_string() {
+                throw 'oops';
+            }
+
+            export function call_panic() {
+                wasm.panic();
+            }
+
+            export function test() {
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn exn_typed() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[derive(Debug, PartialEq)]
+            pub enum ParseError {
+                Message(String),
+                Other,
+            }
+
+            impl From<JsValue> for ParseError {
+                fn from(v: JsValue) -> ParseError {
+                    match v.as_string() {
+                        Some(s) => ParseError::Message(s),
+                        None => ParseError::Other,
+                    }
+                }
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(catch)]
+                fn parse(s: &str) -> Result<u32, ParseError>;
+                #[wasm_bindgen(catch, js_name = parse)]
+                fn parse_untyped(s: &str) -> Result<u32, JsValue>;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(parse("12"), Ok(12));
+                assert_eq!(parse("x"), Err(ParseError::Message("bad number: x".to_string())));
+                assert_eq!(parse(""), Err(ParseError::Other));
+                assert!(parse_untyped("x").unwrap_err().as_string().is_some());
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+
+            export function parse(s: string): number {
+                if (s.length === 0)
+                    throw new Error('empty');
+                const n = parseInt(s, 10);
+                if (isNaN(n))
+                    throw `bad number: ${s}`;
+                return n;
+            }
+
+            export function test() {
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn unit_returns() {
+    test_support::project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn returns_number_implicit();
+                fn returns_number_unit() -> ();
+                fn returns_nothing() -> JsValue;
+                fn returns_number() -> JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() -> () {
+                returns_number_implicit();
+                let () = returns_number_unit();
+                assert!(returns_nothing().is_undefined());
+                assert_eq!(returns_number().as_f64(), Some(3.0));
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function returns_number_implicit(): number {
+                return 1;
+            }
+
+            export function returns_number_unit(): number {
+                return 2;
+            }
+
+            export function returns_nothing() {
+            }
+
+            export function returns_number(): number {
+                return 3;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.run(), undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn nullable_arguments() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(nullable(b))]
+                fn pick(a: &JsValue, b: JsValue, c: u32) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn pass(b: JsValue) -> u32 {
+                pick(&JsValue::from_str("a"), b, 3)
+            }
+
+            #[wasm_bindgen]
+            pub fn pass_null_a() {
+                pick(&JsValue::null(), JsValue::null(), 3);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function pick(a: any, b: any, c: number): number {
+                assert.strictEqual(a, "a");
+                assert.strictEqual(c, 3);
+                if (b === null)
+                    return 0;
+                assert.strictEqual(b, 2);
+                return 1;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.pass(2), 1);
+                assert.strictEqual(wasm.pass(null), 0);
+                assert.strictEqual(wasm.pass(undefined), 0);
+                assert.throws(wasm.pass_null_a, /argument `a` of `pick` must not be null/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn indexing() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                type Dict;
+                #[wasm_bindgen(method, indexing_getter)]
+                fn get(this: &Dict, key: &str) -> JsValue;
+                #[wasm_bindgen(method, indexing_setter)]
+                fn set(this: &Dict, key: &str, value: u32);
+                #[wasm_bindgen(method, indexing_deleter)]
+                fn delete(this: &Dict, key: &str);
+                #[wasm_bindgen(method, indexing_getter)]
+                fn at(this: &Dict, index: u32) -> JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(d: &Dict) {
+                assert_eq!(d.at(0).as_string(), Some(String::from("zero")));
+                d.set("a", 1);
+                d.set("b", 2);
+                assert_eq!(d.get("a").as_f64(), Some(1.0));
+                d.delete("a");
+                assert!(d.get("a").is_undefined());
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export class Dict {
+                [key: string]: any;
+            }
+
+            export function test() {
+                const d = new Dict();
+                d[0] = "zero";
+                wasm.run(d);
+                assert.deepStrictEqual(Object.keys(d), ["0", "b"]);
+                assert.strictEqual(d.b, 2);
+            }
+        "#)
+        .test();
+}
+
 #[test]
 fn free_imports() {
     test_support::project()
@@ -311,6 +590,101 @@ fn import_a_field() {
         .test();
 }
 
+#[test]
+fn lazy_statics() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(lazy)]
+                static LATE: JsValue;
+            }
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(lazy)]
+                static notDefinedAnywhere: JsValue;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(LATE.as_f64(), Some(3.0));
+                assert!(notDefinedAnywhere.is_undefined());
+            }
+        "#)
+        .file("test.js", r#"
+            const { run } = require('./out');
+
+            module.exports.test = function() {
+                run();
+            };
+
+            // Only defined once `./out` has been loaded, by which point an
+            // eager `require` of this module would have seen nothing yet.
+            module.exports.LATE = 3;
+        "#)
+        .test();
+}
+
+#[test]
+fn default_exports() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(js_name = "default")]
+                fn scale(a: u32) -> u32;
+                #[wasm_bindgen(js_namespace = "default")]
+                fn factor() -> u32;
+                fn named() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(scale(2), 6);
+                assert_eq!(factor(), 3);
+                assert_eq!(named(), 4);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            function scale(a: number): number {
+                return a * scale.factor();
+            }
+
+            namespace scale {
+                export function factor(): number {
+                    return 3;
+                }
+            }
+
+            export default scale;
+
+            export function named(): number {
+                return 4;
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
 #[test]
 fn rename() {
     test_support::project()
@@ -349,3 +723,658 @@ fn rename() {
         "#)
         .test();
 }
+
+#[test]
+fn variadic() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(js_namespace = Math, variadic)]
+                fn max(values: &[JsValue]) -> f64;
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(variadic)]
+                fn join(sep: &str, rest: &[JsValue]) -> String;
+                #[wasm_bindgen(variadic, catch)]
+                fn count_or_throw(rest: &[JsValue]) -> Result<u32, JsValue>;
+            }
+
+            #[wasm_bindgen(module = "./test", js_namespace = tally)]
+            extern {
+                #[wasm_bindgen(variadic)]
+                fn add(first: u32, rest: &[JsValue]) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let nums = [JsValue::from(1), JsValue::from(5), JsValue::from(3)];
+                assert_eq!(max(&nums), 5.0);
+                assert_eq!(max(&[]), -::std::f64::INFINITY);
+
+                let strs = [JsValue::from_str("a"), JsValue::from_str("b")];
+                assert_eq!(join("-", &strs), "a-b");
+                assert_eq!(join("-", &[]), "");
+
+                assert_eq!(count_or_throw(&strs).unwrap(), 2);
+                assert!(count_or_throw(&[]).is_err());
+
+                assert_eq!(add(1, &nums), 10);
+                assert_eq!(add(2, &[]), 12);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function join(sep: string, ...rest: string[]): string {
+                return rest.join(sep);
+            }
+
+            export function count_or_throw(...rest: any[]): number {
+                if (rest.length === 0)
+                    throw new Error('nothing to count');
+                return rest.length;
+            }
+
+            export const tally = {
+                total: 0,
+                add(...xs: number[]): number {
+                    for (const x of xs)
+                        this.total += x;
+                    return this.total;
+                },
+            };
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn namespaced_properties() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test", js_namespace = config)]
+            extern {
+                #[wasm_bindgen(getter)]
+                fn name() -> String;
+                #[wasm_bindgen(setter)]
+                fn set_name(s: &str);
+                #[wasm_bindgen(getter = version)]
+                fn config_version() -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(name(), "a");
+                set_name("b");
+                assert_eq!(name(), "b");
+                assert_eq!(config_version(), 3);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+            import * as assert from "assert";
+
+            export const config = { name: "a", version: 3 };
+
+            export function test() {
+                run();
+                assert.strictEqual(config.name, "b");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn nested_namespaces() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test", js_namespace = "a.b.c")]
+            extern {
+                fn add(a: u32, b: u32) -> u32;
+                #[wasm_bindgen(getter)]
+                fn depth() -> u32;
+            }
+
+            #[wasm_bindgen(module = "./test", js_namespace = "a")]
+            extern {
+                fn one() -> u32;
+            }
+
+            #[wasm_bindgen(module = "./test", js_namespace = ["a", "b"])]
+            extern {
+                fn two() -> u32;
+            }
+
+            #[wasm_bindgen(module = "./test", js_namespace = ["a", "my-lib"])]
+            extern {
+                fn three() -> u32;
+            }
+
+            #[wasm_bindgen(module = "./test", js_namespace = "a.my-lib.v2")]
+            extern {
+                fn four() -> u32;
+            }
+
+            #[wasm_bindgen(js_namespace = "Math")]
+            extern {
+                fn abs(a: f64) -> f64;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(add(1, 2), 3);
+                assert_eq!(depth(), 3);
+                assert_eq!(one(), 1);
+                assert_eq!(two(), 2);
+                assert_eq!(three(), 3);
+                assert_eq!(four(), 4);
+                assert_eq!(abs(-2.0), 2.0);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export const a = {
+                one: () => 1,
+                b: {
+                    two: () => 2,
+                    c: {
+                        add: (a: number, b: number) => a + b,
+                        depth: 3,
+                    },
+                },
+                "my-lib": {
+                    three: () => 3,
+                    v2: {
+                        four: () => 4,
+                    },
+                },
+            };
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn iterators() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(iterator)]
+                type Numbers;
+
+                fn numbers(n: u32) -> Numbers;
+            }
+
+            fn total(it: Numbers) -> f64 {
+                it.into_iter().map(|v| v.as_f64().unwrap()).sum()
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(n: u32) -> f64 {
+                total(numbers(n))
+            }
+
+            #[wasm_bindgen]
+            pub fn sum_given(it: Numbers) -> f64 {
+                total(it)
+            }
+
+            #[wasm_bindgen]
+            pub fn first(n: u32) -> f64 {
+                let mut it = numbers(n).into_iter();
+                it.next().unwrap().as_f64().unwrap()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { sum, sum_given, first } from "./out";
+
+            let returned = 0;
+
+            export function numbers(n: number): any {
+                let i = 0;
+                return {
+                    next() {
+                        i += 1;
+                        return i > n ? { done: true } : { done: false, value: i };
+                    },
+                    return() {
+                        returned += 1;
+                        return { done: true };
+                    },
+                };
+            }
+
+            function* gen() {
+                yield 5;
+                yield 6;
+            }
+
+            export function test() {
+                assert.strictEqual(sum(4), 10);
+                assert.strictEqual(sum(0), 0);
+                assert.strictEqual(returned, 0);
+
+                assert.strictEqual(first(3), 1);
+                assert.strictEqual(returned, 1);
+
+                assert.strictEqual(sum_given(gen()), 11);
+                assert.strictEqual(sum_given(numbers(2)), 3);
+                assert.throws(() => sum_given({} as any), /expected an iterator/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn reentrancy() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn call_back(depth: u32, s: &str) -> String;
+                fn call_back_i64(depth: u32) -> i64;
+            }
+
+            #[wasm_bindgen]
+            pub fn reenter(depth: u32, s: &str) -> String {
+                if depth == 0 {
+                    return format!("[{}]", s)
+                }
+                let inner = call_back(depth - 1, &format!("{}{}", s, depth));
+                format!("{}-{}", inner, s)
+            }
+
+            #[wasm_bindgen]
+            pub fn reenter_i64(depth: u32) -> i64 {
+                if depth == 0 {
+                    return -(1 << 40)
+                }
+                call_back_i64(depth - 1) * 2 + depth as i64
+            }
+
+            #[wasm_bindgen]
+            pub fn concat(a: &str, b: &str) -> String {
+                format!("{}{}", a, b)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function call_back(depth: number, s: string): string {
+                const ret = wasm.reenter(depth, s);
+                // Calls made before returning to Rust mustn't disturb it
+                assert.strictEqual(wasm.reenter(0, "other"), "[other]");
+                return ret + "!";
+            }
+
+            export function call_back_i64(depth: number): bigint {
+                const ret = wasm.reenter_i64(depth);
+                assert.strictEqual(wasm.reenter_i64(0), -(BigInt(1) << BigInt(40)));
+                return ret;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.reenter(2, "a"), "[a21]!-a2!-a");
+                const base = -(BigInt(1) << BigInt(40));
+                assert.strictEqual(wasm.reenter_i64(2), (base * BigInt(2) + BigInt(1)) * BigInt(2) + BigInt(2));
+
+                // Converting the second argument calls into Rust after the
+                // first one has been converted
+                const sneaky: any = {
+                    toString() {
+                        assert.strictEqual(wasm.reenter(1, "x"), "[x1]!-x");
+                        return "cd";
+                    },
+                };
+                assert.strictEqual(wasm.concat("ab", sneaky), "abcd");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn late_bound() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(late_bound)]
+                fn polyfilled(a: u32) -> u32;
+                #[wasm_bindgen(js_namespace = lateNamespace, late_bound)]
+                fn shout(a: &str) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn call_polyfilled(a: u32) -> u32 {
+                polyfilled(a)
+            }
+
+            #[wasm_bindgen]
+            pub fn call_shout(a: &str) -> String {
+                shout(a)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const g = global as any;
+                assert.throws(() => wasm.call_polyfilled(1), /`polyfilled` is not a function/);
+                g.polyfilled = (a: number) => a + 1;
+                assert.strictEqual(wasm.call_polyfilled(1), 2);
+                g.polyfilled = (a: number) => a * 10;
+                assert.strictEqual(wasm.call_polyfilled(2), 20);
+                delete g.polyfilled;
+
+                assert.throws(() => wasm.call_shout("a"), /`lateNamespace.shout` is not a function/);
+                g.lateNamespace = {};
+                assert.throws(() => wasm.call_shout("a"), /`lateNamespace.shout` is not a function/);
+                // Called as a method of its namespace
+                g.lateNamespace = {
+                    suffix: "!",
+                    shout(a: string) { return a.toUpperCase() + this.suffix; },
+                };
+                assert.strictEqual(wasm.call_shout("a"), "A!");
+                delete g.lateNamespace;
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn overloads() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(js_name = arity)]
+                fn arity0() -> u32;
+                #[wasm_bindgen(js_name = arity)]
+                fn arity2(a: u32, b: &str) -> u32;
+
+                type Target;
+                #[wasm_bindgen(constructor)]
+                fn new() -> Target;
+                #[wasm_bindgen(method)]
+                fn listen(this: &Target, ty: &str) -> u32;
+                #[wasm_bindgen(method, js_name = listen)]
+                fn listen_with(this: &Target, ty: &str, capture: bool) -> u32;
+
+                type Cat;
+                type Dog;
+                #[wasm_bindgen(js_namespace = Cat)]
+                fn speak() -> String;
+                #[wasm_bindgen(js_namespace = Dog)]
+                fn speak() -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(arity0(), 0);
+                assert_eq!(arity2(1, "a"), 2);
+
+                let t = Target::new();
+                assert_eq!(t.listen("click"), 1);
+                assert_eq!(t.listen_with("click", true), 2);
+
+                assert_eq!(Cat::speak(), "meow");
+                assert_eq!(Dog::speak(), "woof");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function arity(): number {
+                return arguments.length;
+            }
+
+            export class Target {
+                listen(): number {
+                    return arguments.length;
+                }
+            }
+
+            export class Cat {
+                static speak(): string {
+                    return 'meow';
+                }
+            }
+
+            export class Dog {
+                static speak(): string {
+                    return 'woof';
+                }
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn pure_imports() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(pure)]
+                fn double(a: u32) -> u32;
+                fn record(a: u32);
+            }
+
+            #[wasm_bindgen]
+            pub fn run() -> u32 {
+                record(1);
+                double(21)
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            let recorded = [];
+            module.exports.double = a => a * 2;
+            module.exports.record = a => recorded.push(a);
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.run(), 42);
+                assert.deepStrictEqual(recorded, [1]);
+
+                // Only the call to the pure import is annotated
+                const js = fs.readFileSync(path.join(__dirname, 'out.js'), 'utf8');
+                assert.strictEqual(js.split('/*#__PURE__*/').length, 2);
+                assert.ok(/\/\*#__PURE__\*\/\S*double\(/.test(js));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn this_arg() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(this_arg)]
+                fn receiver(this: &JsValue, a: u32) -> JsValue;
+                #[wasm_bindgen(this_arg)]
+                fn owned_receiver(this: JsValue) -> JsValue;
+                #[wasm_bindgen(this_arg, variadic)]
+                fn count(this: &JsValue, rest: &[JsValue]) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(this: &JsValue) -> JsValue {
+                assert!(receiver(&JsValue::null(), 1).is_null());
+                assert!(receiver(&JsValue::undefined(), 2).is_undefined());
+                assert!(owned_receiver(JsValue::null()).is_null());
+                assert_eq!(count(this, &[JsValue::from(1), JsValue::from(2), JsValue::from(3)]), 3);
+                receiver(this, 0)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { run } from "./out";
+
+            let args: number[] = [];
+
+            export function receiver(this: any, a: number): any {
+                args.push(a);
+                return this;
+            }
+
+            export function owned_receiver(this: any): any {
+                return this;
+            }
+
+            export function count(this: any, ...rest: number[]): number {
+                assert.strictEqual(this.name, "receiver");
+                return rest.length;
+            }
+
+            export function test() {
+                const obj = { name: "receiver" };
+                assert.strictEqual(run(obj), obj);
+                assert.deepStrictEqual(args, [1, 2, 0]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn exn_raw_and_converted() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(catch, js_name = fail)]
+                fn fail_raw(what: u32) -> Result<u32, JsValue>;
+                #[wasm_bindgen(catch, js_name = fail)]
+                fn fail_converted(what: u32) -> Result<u32, JsError>;
+            }
+
+            #[wasm_bindgen]
+            pub fn raw(what: u32) -> JsValue {
+                fail_raw(what).unwrap_err()
+            }
+
+            #[wasm_bindgen]
+            pub fn converted(what: u32) -> String {
+                let e = fail_converted(what).unwrap_err();
+                format!("{:?} {:?}", e.name(), e.message())
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(fail_raw(3).ok(), Some(3));
+                assert_eq!(fail_converted(3).ok(), Some(3));
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export const thrown: any[] = [
+                new RangeError('too far'),
+                { name: 'NotAnError', message: 'plain object' },
+                'a string',
+            ];
+
+            export function fail(what: number): number {
+                if (what < thrown.length)
+                    throw thrown[what];
+                return what;
+            }
+
+            export function test() {
+                // The raw `JsValue` is the very value which was thrown
+                for (let i = 0; i < thrown.length; i++)
+                    assert.strictEqual(wasm.raw(i), thrown[i]);
+
+                assert.strictEqual(wasm.converted(0), 'Some("RangeError") Some("too far")');
+                assert.strictEqual(wasm.converted(2), 'None None');
+                wasm.run();
+            }
+        "#)
+        .test();
+}
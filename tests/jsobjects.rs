@@ -210,3 +210,183 @@ fn returning_vector() {
         "#)
         .test();
 }
+
+#[test]
+fn returned_vectors_are_arrays() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn take(values: Vec<JsValue>) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn values(n: u32) -> Vec<JsValue> {
+                (0..n).map(|i| JsValue::from(i * 2)).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn strings() -> Vec<String> {
+                vec!["a".to_string(), "b".to_string()]
+            }
+
+            #[wasm_bindgen]
+            pub fn numbers() -> Vec<u32> {
+                vec![1, 2, 3]
+            }
+
+            #[wasm_bindgen]
+            pub fn pass_to_js() -> u32 {
+                take(vec![JsValue::from(1u32), JsValue::from("x")])
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function take(values: any[]): number {
+                assert.ok(Array.isArray(values));
+                assert.deepStrictEqual(values, [1, "x"]);
+                return values.length;
+            }
+
+            export function test() {
+                for (let i = 0; i < 100; i++) {
+                    const values = wasm.values(5);
+                    assert.ok(Array.isArray(values));
+                    assert.deepStrictEqual(values.map((v: number) => v + 1), [1, 3, 5, 7, 9]);
+                    assert.deepStrictEqual(values.filter((v: number) => v > 4), [6, 8]);
+                }
+
+                const strings = wasm.strings();
+                assert.ok(Array.isArray(strings));
+                assert.strictEqual(strings.join(""), "ab");
+
+                // Numeric vectors are still typed arrays
+                const numbers = wasm.numbers();
+                assert.ok(numbers instanceof Uint32Array);
+                assert.deepStrictEqual(Array.from(numbers), [1, 2, 3]);
+
+                assert.strictEqual(wasm.pass_to_js(), 2);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn clones_keep_values_alive() {
+    test_support::project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn clone_and_drop(s: JsValue) -> JsValue {
+                let a = s.clone();
+                let b = a.clone();
+                drop(s);
+                drop(a);
+                b
+            }
+
+            #[wasm_bindgen]
+            pub struct Holder {
+                value: JsValue,
+            }
+
+            #[wasm_bindgen]
+            impl Holder {
+                pub fn new(value: &JsValue) -> Holder {
+                    Holder { value: value.clone() }
+                }
+
+                pub fn get(&self) -> JsValue {
+                    self.value.clone()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                const a = { "foo": "bar" };
+                assert.strictEqual(wasm.clone_and_drop(a), a);
+
+                const b = { "bar": "baz" };
+                const holder = wasm.Holder.new(b);
+                assert.strictEqual(holder.get(), b);
+                assert.strictEqual(wasm.clone_and_drop(holder.get()), b);
+                assert.strictEqual(holder.get(), b);
+                holder.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrows_keep_ownership() {
+    test_support::project()
+        .debug(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn look(s: &JsValue) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn look_thrice(s: JsValue) -> JsValue {
+                assert_eq!(look(&s), 1);
+                assert_eq!(look(&s), 2);
+                assert_eq!(look(&s), 3);
+                s
+            }
+
+            #[wasm_bindgen]
+            pub fn peek(s: &JsValue) -> bool {
+                !s.is_null()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            let SEEN: any = null;
+            let COUNT = 0;
+
+            export function look(s: any): number {
+                if (SEEN !== null)
+                    assert.strictEqual(s, SEEN);
+                SEEN = s;
+                COUNT += 1;
+                return COUNT;
+            }
+
+            export function test() {
+                const a = { "foo": "bar" };
+                assert.strictEqual(wasm.look_thrice(a), a);
+
+                // The caller still owns what it lends to Rust
+                for (let i = 0; i < 3; i++)
+                    assert.strictEqual(wasm.peek(a), true);
+                assert.strictEqual(a.foo, "bar");
+            }
+        "#)
+        .test();
+}
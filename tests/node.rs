@@ -152,3 +152,453 @@ fn works() {
         "#)
         .test();
 }
+
+#[test]
+fn same_conversions_as_es_modules() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn shout(s: &str) -> String;
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str) -> String {
+                shout(&format!("hello {}", name))
+            }
+
+            #[wasm_bindgen]
+            pub fn words(s: &str) -> Vec<String> {
+                s.split(' ').map(|s| s.to_string()).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn doubled(a: &[f64]) -> Vec<f64> {
+                a.iter().map(|x| x * 2.0).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn fill(a: &mut [u8], val: u8) {
+                for x in a.iter_mut() {
+                    *x = val;
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn half(a: u32) -> Option<u32> {
+                if a % 2 == 0 { Some(a / 2) } else { None }
+            }
+
+            #[wasm_bindgen(js_class = OldPoint)]
+            pub struct Point {
+                pub x: u32,
+                #[wasm_bindgen(readonly)]
+                pub y: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Point {
+                pub fn new(x: u32, y: u32) -> Point {
+                    Point { x, y }
+                }
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+
+            module.exports.shout = function(s) {
+                return s.toUpperCase();
+            };
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.greet('node'), 'HELLO NODE');
+                assert.deepStrictEqual(wasm.words('a bc d'), ['a', 'bc', 'd']);
+                assert.deepStrictEqual(Array.from(wasm.doubled(new Float64Array([1, 2.5]))), [2, 5]);
+
+                const a = new Uint8Array(3);
+                wasm.fill(a, 7);
+                assert.deepStrictEqual(Array.from(a), [7, 7, 7]);
+
+                assert.strictEqual(wasm.half(4), 2);
+                assert.strictEqual(wasm.half(3), undefined);
+
+                assert.strictEqual(wasm.OldPoint, wasm.Point);
+                const p = wasm.OldPoint.new(1, 2);
+                assert.ok(p instanceof wasm.Point);
+                p.x = 5;
+                assert.strictEqual(p.x, 5);
+                assert.strictEqual(p.y, 2);
+                p.free();
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn verbose_shims() {
+    test_support::project()
+        .node(true)
+        .verbose_shims(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn hit();
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                hit();
+            }
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo {}
+                }
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            let HIT = false;
+            module.exports.hit = function() {
+                HIT = true;
+            };
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                wasm.run();
+                assert.strictEqual(HIT, true);
+                assert.strictEqual(wasm.run.name, 'run');
+
+                const js = fs.readFileSync(path.join(__dirname, 'out.js'), 'utf8');
+                assert.ok(js.includes('// Wrapper for the exported Rust function `run`'));
+                assert.ok(js.includes('// Wrapper for the exported Rust method `Foo::new`'));
+                assert.ok(js.includes('// Shim for the imported function `hit` from `./test`'));
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn emit_descriptors() {
+    test_support::project()
+        .node(true)
+        .emit_descriptors(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn hit(s: &str);
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            module.exports.hit = function() {};
+
+            module.exports.test = function() {
+                const text = fs.readFileSync(path.join(__dirname, 'out_descriptors.json'), 'utf8');
+                const programs = JSON.parse(text);
+                assert.strictEqual(programs.length, 2);
+                const exports = [].concat(...programs.map(p => p.exports));
+                assert.deepStrictEqual(exports.map(e => e.function.name), ['add']);
+                const imports = [].concat(...programs.map(p => p.imports));
+                assert.strictEqual(imports[0].module, './test');
+
+                // Each program is exactly what the macro embedded in the wasm
+                const wasm = fs.readFileSync(path.join(__dirname, 'out.wasm'));
+                const raw = text.slice('[\n'.length, -'\n]\n'.length).split(',\n');
+                assert.strictEqual(raw.length, programs.length);
+                for (const p of raw)
+                    assert.notStrictEqual(wasm.indexOf(Buffer.from(p)), -1);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn emit_api_schema() {
+    test_support::project()
+        .node(true)
+        .emit_api_schema(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str, times: Option<u8>) -> String {
+                name.repeat(times.unwrap_or(1) as usize)
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                pub count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                #[wasm_bindgen(constructor)]
+                pub fn new() -> Counter {
+                    Counter { count: 0 }
+                }
+
+                pub fn bump(&mut self, by: u32) -> Result<u32, JsValue> {
+                    self.count += by;
+                    Ok(self.count)
+                }
+
+                pub fn merge(a: &Counter, b: &Counter) -> Counter {
+                    Counter { count: a.count + b.count }
+                }
+            }
+
+            #[wasm_bindgen]
+            pub enum Color { Green, Red = 3 }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                type Logger;
+                #[wasm_bindgen(method)]
+                fn log(this: &Logger, msg: &str);
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            module.exports.test = function() {
+                const text = fs.readFileSync(path.join(__dirname, 'out_api.json'), 'utf8');
+                const api = JSON.parse(text);
+                assert.strictEqual(api.api_schema_version, 1);
+
+                assert.deepStrictEqual(api.functions, [{
+                    name: 'greet',
+                    arguments: [
+                        { name: 'name', type: '&str' },
+                        { name: 'times', type: 'Option<u8>' },
+                    ],
+                    returns: 'String',
+                    throws: null,
+                    async: false,
+                }]);
+
+                assert.strictEqual(api.classes.length, 1);
+                const counter = api.classes[0];
+                assert.strictEqual(counter.name, 'Counter');
+                assert.deepStrictEqual(counter.fields,
+                                       [{ name: 'count', type: 'u32', readonly: false }]);
+                assert.strictEqual(counter.constructor.name, 'new');
+                assert.strictEqual(counter.constructor.returns, 'Counter');
+                const [bump, merge] = counter.methods;
+                assert.strictEqual(bump.static, false);
+                assert.strictEqual(bump.returns, 'u32');
+                assert.strictEqual(bump.throws, 'JsValue');
+                assert.strictEqual(merge.static, true);
+                assert.deepStrictEqual(merge.arguments.map(a => a.type), ['&Counter', '&Counter']);
+
+                assert.deepStrictEqual(api.enums, [{
+                    name: 'Color',
+                    variants: [{ name: 'Green', value: 0 }, { name: 'Red', value: 3 }],
+                }]);
+
+                const [logger, log] = api.imports;
+                assert.strictEqual(logger.kind, 'type');
+                assert.strictEqual(logger.name, 'Logger');
+                assert.strictEqual(log.kind, 'function');
+                assert.strictEqual(log.module, './test');
+                assert.strictEqual(log.class, 'Logger');
+                assert.strictEqual(log.method, true);
+                assert.deepStrictEqual(log.arguments.map(a => a.type), ['&Logger', '&str']);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn reproducible_descriptors() {
+    let build = || {
+        test_support::project()
+            .file("src/lib.rs", r#"
+                #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+                extern crate wasm_bindgen;
+
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub struct Zebra {}
+
+                #[wasm_bindgen]
+                pub struct Aardvark {}
+
+                #[wasm_bindgen]
+                impl Zebra {
+                    pub fn new() -> Zebra { Zebra {} }
+                    pub fn befriend(&self, _a: &Aardvark) {}
+                }
+
+                #[wasm_bindgen]
+                impl Aardvark {
+                    pub fn new() -> Aardvark { Aardvark {} }
+                }
+
+                #[wasm_bindgen]
+                pub enum Color { Green, Yellow = 3, Red }
+
+                #[wasm_bindgen(module = "./test")]
+                extern {
+                    type Mole;
+                    fn dig(m: &Mole, depth: u32) -> Mole;
+                    #[wasm_bindgen(method, property)]
+                    fn depth(this: &Mole) -> u32;
+                }
+            "#)
+            .descriptors()
+    };
+    let first = build();
+    let second = build();
+    assert!(first == second, "descriptors differ between builds:\n{}\n{}",
+            String::from_utf8_lossy(&first), String::from_utf8_lossy(&second));
+}
+
+#[test]
+fn descriptors_stripped_from_wasm() {
+    test_support::project()
+        .node(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_custom_section = "extra"]
+            pub const EXTRA: [u8; 3] = *b"abc";
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.add(1, 2), 3);
+
+                const bytes = fs.readFileSync(path.join(__dirname, 'out_bg.wasm'));
+                const mod = new WebAssembly.Module(bytes);
+                const sections = name => WebAssembly.Module.customSections(mod, name);
+                assert.strictEqual(sections('__wasm_bindgen_unstable').length, 0);
+                const extra = sections('extra');
+                assert.strictEqual(extra.length, 1);
+                assert.deepStrictEqual(Array.from(new Uint8Array(extra[0])), [97, 98, 99]);
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn env_imports() {
+    test_support::project()
+        .node(true)
+        .env_imports(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn double(a: f64) -> f64;
+            }
+
+            #[wasm_bindgen]
+            pub fn run(s: &str, b: f64) -> String {
+                format!("{} {} {}", s, double(b), b.cosh())
+            }
+        "#)
+        .file("test.js", r#"
+            const assert = require('assert');
+            const fs = require('fs');
+            const path = require('path');
+
+            module.exports.double = a => a * 2;
+
+            const wasm = require('./out');
+
+            module.exports.test = function() {
+                assert.strictEqual(wasm.run('a', 0), 'a 0 1');
+
+                const bytes = fs.readFileSync(path.join(__dirname, 'out_bg.wasm'));
+                const mod = new WebAssembly.Module(bytes);
+                const imports = WebAssembly.Module.imports(mod);
+                assert.ok(imports.length > 0);
+                for (const i of imports)
+                    assert.strictEqual(i.module, 'env');
+            };
+        "#)
+        .test();
+}
+
+#[test]
+fn env_imports_need_a_loader() {
+    test_support::project()
+        .env_imports(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn hello() -> u32 {
+                1
+            }
+        "#)
+        .test_bindgen_error("`--env-imports` needs `--nodejs` or `--deno`");
+}
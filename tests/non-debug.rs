@@ -26,6 +26,11 @@ fn works() {
                 drop(a.clone());
                 a.clone()
             }
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
         "#)
         .file("test.ts", r#"
             import * as assert from "assert";
@@ -36,6 +41,9 @@ fn works() {
                 assert.strictEqual(wasm.clone(sym), sym);
                 let a = wasm.A.new();
                 a.free();
+
+                // Arguments aren't checked without `--debug`
+                assert.strictEqual((wasm.add as any)("1", true), 2);
             }
         "#)
         .test();
@@ -0,0 +1,156 @@
+extern crate test_support;
+
+#[test]
+fn numbers_and_booleans() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add_one(a: Option<u32>) -> Option<u32> {
+                a.map(|a| a + 1)
+            }
+
+            #[wasm_bindgen]
+            pub fn halve(a: Option<f64>) -> Option<f64> {
+                a.map(|a| a / 2.0)
+            }
+
+            #[wasm_bindgen]
+            pub fn negate(a: Option<bool>) -> Option<bool> {
+                a.map(|a| !a)
+            }
+
+            #[wasm_bindgen]
+            pub fn add(a: Option<u32>, b: Option<u32>) -> u32 {
+                a.unwrap_or(0) + b.unwrap_or(0)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.add_one(1), 2);
+                assert.strictEqual(wasm.add_one(0), 1);
+                assert.strictEqual(wasm.add_one(undefined), undefined);
+                assert.strictEqual(wasm.add_one(null), undefined);
+
+                assert.strictEqual(wasm.halve(3), 1.5);
+                assert.strictEqual(wasm.halve(undefined), undefined);
+
+                assert.strictEqual(wasm.negate(true), false);
+                assert.strictEqual(wasm.negate(false), true);
+                assert.strictEqual(wasm.negate(null), undefined);
+
+                assert.strictEqual(wasm.add(1, 2), 3);
+                assert.strictEqual(wasm.add(null, 2), 2);
+                assert.strictEqual(wasm.add(1, undefined), 1);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn nested() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn roundtrip(a: Option<Option<u32>>, b: Option<u32>) -> Option<Option<u32>> {
+                assert_eq!(b, Some(3));
+                a
+            }
+
+            #[wasm_bindgen]
+            pub fn some_none() -> Option<Option<u32>> {
+                Some(None)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.roundtrip(1, 3), 1);
+                assert.strictEqual(wasm.roundtrip(null, 3), null);
+                assert.strictEqual(wasm.roundtrip(undefined, 3), undefined);
+                assert.strictEqual(wasm.some_none(), null);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn strings() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn shout(a: Option<&str>) -> Option<String> {
+                a.map(|a| a.to_uppercase())
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(name: Option<String>, greeting: Option<&str>) -> String {
+                format!("{}, {}!",
+                        greeting.unwrap_or("hello"),
+                        name.unwrap_or(String::from("world")))
+            }
+
+            #[wasm_bindgen]
+            pub fn name_of(n: u32) -> Option<&'static str> {
+                ["zero", "one", ""].get(n as usize).cloned()
+            }
+
+            #[wasm_bindgen]
+            pub fn non_empty(a: &str) -> Option<String> {
+                if a.is_empty() {
+                    None
+                } else {
+                    Some(a.to_string())
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.shout("hi"), "HI");
+                assert.strictEqual(wasm.shout(""), "");
+                assert.strictEqual(wasm.shout(null), null);
+                assert.strictEqual(wasm.shout(undefined), null);
+
+                assert.strictEqual(wasm.greet(null, null), "hello, world!");
+                assert.strictEqual(wasm.greet("you", undefined), "hello, you!");
+                assert.strictEqual(wasm.greet(null, "hey"), "hey, world!");
+                assert.strictEqual(wasm.greet("ünïcödé", "hi"), "hi, ünïcödé!");
+
+                // Each call hands JS a copy of the static string to free
+                for (let i = 0; i < 100; i++) {
+                    assert.strictEqual(wasm.name_of(1), "one");
+                    assert.strictEqual(wasm.name_of(2), "");
+                    assert.strictEqual(wasm.name_of(3), null);
+                }
+
+                assert.strictEqual(wasm.non_empty("a"), "a");
+                assert.strictEqual(wasm.non_empty(""), null);
+            }
+        "#)
+        .test();
+}
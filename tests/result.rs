@@ -0,0 +1,198 @@
+extern crate test_support;
+
+#[test]
+fn works() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn half(a: u32) -> Result<u32, JsValue> {
+                if a % 2 == 0 {
+                    Ok(a / 2)
+                } else {
+                    Err(JsValue::from_str("odd"))
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn check(a: bool) -> Result<(), JsValue> {
+                if a {
+                    Ok(())
+                } else {
+                    Err(JsValue::from(3u32))
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn greet(a: &str) -> Result<String, String> {
+                if a.len() > 0 {
+                    Ok(format!("hello {}", a))
+                } else {
+                    Err(format!("no name"))
+                }
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                value: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                pub fn new() -> Counter {
+                    Counter { value: 0 }
+                }
+
+                pub fn bump(&mut self, max: u32) -> Result<u32, JsValue> {
+                    if self.value == max {
+                        return Err(JsValue::from_str("too big"))
+                    }
+                    self.value += 1;
+                    Ok(self.value)
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            function thrown(f: () => void): any {
+                try {
+                    f();
+                } catch (e) {
+                    return e;
+                }
+                throw new Error("expected an exception");
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.half(4), 2);
+                assert.strictEqual(thrown(() => wasm.half(3)), "odd");
+
+                assert.strictEqual(wasm.check(true), undefined);
+                assert.strictEqual(thrown(() => wasm.check(false)), 3);
+
+                assert.strictEqual(wasm.greet("foo"), "hello foo");
+                assert.strictEqual(thrown(() => wasm.greet("")), "no name");
+                assert.strictEqual(wasm.greet("bar"), "hello bar");
+
+                const c = wasm.Counter.new();
+                assert.strictEqual(c.bump(2), 1);
+                assert.strictEqual(c.bump(2), 2);
+                assert.strictEqual(thrown(() => c.bump(2)), "too big");
+                c.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn catch_panics() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(catch)]
+            pub fn divide(a: u32, b: u32) -> Result<u32, JsValue> {
+                if a == 0 {
+                    return Err(JsValue::from_str("zero"))
+                }
+                if b == 0 {
+                    panic!("division by zero");
+                }
+                Ok(a / b)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            function thrown(f: () => void): any {
+                try {
+                    f();
+                } catch (e) {
+                    return e;
+                }
+                throw new Error("expected an exception");
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.divide(6, 3), 2);
+                assert.strictEqual(thrown(() => wasm.divide(0, 3)), "zero");
+
+                const panic = thrown(() => wasm.divide(1, 0));
+                assert.strictEqual(panic instanceof Error, true);
+                assert.strictEqual(panic.name, "RustPanic");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn unit_results() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(catch)]
+            pub fn validate(n: u32) -> Result<(), String> {
+                if n >= 100 {
+                    panic!("way too big");
+                }
+                if n >= 10 {
+                    return Err(format!("{} is too big", n))
+                }
+                Ok(())
+            }
+
+            #[wasm_bindgen]
+            pub fn validate_uncaught(n: u32) -> Result<(), String> {
+                if n >= 100 {
+                    panic!("way too big");
+                }
+                Ok(())
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            function thrown(f: () => void): any {
+                try {
+                    f();
+                } catch (e) {
+                    return e;
+                }
+                throw new Error("expected an exception");
+            }
+
+            export function test() {
+                const ok: void = wasm.validate(3);
+                assert.strictEqual(ok, undefined);
+                assert.strictEqual(thrown(() => wasm.validate(42)), "42 is too big");
+
+                // A panic isn't an `Err`, it's thrown as an `Error` of its own
+                const panic = thrown(() => wasm.validate(100));
+                assert.strictEqual(panic instanceof Error, true);
+                assert.strictEqual(panic.name, "RustPanic");
+
+                // and without `catch` it's a trap
+                assert.strictEqual(wasm.validate_uncaught(1), undefined);
+                assert.strictEqual(thrown(() => wasm.validate_uncaught(100)).name, "RuntimeError");
+            }
+        "#)
+        .test();
+}
@@ -53,6 +53,437 @@ fn add() {
         .test();
 }
 
+#[test]
+fn floats() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn nan() -> f64 {
+                ::std::f64::NAN
+            }
+
+            #[wasm_bindgen]
+            pub fn negative_zero() -> f64 {
+                -0.0
+            }
+
+            #[wasm_bindgen]
+            pub fn negative_zero_f32() -> f32 {
+                -0.0
+            }
+
+            #[wasm_bindgen]
+            pub fn is_negative_zero(a: f64) -> bool {
+                a == 0.0 && a.is_sign_negative()
+            }
+
+            #[wasm_bindgen]
+            pub fn add(a: f32, b: f64) -> f64 {
+                a as f64 + b
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(Number.isNaN(wasm.nan()), true);
+                assert.strictEqual(Object.is(wasm.negative_zero(), -0), true);
+                assert.strictEqual(Object.is(wasm.negative_zero_f32(), -0), true);
+                assert.strictEqual(wasm.is_negative_zero(-0), true);
+                assert.strictEqual(wasm.is_negative_zero(0), false);
+                assert.strictEqual(wasm.add(1.5, 2.25), 3.75);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn durations() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::time::Duration;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn twice(a: Duration) -> Duration;
+            }
+
+            #[wasm_bindgen]
+            pub fn micros(a: Duration) -> u32 {
+                a.as_secs() as u32 * 1_000_000 + a.subsec_nanos() / 1000
+            }
+
+            #[wasm_bindgen]
+            pub fn from_secs(a: u32) -> Duration {
+                Duration::from_secs(a as u64)
+            }
+
+            #[wasm_bindgen]
+            pub fn is_max(a: Duration) -> bool {
+                a == Duration::new(u64::max_value(), 999_999_999)
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(twice(Duration::new(1, 250_000)), Duration::new(2, 500_000));
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function twice(a: number): number {
+                assert.strictEqual(a, 1000.25);
+                return a * 2;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.micros(1.5), 1500);
+                assert.strictEqual(wasm.micros(2500.001), 2500001);
+                assert.strictEqual(wasm.micros(-3), 0);
+                assert.strictEqual(wasm.micros(NaN), 0);
+                assert.strictEqual(wasm.from_secs(3), 3000);
+                assert.strictEqual(wasm.is_max(Infinity), true);
+                assert.strictEqual(wasm.is_max(1e30), true);
+                assert.strictEqual(wasm.is_max(1e3), false);
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn unsigned_integers() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn echo_u32(a: u32) -> u32;
+                fn echo_usize(a: usize) -> usize;
+            }
+
+            #[wasm_bindgen]
+            pub fn max_u32() -> u32 {
+                u32::max_value()
+            }
+
+            #[wasm_bindgen]
+            pub fn max_usize() -> usize {
+                usize::max_value()
+            }
+
+            #[wasm_bindgen]
+            pub fn min_isize() -> isize {
+                isize::min_value()
+            }
+
+            #[wasm_bindgen]
+            pub fn incr_usize(a: usize) -> usize {
+                a + 1
+            }
+
+            #[wasm_bindgen]
+            pub fn half_u32(a: u32) -> Option<u32> {
+                if a % 2 == 0 { Some(a / 2 + (1 << 31)) } else { None }
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(echo_u32(u32::max_value()), u32::max_value());
+                assert_eq!(echo_usize(usize::max_value() - 1), usize::max_value() - 1);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function echo_u32(a: number): number {
+                assert.strictEqual(a, 4294967295);
+                return a;
+            }
+
+            export function echo_usize(a: number): number {
+                assert.strictEqual(a, 4294967294);
+                return a;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.max_u32(), 4294967295);
+                assert.strictEqual(wasm.max_usize(), 4294967295);
+                assert.strictEqual(wasm.min_isize(), -2147483648);
+                assert.strictEqual(wasm.incr_usize(4294967294), 4294967295);
+                assert.strictEqual(wasm.half_u32(4), 2147483650);
+                assert.strictEqual(wasm.half_u32(3), undefined);
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn non_zero_integers() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn zero() -> NonZeroU32;
+                fn seven() -> NonZeroU32;
+            }
+
+            #[wasm_bindgen]
+            pub fn next(a: NonZeroU32) -> NonZeroU32 {
+                NonZeroU32::new(a.get() + 1).unwrap()
+            }
+
+            #[wasm_bindgen]
+            pub fn next64(a: NonZeroU64) -> NonZeroU64 {
+                NonZeroU64::new(a.get() + 1).unwrap()
+            }
+
+            #[wasm_bindgen]
+            pub fn len(a: NonZeroUsize) -> usize {
+                a.get()
+            }
+
+            #[wasm_bindgen]
+            pub fn maybe(a: Option<NonZeroU32>) -> Option<NonZeroU32> {
+                a
+            }
+
+            #[wasm_bindgen]
+            pub fn maybe64(a: Option<NonZeroU64>, b: u32) -> Option<NonZeroU64> {
+                a.map(|a| NonZeroU64::new(a.get() + b as u64).unwrap())
+            }
+
+            #[wasm_bindgen]
+            pub fn imported() -> u32 {
+                seven().get()
+            }
+
+            #[wasm_bindgen]
+            pub fn imported_zero() -> u32 {
+                zero().get()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function zero(): number {
+                return 0;
+            }
+
+            export function seven(): number {
+                return 7;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.next(1), 2);
+                assert.strictEqual(wasm.next(0xfffffffe), 0xffffffff);
+                assert.strictEqual(wasm.next64(BigInt(4)), BigInt(5));
+                assert.strictEqual(wasm.len(3), 3);
+                assert.strictEqual(wasm.maybe(9), 9);
+                assert.strictEqual(wasm.maybe(undefined), undefined);
+                assert.strictEqual(wasm.maybe64(BigInt("1099511627776"), 1), BigInt("1099511627777"));
+                assert.strictEqual(wasm.maybe64(null, 1), undefined);
+
+                assert.throws(() => wasm.next(0), RangeError);
+                assert.throws(() => wasm.next(0), /expected a non-zero number at index 0/);
+                assert.throws(() => wasm.next64(BigInt(0)), RangeError);
+                assert.throws(() => wasm.next64(0), RangeError);
+                assert.throws(() => wasm.len(0), RangeError);
+                assert.throws(() => wasm.maybe(0), RangeError);
+                assert.throws(() => wasm.maybe64(BigInt(0), 1), RangeError);
+
+                assert.strictEqual(wasm.imported(), 7);
+                assert.throws(() => wasm.imported_zero(), /expected a non-zero value for `NonZeroU32`/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn zero_sized_types() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::marker::PhantomData;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn imported(a: u32, b: (), c: u32) -> u32;
+            }
+
+            #[wasm_bindgen]
+            pub fn unit(x: ()) -> u32 {
+                let () = x;
+                3
+            }
+
+            #[wasm_bindgen]
+            pub fn between(a: u32, _b: PhantomData<String>, c: u32) -> u32 {
+                a * 10 + c
+            }
+
+            #[wasm_bindgen]
+            pub fn phantom() -> PhantomData<u8> {
+                PhantomData
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(imported(1, (), 2), 12);
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import * as bg from "./out_bg";
+
+            export function imported(a: number, b: any, c: number): number {
+                assert.strictEqual(b, undefined);
+                return a * 10 + c;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.unit(undefined), 3);
+                assert.strictEqual(wasm.between(1, undefined, 2), 12);
+                assert.strictEqual(wasm.phantom(), undefined);
+                wasm.run();
+
+                // Zero-sized arguments don't take up a wasm argument, only
+                // the others and the frame do
+                assert.strictEqual(bg.unit.length, 1);
+                assert.strictEqual(bg.between.length, 3);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn narrow_integers() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add_u8(a: u8, b: u8) -> u8 {
+                a.wrapping_add(b)
+            }
+
+            #[wasm_bindgen]
+            pub fn neg_i8(a: i8) -> i8 {
+                a.wrapping_neg()
+            }
+
+            #[wasm_bindgen]
+            pub fn double_u16(a: u16) -> u16 {
+                a.wrapping_mul(2)
+            }
+
+            #[wasm_bindgen]
+            pub fn neg_i16(a: i16) -> i16 {
+                a.wrapping_neg()
+            }
+
+            #[wasm_bindgen]
+            pub fn maybe_u8(a: Option<u8>) -> Option<u8> {
+                a.map(|a| a / 2)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.add_u8(200, 55), 255);
+                assert.strictEqual(wasm.add_u8(200, 56), 0);
+                assert.strictEqual(wasm.neg_i8(-127), 127);
+                assert.strictEqual(wasm.neg_i8(5), -5);
+                assert.strictEqual(wasm.double_u16(40000), 14464);
+                assert.strictEqual(wasm.neg_i16(-32768), -32768);
+                assert.strictEqual(wasm.maybe_u8(9), 4);
+                assert.strictEqual(wasm.maybe_u8(undefined), undefined);
+
+                assert.throws(() => wasm.add_u8(1, 300), RangeError);
+                assert.throws(() => wasm.add_u8(1, 300),
+                              /expected an integer from 0 to 255 at index 1, found 300/);
+                assert.throws(() => wasm.add_u8(-1, 1), RangeError);
+                assert.throws(() => wasm.add_u8(1.5, 1), RangeError);
+                assert.throws(() => wasm.neg_i8(128), /from -128 to 127 at index 0/);
+                assert.throws(() => wasm.double_u16(65536), /from 0 to 65535/);
+                assert.throws(() => wasm.neg_i16(-32769), /from -32768 to 32767/);
+                assert.throws(() => wasm.maybe_u8(256), RangeError);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn narrow_integers_unchecked() {
+    test_support::project()
+        .debug(false)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn id_u8(a: u8) -> u8 {
+                a
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                // Without range checks the number is truncated like an `as`
+                // cast in Rust
+                assert.strictEqual(wasm.id_u8(300), 44);
+            }
+        "#)
+        .test();
+}
+
 #[test]
 fn string_arguments() {
     test_support::project()
@@ -64,45 +495,557 @@ fn string_arguments() {
             use wasm_bindgen::prelude::*;
 
             #[wasm_bindgen]
-            pub fn assert_foo_and_bar(a: &str, b: &str) {
-                assert_eq!(a, "foo2");
-                assert_eq!(b, "bar");
+            pub fn assert_foo_and_bar(a: &str, b: &str) {
+                assert_eq!(a, "foo2");
+                assert_eq!(b, "bar");
+            }
+
+            #[wasm_bindgen]
+            pub fn assert_foo(a: &str) {
+                assert_eq!(a, "foo");
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+
+            export function test() {
+                wasm.assert_foo("foo");
+                wasm.assert_foo_and_bar("foo2", "bar");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn cow_string_arguments() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::borrow::Cow;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn cow_len(a: Cow<str>, b: Cow<str>) -> usize {
+                assert!(match a { Cow::Borrowed(_) => true, Cow::Owned(_) => false });
+                a.len() + b.len()
+            }
+
+            #[wasm_bindgen]
+            pub fn cow_shout(a: Cow<str>) -> String {
+                a.into_owned().to_uppercase()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                assert.strictEqual(wasm.cow_len("foo", "ba"), 5);
+                assert.strictEqual(wasm.cow_len("", ""), 0);
+                assert.strictEqual(wasm.cow_len("\u{1f600}", ""), 4);
+                assert.strictEqual(wasm.cow_shout("hi"), "HI");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn return_a_string() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn clone(a: &str) -> String {
+                a.to_string()
+            }
+
+            #[wasm_bindgen]
+            pub fn concat(a: &str, b: &str, c: i8) -> String {
+                format!("{} {} {}", a, b, c)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.clone("foo"), "foo");
+                assert.strictEqual(wasm.clone("another"), "another");
+                assert.strictEqual(wasm.concat("a", "b", 3), "a b 3");
+                assert.strictEqual(wasm.concat("c", "d", -2), "c d -2");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn returned_strings_are_freed() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn big_string(n: u32) -> String {
+                "a".repeat(n as usize)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_bg";
+
+            export function test() {
+                const n = 1 << 20;
+                assert.strictEqual(wasm.big_string(n).length, n);
+                const size = memory.buffer.byteLength;
+                for (let i = 0; i < 100; i++) {
+                    assert.strictEqual(wasm.big_string(n).length, n);
+                }
+                assert.strictEqual(memory.buffer.byteLength, size);
+                assert.strictEqual(wasm.big_string(0), "");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn exceptions() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn foo(_a: u32) {}
+
+            #[wasm_bindgen]
+            pub fn bar(_a: &str) {}
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.throws(() => wasm.foo('a'), /expected a number argument/);
+                assert.throws(() => wasm.bar(3), /expected a string argument/);
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+#[test]
+fn argument_type_checks() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {}
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo {}
+                }
+
+                pub fn take(&self, _a: &str, _b: &Foo) {}
+            }
+
+            #[wasm_bindgen]
+            pub fn mixed(_a: u32, _b: bool, _c: String, _d: Option<f64>, _e: i64) {}
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const m: any = wasm.mixed;
+                m(1, true, "a", undefined, 2);
+                assert.throws(() => m("1", true, "a", undefined, 2),
+                              /expected a number argument at index 0/);
+                assert.throws(() => m(1, 1, "a", undefined, 2),
+                              /expected a boolean argument at index 1/);
+                assert.throws(() => m(1, true, 3, undefined, 2),
+                              /expected a string argument at index 2/);
+                assert.throws(() => m(1, true, "a", "b", 2),
+                              /expected a number argument at index 3/);
+                assert.throws(() => m(1, true, "a", null, 0.5),
+                              /expected a BigInt or safe integer argument at index 4/);
+
+                const foo: any = wasm.Foo.new();
+                foo.take("a", foo);
+                assert.throws(() => foo.take(null, foo), /expected a string argument at index 0/);
+                assert.throws(() => foo.take("a", {}), /expected instance of Foo at index 1/);
+                foo.free();
+            }
+        "#)
+        .test();
+}
+
+// #[test]
+// fn other_imports() {
+//     test_support::project()
+//         .file("src/lib.rs", r#"
+//             #![feature(proc_macro, wasm_custom_section)]
+//
+//             extern crate wasm_bindgen;
+//
+//             use wasm_bindgen::prelude::*;
+//
+//             extern {
+//                 fn another_import(a: u32);
+//             }
+//
+//             wasm_bindgen! {
+//                 pub fn foo(a: u32) {
+//                     unsafe { another_import(a); }
+//                 }
+//             }
+//         "#)
+//         .file("test.ts", r#"
+//             import * as assert from "assert";
+//             import * as wasm from "./out";
+//
+//             let ARG: number | null = null;
+//
+//             export function test() {
+//                 wasm.foo(2);
+//                 assert.strictEqual(ARG, 2);
+//             }
+//         "#)
+//         .test();
+// }
+
+#[test]
+fn other_exports() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #[no_mangle]
+            pub extern fn foo(_a: u32) {
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out_bg";
+
+            export function test() {
+                wasm.foo(2);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn rename_exports() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(js_name = doThing)]
+            pub fn do_thing() -> u32 {
+                1
+            }
+
+            #[wasm_bindgen(js_name = "doOtherThing")]
+            pub fn do_other_thing() -> u32 {
+                do_thing() + 1
+            }
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { contents: 3 }
+                }
+
+                #[wasm_bindgen(js_name = getContents)]
+                pub fn get_contents(&self) -> u32 {
+                    self.contents
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.doThing(), 1);
+                assert.strictEqual(wasm.doOtherThing(), 2);
+                assert.strictEqual((wasm as any).do_thing, undefined);
+                assert.strictEqual((wasm as any).do_other_thing, undefined);
+
+                const foo = wasm.Foo.new();
+                assert.strictEqual(foo.getContents(), 3);
+                assert.strictEqual((foo as any).get_contents, undefined);
+                foo.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn js_name_not_an_identifier() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn foo_bar() -> u32 {
+                1
+            }
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { contents: 3 }
+                }
+
+                #[wasm_bindgen(js_name = "get-contents")]
+                pub fn get_contents(&self) -> u32 {
+                    self.contents
+                }
+
+                #[wasm_bindgen(js_name = bar)]
+                pub fn two() -> u32 {
+                    2
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.foo_bar(), 1);
+                assert.strictEqual(wasm.Foo.bar(), 2);
+
+                const foo = wasm.Foo.new();
+                assert.strictEqual(foo['get-contents'](), 3);
+                foo.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn chars() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn js_identity(c: char) -> char;
+            }
+
+            #[wasm_bindgen]
+            pub fn identity(c: char) -> char {
+                c
+            }
+
+            #[wasm_bindgen]
+            pub fn code(c: char) -> u32 {
+                c as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn crab() -> char {
+                '🦀'
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                assert_eq!(js_identity('a'), 'a');
+                assert_eq!(js_identity('🦀'), '🦀');
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function js_identity(c: string): string {
+                assert.strictEqual(typeof(c), 'string');
+                return c;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.identity('a'), 'a');
+                assert.strictEqual(wasm.identity('é'), 'é');
+                assert.strictEqual(wasm.code('a'), 97);
+                assert.strictEqual(wasm.code('🦀'), 0x1f980);
+                assert.strictEqual(wasm.crab(), '🦀');
+                assert.strictEqual(wasm.identity('🦀'), '🦀');
+
+                assert.throws(() => wasm.identity(''), /single Unicode scalar value/);
+                assert.throws(() => wasm.identity('ab'), /single Unicode scalar value/);
+                assert.throws(() => wasm.identity('\ud83e'), /single Unicode scalar value/);
+                assert.throws(() => wasm.identity('\udd80'), /single Unicode scalar value/);
+
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn argument_names() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn weighted(function: u32, _: u32, mut count: u32, arg1: u32) -> u32 {
+                count += arg1;
+                function * count
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.weighted(2, 100, 3, 4), 14);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn packed_bools() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn flags(a: bool, b: bool, c: bool, d: bool, e: bool,
+                         f: bool, g: bool, h: bool, n: u32, last: bool) -> u32 {
+                let bits = [a, b, c, d, e, f, g, h];
+                let mut ret = 0;
+                for (i, &bit) in bits.iter().enumerate() {
+                    if bit {
+                        ret |= 1 << i;
+                    }
+                }
+                ret + n * if last { 1000 } else { 0 }
             }
 
             #[wasm_bindgen]
-            pub fn assert_foo(a: &str) {
-                assert_eq!(a, "foo");
+            pub fn single(a: bool) -> bool {
+                !a
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                n: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                pub fn new() -> Counter {
+                    Counter { n: 0 }
+                }
+
+                pub fn bump(&mut self, twice: bool, skip: bool) -> u32 {
+                    if !skip {
+                        self.n += if twice { 2 } else { 1 };
+                    }
+                    self.n
+                }
             }
         "#)
         .file("test.ts", r#"
+            import * as assert from "assert";
             import * as wasm from "./out";
 
             export function test() {
-                wasm.assert_foo("foo");
-                wasm.assert_foo_and_bar("foo2", "bar");
+                assert.strictEqual(wasm.flags(true, false, false, false, false, false, false, false, 0, false), 1);
+                assert.strictEqual(wasm.flags(false, true, false, true, false, false, false, true, 0, false), 0x8a);
+                assert.strictEqual(wasm.flags(true, true, true, true, true, true, true, true, 2, true), 2255);
+                assert.strictEqual(wasm.single(false), true);
+
+                const c = wasm.Counter.new();
+                assert.strictEqual(c.bump(false, false), 1);
+                assert.strictEqual(c.bump(true, false), 3);
+                assert.strictEqual(c.bump(true, true), 3);
+                c.free();
             }
         "#)
         .test();
 }
 
 #[test]
-fn return_a_string() {
+fn start() {
     test_support::project()
         .file("src/lib.rs", r#"
             #![feature(proc_macro, wasm_custom_section)]
 
             extern crate wasm_bindgen;
 
+            use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
             use wasm_bindgen::prelude::*;
 
-            #[wasm_bindgen]
-            pub fn clone(a: &str) -> String {
-                a.to_string()
+            static STARTED: AtomicUsize = ATOMIC_USIZE_INIT;
+
+            #[wasm_bindgen(start)]
+            pub fn main() {
+                STARTED.fetch_add(1, Ordering::SeqCst);
             }
 
             #[wasm_bindgen]
-            pub fn concat(a: &str, b: &str, c: i8) -> String {
-                format!("{} {} {}", a, b, c)
+            pub fn times_started() -> u32 {
+                STARTED.load(Ordering::SeqCst) as u32
             }
         "#)
         .file("test.ts", r#"
@@ -110,18 +1053,17 @@ fn return_a_string() {
             import * as wasm from "./out";
 
             export function test() {
-                assert.strictEqual(wasm.clone("foo"), "foo");
-                assert.strictEqual(wasm.clone("another"), "another");
-                assert.strictEqual(wasm.concat("a", "b", 3), "a b 3");
-                assert.strictEqual(wasm.concat("c", "d", -2), "c d -2");
+                assert.strictEqual(wasm.times_started(), 1);
+                assert.strictEqual((wasm as any).main, undefined);
             }
         "#)
         .test();
 }
 
 #[test]
-fn exceptions() {
+fn arity_checks() {
     test_support::project()
+        .check_arity(true)
         .file("src/lib.rs", r#"
             #![feature(proc_macro, wasm_custom_section)]
 
@@ -130,18 +1072,29 @@ fn exceptions() {
             use wasm_bindgen::prelude::*;
 
             #[wasm_bindgen]
-            pub fn foo(_a: u32) {}
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
 
             #[wasm_bindgen]
-            pub fn bar(_a: &str) {}
+            pub fn add_maybe(a: u32, b: Option<u32>, c: Option<u32>) -> u32 {
+                a + b.unwrap_or(0) + c.unwrap_or(0)
+            }
         "#)
         .file("test.js", r#"
             import * as assert from "assert";
             import * as wasm from "./out";
 
             export function test() {
-                assert.throws(() => wasm.foo('a'), /expected a number argument/);
-                assert.throws(() => wasm.bar(3), /expected a string argument/);
+                assert.strictEqual(wasm.add(1, 2), 3);
+                assert.throws(() => wasm.add(1), /add expects 2 argument\(s\) but got 1/);
+                assert.throws(() => wasm.add(1, 2, 3), TypeError);
+
+                assert.strictEqual(wasm.add_maybe(1), 1);
+                assert.strictEqual(wasm.add_maybe(1, 2), 3);
+                assert.strictEqual(wasm.add_maybe(1, 2, 3), 6);
+                assert.throws(() => wasm.add_maybe(), /add_maybe expects 1 to 3 argument\(s\) but got 0/);
+                assert.throws(() => wasm.add_maybe(1, 2, 3, 4), TypeError);
             }
         "#)
         .file("test.d.ts", r#"
@@ -150,54 +1103,398 @@ fn exceptions() {
         .test();
 }
 
-// #[test]
-// fn other_imports() {
-//     test_support::project()
-//         .file("src/lib.rs", r#"
-//             #![feature(proc_macro, wasm_custom_section)]
-//
-//             extern crate wasm_bindgen;
-//
-//             use wasm_bindgen::prelude::*;
-//
-//             extern {
-//                 fn another_import(a: u32);
-//             }
-//
-//             wasm_bindgen! {
-//                 pub fn foo(a: u32) {
-//                     unsafe { another_import(a); }
-//                 }
-//             }
-//         "#)
-//         .file("test.ts", r#"
-//             import * as assert from "assert";
-//             import * as wasm from "./out";
-//
-//             let ARG: number | null = null;
-//
-//             export function test() {
-//                 wasm.foo(2);
-//                 assert.strictEqual(ARG, 2);
-//             }
-//         "#)
-//         .test();
-// }
+#[test]
+fn tuples() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn pair(a: u32) -> (u32, f64) {
+                (a, a as f64 / 2.0)
+            }
+
+            #[wasm_bindgen]
+            pub fn nested() -> (u32, (f32, f32)) {
+                (1, (2.5, 3.5))
+            }
+
+            #[wasm_bindgen]
+            pub fn mixed(s: &str) -> (String, bool, JsValue) {
+                (s.to_uppercase(), s.is_empty(), JsValue::null())
+            }
+
+            #[wasm_bindgen]
+            pub fn six() -> (u8, u16, u32, i8, i16, i32) {
+                (1, 2, 3, -4, -5, -6)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                assert.deepStrictEqual(wasm.pair(3), [3, 1.5]);
+                assert.deepStrictEqual(wasm.nested(), [1, [2.5, 3.5]]);
+                assert.deepStrictEqual(wasm.mixed("ab"), ["AB", false, null]);
+                assert.deepStrictEqual(wasm.six(), [1, 2, 3, -4, -5, -6]);
+            }
+        "#)
+        .test();
+}
 
 #[test]
-fn other_exports() {
+fn maps() {
     test_support::project()
         .file("src/lib.rs", r#"
-            #[no_mangle]
-            pub extern fn foo(_a: u32) {
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::{BTreeMap, HashMap};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn counts(s: &str) -> HashMap<String, u32> {
+                let mut map = HashMap::new();
+                for word in s.split_whitespace() {
+                    *map.entry(word.to_string()).or_insert(0) += 1;
+                }
+                map
+            }
+
+            #[wasm_bindgen]
+            pub fn sorted() -> BTreeMap<String, JsValue> {
+                let mut map = BTreeMap::new();
+                map.insert("b".to_string(), JsValue::from_str("two"));
+                map.insert("a".to_string(), JsValue::null());
+                map.insert("c".to_string(), (1, true).into());
+                map.insert("__proto__".to_string(), JsValue::from(3));
+                map
             }
         "#)
         .file("test.ts", r#"
-            import * as wasm from "./out_bg";
+            import * as wasm from "./out";
+            import * as assert from "assert";
 
             export function test() {
-                wasm.foo(2);
+                assert.deepStrictEqual(wasm.counts("a b a c a"), { a: 3, b: 1, c: 1 });
+                assert.deepStrictEqual(wasm.counts(""), {});
+
+                const sorted = wasm.sorted();
+                assert.deepStrictEqual(Object.keys(sorted), ["__proto__", "a", "b", "c"]);
+                assert.strictEqual(Object.getPrototypeOf(sorted), Object.prototype);
+                assert.strictEqual(sorted.a, null);
+                assert.strictEqual(sorted.b, "two");
+                assert.deepStrictEqual(sorted.c, [1, true]);
+                assert.strictEqual(Object.getOwnPropertyDescriptor(sorted, "__proto__").value, 3);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn exported_iterators() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use std::cell::Cell;
+
+            use wasm_bindgen::prelude::*;
+
+            thread_local!(static DROPPED: Cell<u32> = Cell::new(0));
+
+            struct Counted(u32);
+
+            impl Iterator for Counted {
+                type Item = u32;
+
+                fn next(&mut self) -> Option<u32> {
+                    self.0 += 1;
+                    Some(self.0)
+                }
+            }
+
+            impl Drop for Counted {
+                fn drop(&mut self) {
+                    DROPPED.with(|d| d.set(d.get() + 1));
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn squares(n: u32) -> impl Iterator<Item = u32> {
+                (0..n).map(|i| i * i)
+            }
+
+            #[wasm_bindgen]
+            pub fn words(s: String) -> impl Iterator<Item = String> {
+                s.split(' ').map(|w| w.to_string()).collect::<Vec<_>>().into_iter()
+            }
+
+            #[wasm_bindgen]
+            pub fn forever() -> impl Iterator<Item = u32> {
+                Counted(0)
+            }
+
+            #[wasm_bindgen]
+            pub fn dropped() -> u32 {
+                DROPPED.with(|d| d.get())
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            export function test() {
+                assert.deepStrictEqual([...wasm.squares(4)], [0, 1, 4, 9]);
+                assert.deepStrictEqual(Array.from(wasm.words("a bc d")), ["a", "bc", "d"]);
+
+                const empty = wasm.squares(0);
+                assert.deepStrictEqual(empty.next(), { value: undefined, done: true });
+                assert.deepStrictEqual(empty.next(), { value: undefined, done: true });
+
+                let seen = 0;
+                for (const i of wasm.forever()) {
+                    seen = i;
+                    if (i === 3)
+                        break;
+                }
+                assert.strictEqual(seen, 3);
+                assert.strictEqual(wasm.dropped(), 1);
+
+                const it = wasm.forever();
+                assert.strictEqual(it.next().value, 1);
+                it.return();
+                assert.strictEqual(wasm.dropped(), 2);
+                assert.strictEqual(it.next().done, true);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn shared_conversions() {
+    test_support::project()
+        .share_conversions(true)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn first(a: Option<u8>, b: u8) -> Option<String> {
+                a.map(|a| format!("{}", a + b))
+            }
+
+            #[wasm_bindgen]
+            pub fn second(b: u8, a: Option<u8>) -> Option<String> {
+                a.map(|a| format!("{}", a * b))
+            }
+
+            #[wasm_bindgen]
+            pub fn numbers(n: u32) -> Vec<u32> {
+                (0..n).collect()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.first(1, 2), "3");
+                assert.strictEqual(wasm.first(undefined, 2), null);
+                assert.strictEqual(wasm.second(2, 3), "6");
+                assert.strictEqual(wasm.second(2, null), null);
+                assert.deepStrictEqual(Array.from(wasm.numbers(3)), [0, 1, 2]);
+
+                // Shared checks still report which argument was wrong
+                assert.throws(() => wasm.first(300, 2), /from 0 to 255 at index 0/);
+                assert.throws(() => wasm.second(2, 300), /from 0 to 255 at index 1/);
+                assert.throws(() => wasm.second(2, 'a' as any), /number argument at index 1/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn shared_conversions_size() {
+    // Many exports with the same signatures, each of which needs checks of
+    // its arguments and a conversion of its return value
+    let mut lib = String::from(r#"
+        #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+        extern crate wasm_bindgen;
+
+        use wasm_bindgen::prelude::*;
+    "#);
+    for i in 0..20 {
+        lib.push_str(&format!(r#"
+            #[wasm_bindgen]
+            pub fn f{}(a: Option<u8>, b: Option<i16>) -> Option<String> {{
+                a.and(b).map(|b| b.to_string())
+            }}
+        "#, i));
+    }
+
+    let mut project = test_support::project();
+    project.file("src/lib.rs", &lib);
+    let inline = project.generated_js();
+    let shared = project.share_conversions(true).generated_js();
+
+    // The sizes are printed so the saving can be seen with `--nocapture`
+    println!("inline conversions: {} bytes", inline.len());
+    println!("shared conversions: {} bytes", shared.len());
+    assert!(shared.contains("function _checkArg"));
+    assert!(shared.contains("function _convertRet"));
+    assert!(shared.len() < inline.len() * 3 / 4,
+            "expected at least a quarter to be saved, went from {} to {} bytes",
+            inline.len(), shared.len());
+}
+
+#[test]
+fn explicit_unit_returns() {
+    let lib = |ret: &str| format!(r#"
+        #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+        extern crate wasm_bindgen;
+
+        use wasm_bindgen::prelude::*;
+
+        #[wasm_bindgen(module = "./test")]
+        extern {{
+            fn hit(a: u32){ret};
+        }}
+
+        #[wasm_bindgen]
+        pub fn run(a: u32){ret} {{
+            hit(a)
+        }}
+
+        #[wasm_bindgen]
+        pub struct Foo {{}}
+
+        #[wasm_bindgen]
+        impl Foo {{
+            pub fn new() -> Foo {{
+                Foo {{}}
+            }}
+
+            pub fn poke(&self){ret} {{
+            }}
+        }}
+    "#, ret = ret);
+
+    // `-> ()` is the same as no return type at all, rather than returning a
+    // `()` which would then go through its own conversion
+    let implicit = test_support::project()
+        .file("src/lib.rs", &lib(""))
+        .generated_js();
+    let explicit = test_support::project()
+        .file("src/lib.rs", &lib(" -> ()"))
+        .generated_js();
+    assert_eq!(implicit, explicit);
+
+    test_support::project()
+        .file("src/lib.rs", &lib(" -> ()"))
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            let hits = 0;
+
+            export function hit(a: number): number {
+                hits += a;
+                return 1;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.run(2), undefined);
+                assert.strictEqual(hits, 2);
+                const foo = wasm.Foo.new();
+                assert.strictEqual(foo.poke(), undefined);
+                foo.free();
             }
         "#)
         .test();
 }
+
+#[test]
+fn frame_overflow() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            // Each optional string takes up two words of the frame
+            #[wasm_bindgen]
+            pub fn too_many(
+                a: Option<String>,
+                b: Option<String>,
+                c: Option<String>,
+                d: Option<String>,
+                e: Option<String>,
+                f: Option<String>,
+                g: Option<String>,
+                h: Option<String>,
+                i: Option<String>,
+            ) {
+                drop((a, b, c, d, e, f, g, h, i));
+            }
+        "#)
+        .test_compile_error("overflow");
+}
+
+#[test]
+fn frame_overflow_import() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn too_many(
+                    a: &str,
+                    b: &str,
+                    c: &str,
+                    d: &str,
+                    e: &str,
+                    f: &str,
+                    g: &str,
+                    h: &str,
+                    i: &str,
+                    j: &str,
+                    k: &str,
+                    l: &str,
+                    m: &str,
+                    n: &str,
+                    o: &str,
+                    p: &str,
+                    q: &str,
+                );
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                too_many("a", "b", "c", "d", "e", "f", "g", "h", "i",
+                         "j", "k", "l", "m", "n", "o", "p", "q");
+            }
+        "#)
+        .test_compile_error("overflow");
+}
@@ -228,3 +228,394 @@ fn import() {
         "#)
         .test();
 }
+
+#[test]
+fn export_mut() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            macro_rules! doit {
+                ($(($i:ident, $name:ident))*) => ($(
+                    #[wasm_bindgen]
+                    pub fn $name(a: &mut [$i]) -> u32 {
+                        for x in a.iter_mut() {
+                            *x = *x * (2 as $i);
+                        }
+                        a.len() as u32
+                    }
+                )*)
+            }
+
+            doit! {
+                (i8, double_i8)
+                (u8, double_u8)
+                (i16, double_i16)
+                (u16, double_u16)
+                (i32, double_i32)
+                (u32, double_u32)
+                (f32, double_f32)
+                (f64, double_f64)
+            }
+
+            #[wasm_bindgen]
+            pub fn fill(a: &mut [u8], b: &mut [u8]) {
+                for x in a.iter_mut() {
+                    *x = 1;
+                }
+                for x in b.iter_mut() {
+                    *x = 2;
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const arrays: [any, (a: any) => number][] = [
+                    [Int8Array, wasm.double_i8],
+                    [Uint8Array, wasm.double_u8],
+                    [Int16Array, wasm.double_i16],
+                    [Uint16Array, wasm.double_u16],
+                    [Int32Array, wasm.double_i32],
+                    [Uint32Array, wasm.double_u32],
+                    [Float32Array, wasm.double_f32],
+                    [Float64Array, wasm.double_f64],
+                ];
+                for (const [Ty, double] of arrays) {
+                    const a = new Ty([1, 2, 3]);
+                    assert.strictEqual(double(a), 3);
+                    assert.deepStrictEqual(Array.from(a), [2, 4, 6]);
+
+                    const empty = new Ty(0);
+                    assert.strictEqual(double(empty), 0);
+                    assert.strictEqual(empty.length, 0);
+
+                    // Only the viewed elements are written back.
+                    const whole = new Ty([1, 2, 3, 4]);
+                    assert.strictEqual(double(whole.subarray(1, 3)), 2);
+                    assert.deepStrictEqual(Array.from(whole), [1, 4, 6, 4]);
+                }
+
+                // Two views of one buffer are copied in and out separately.
+                const buf = new Uint8Array(4);
+                wasm.fill(buf.subarray(0, 2), buf.subarray(2, 4));
+                assert.deepStrictEqual(Array.from(buf), [1, 1, 2, 2]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn strings() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section, wasm_import_module)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn js_reverse(a: Vec<String>) -> Vec<String>;
+            }
+
+            #[wasm_bindgen]
+            pub fn words(a: &str) -> Vec<String> {
+                a.split(' ').map(|s| s.to_string()).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn join(a: Vec<String>) -> String {
+                a.join("-")
+            }
+
+            #[wasm_bindgen]
+            pub fn run() {
+                let a = vec!["a".to_string(), String::new(), "ü".to_string()];
+                assert_eq!(js_reverse(a), ["ü", "", "a"]);
+                assert_eq!(js_reverse(Vec::new()), Vec::<String>::new());
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function js_reverse(a: string[]): string[] {
+                assert.strictEqual(Array.isArray(a), true);
+                return a.reverse();
+            }
+
+            export function test() {
+                assert.deepStrictEqual(wasm.words("foo bar baz"), ["foo", "bar", "baz"]);
+                assert.deepStrictEqual(wasm.words(""), [""]);
+                assert.strictEqual(wasm.join(["a", "b", "c"]), "a-b-c");
+                assert.strictEqual(wasm.join([]), "");
+                wasm.run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_strings() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn join(a: &[&str], sep: &str) -> String {
+                a.join(sep)
+            }
+
+            #[wasm_bindgen]
+            pub fn total_len(a: &[&str]) -> u32 {
+                a.iter().map(|s| s.len() as u32).sum()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.join(["a", "", "ü"], "-"), "a--ü");
+                assert.strictEqual(wasm.join([], "-"), "");
+
+                const many: string[] = [];
+                for (let i = 0; i < 10000; i++)
+                    many.push("ab");
+                assert.strictEqual(wasm.total_len(many), 20000);
+                assert.strictEqual(wasm.total_len(many), 20000);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn fixed_size_arrays() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn hash(a: &[u8]) -> [u8; 32] {
+                let mut ret = [0; 32];
+                for (i, b) in a.iter().enumerate() {
+                    ret[i % 32] ^= *b;
+                }
+                ret
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(a: [f64; 3]) -> f64 {
+                a[0] + a[1] + a[2]
+            }
+
+            #[wasm_bindgen]
+            pub fn swap(a: [u16; 2]) -> [u16; 2] {
+                [a[1], a[0]]
+            }
+
+            #[wasm_bindgen]
+            pub fn nothing(a: [u32; 0]) -> [u32; 0] {
+                a
+            }
+
+            #[wasm_bindgen]
+            pub fn label(name: &str, a: [i32; 2]) -> String {
+                format!("{}: {}, {}", name, a[0], a[1])
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const h = wasm.hash(new Uint8Array([1, 2, 3]));
+                assert.strictEqual(h.length, 32);
+                assert.deepStrictEqual(Array.from(h.subarray(0, 4)), [1, 2, 3, 0]);
+
+                assert.strictEqual(wasm.sum(new Float64Array([1, 2, 3.5])), 6.5);
+                assert.deepStrictEqual(Array.from(wasm.swap(new Uint16Array([1, 2]))), [2, 1]);
+                assert.strictEqual(wasm.nothing(new Uint32Array(0)).length, 0);
+
+                assert.throws(() => wasm.sum(new Float64Array([1, 2])),
+                              /expected an array of length 3, found 2/);
+                assert.throws(() => wasm.nothing(new Uint32Array(1)),
+                              /expected an array of length 0, found 1/);
+
+                assert.strictEqual(wasm.label("x", new Int32Array([1, -2])), "x: 1, -2");
+                assert.throws(() => wasm.label("x", new Int32Array(3)),
+                              /expected an array of length 2, found 3/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn boxed_slices() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn reversed(a: Box<[u8]>) -> Box<[u8]> {
+                let mut a = a.into_vec();
+                a.reverse();
+                a.into_boxed_slice()
+            }
+
+            #[wasm_bindgen]
+            pub fn capacity(a: Vec<f64>) -> u32 {
+                assert_eq!(a.capacity(), a.len());
+                a.capacity() as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn with_slack(n: u32) -> Box<[u32]> {
+                let mut v = Vec::with_capacity(100);
+                v.extend(0..n);
+                v.into_boxed_slice()
+            }
+
+            #[wasm_bindgen]
+            pub fn values(n: u32) -> Box<[JsValue]> {
+                (0..n).map(JsValue::from).collect::<Vec<_>>().into_boxed_slice()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_bg";
+
+            export function test() {
+                const r = wasm.reversed(new Uint8Array([1, 2, 3]));
+                assert.ok(r instanceof Uint8Array);
+                assert.deepStrictEqual(Array.from(r), [3, 2, 1]);
+                assert.strictEqual(wasm.reversed(new Uint8Array(0)).length, 0);
+
+                assert.strictEqual(wasm.capacity(new Float64Array([1, 2, 3])), 3);
+                assert.strictEqual(wasm.capacity(new Float64Array(0)), 0);
+
+                assert.deepStrictEqual(Array.from(wasm.with_slack(4)), [0, 1, 2, 3]);
+                const size = memory.buffer.byteLength;
+                for (let i = 0; i < 10000; i++) {
+                    wasm.with_slack(50);
+                }
+                assert.strictEqual(memory.buffer.byteLength, size);
+
+                assert.deepStrictEqual(wasm.values(3), [0, 1, 2]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn borrowed_f64_views() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro, wasm_custom_section)]
+
+            extern crate wasm_bindgen;
+
+            use std::mem;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn alloc(n: u32) -> u32 {
+                let mut v = vec![0f64; n as usize];
+                let ptr = v.as_mut_ptr();
+                mem::forget(v);
+                ptr as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn address(a: &[f64]) -> u32 {
+                a.as_ptr() as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn sum_after(s: String, a: &[f64]) -> f64 {
+                s.len() as f64 + a.iter().sum::<f64>()
+            }
+
+            #[wasm_bindgen]
+            pub fn address_with_mut(a: &[f64], b: &mut [f64]) -> u32 {
+                b[0] = a[0];
+                a.as_ptr() as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn second_address(a: &[f64], b: &[f64]) -> u32 {
+                assert_eq!(a.len(), 1024 * 1024);
+                b.as_ptr() as u32
+            }
+
+            #[wasm_bindgen]
+            pub fn sum_growing(a: &[f64]) -> f64 {
+                let grown = vec![1u8; 4 * 1024 * 1024];
+                grown.len() as f64 + a.iter().sum::<f64>()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_bg";
+
+            export function test() {
+                const ptr = wasm.alloc(3);
+                const view = new Float64Array(memory.buffer, ptr, 3);
+                view.set([1, 2, 3]);
+                assert.strictEqual(wasm.address(view), ptr);
+                assert.notStrictEqual(wasm.address(new Float64Array([1, 2, 3])), ptr);
+                assert.notStrictEqual(wasm.address(view.subarray(1)), ptr);
+                assert.strictEqual(wasm.address(view.subarray(1)), ptr + 8);
+
+                // Passing the string grows the memory before Rust reads the
+                // slice, which must still be found in place
+                const s = 'x'.repeat(4 * 1024 * 1024);
+                const view2 = new Float64Array(memory.buffer, ptr, 3);
+                assert.strictEqual(wasm.sum_after(s, view2), s.length + 6);
+
+                const out = new Float64Array(1);
+                const view3 = new Float64Array(memory.buffer, ptr, 3);
+                assert.notStrictEqual(wasm.address_with_mut(view3, out), ptr);
+                assert.strictEqual(out[0], 1);
+
+                // Copying the first argument grows the memory, which mustn't
+                // stop the second from being found in place
+                const view4 = new Float64Array(memory.buffer, ptr, 3);
+                assert.strictEqual(wasm.second_address(new Float64Array(1024 * 1024), view4), ptr);
+
+                // Growing the memory during the call leaves the slice in place
+                const view5 = new Float64Array(memory.buffer, ptr, 3);
+                assert.strictEqual(wasm.sum_growing(view5), 4 * 1024 * 1024 + 6);
+
+                // Views at the very start of the memory are copied, as Rust
+                // can't be given a null pointer, while empty ones are fine
+                assert.notStrictEqual(wasm.address(new Float64Array(memory.buffer, 0, 1)), 0);
+                assert.strictEqual(wasm.sum_growing(new Float64Array(memory.buffer, 0, 0)),
+                                   4 * 1024 * 1024);
+            }
+        "#)
+        .test();
+}